@@ -0,0 +1,103 @@
+/*
+ * batch_lookup.rs
+ *
+ * Benchmark resolving a large ARP-table-sized batch of MAC addresses.
+ *
+ * OVERVIEW:
+ * Compares running lookup_oui once per address (a full CSV scan each time)
+ * against loading an OuiDatabase once and hashing every address, on one
+ * thread and spread across rayon's default pool as --threads does. Every
+ * path runs over the same deterministic fixture so results are comparable
+ * between runs and machines.
+ */
+
+use std::fs;
+use std::hint::black_box;
+use std::path::PathBuf;
+
+mod common;
+
+use common::{Lcg, write_database};
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use oui::{OuiDatabase, lookup_oui, parse_mac};
+use rayon::prelude::*;
+
+const DB_ROWS: usize = 2_000; /* Rows in the generated database */
+const BATCH_SIZE: usize = 5_000; /* Roughly the ARP cache of a large subnet */
+
+/*
+ * write_fixture - Generate the database file and the batch of addresses
+ *
+ * Every other address reuses a prefix from the database so the batch mixes
+ * hits and misses, like a real network with some unregistered devices.
+ *
+ * Return: Path to the generated CSV and the list of MAC strings
+ */
+fn write_fixture() -> (PathBuf, Vec<String>) {
+    let mut rng = Lcg(0x5EED);
+    let (path, ouis) = write_database("batch", DB_ROWS, &mut rng);
+
+    let macs = (0..BATCH_SIZE)
+        .map(|i| {
+            let oui = if i % 2 == 0 {
+                ouis[i % DB_ROWS]
+            } else {
+                rng.next() & 0xFF_FFFF
+            };
+            format!("{:06X}{:06X}", oui, rng.next() & 0xFF_FFFF)
+        })
+        .collect();
+
+    (path, macs)
+}
+
+fn bench_batch(c: &mut Criterion) {
+    let (path, macs) = write_fixture();
+    let csv_path = path.to_str().expect("temp path is not UTF-8");
+
+    let mut group = c.benchmark_group("batch_lookup");
+    group.throughput(Throughput::Elements(macs.len() as u64));
+    group.sample_size(10);
+
+    group.bench_function("per_mac_scan", |b| {
+        b.iter(|| {
+            for mac in &macs {
+                let mac = parse_mac(mac).unwrap();
+                black_box(lookup_oui(csv_path, &mac.oui).unwrap());
+            }
+        })
+    });
+
+    group.bench_function("indexed", |b| {
+        b.iter(|| {
+            let db = OuiDatabase::open(csv_path).unwrap();
+            for mac in &macs {
+                let mac = parse_mac(mac).unwrap();
+                black_box(db.lookup(&mac.oui));
+            }
+        })
+    });
+
+    /* The load is shared, so this isolates how well the lookups scale */
+    let db = OuiDatabase::open(csv_path).unwrap();
+    group.bench_function("lookups_serial", |b| {
+        b.iter(|| {
+            macs.iter()
+                .map(|mac| db.lookup(&parse_mac(mac).unwrap().oui))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("lookups_parallel", |b| {
+        b.iter(|| {
+            macs.par_iter()
+                .map(|mac| db.lookup(&parse_mac(mac).unwrap().oui))
+                .collect::<Vec<_>>()
+        })
+    });
+
+    group.finish();
+    let _ = fs::remove_file(&path);
+}
+
+criterion_group!(benches, bench_batch);
+criterion_main!(benches);
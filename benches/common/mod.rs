@@ -0,0 +1,47 @@
+/*
+ * common/mod.rs
+ *
+ * Deterministic fixtures shared by the benchmarks.
+ */
+
+use std::fs;
+use std::path::PathBuf;
+
+/*
+ * Lcg - Tiny linear congruential generator
+ *
+ * The fixtures only need to look random, not be random, and a fixed seed
+ * keeps them identical across runs without pulling in a rand dependency.
+ */
+pub struct Lcg(pub u64);
+
+impl Lcg {
+    pub fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 16
+    }
+}
+
+/*
+ * write_database - Generate a database file with the given number of rows
+ * @name: Distinguishes fixtures written by the same benchmark
+ * @rows: Number of OUI rows to generate
+ * @rng: Generator the OUIs are drawn from
+ *
+ * Return: Path to the generated CSV and the OUIs it contains, in file order
+ */
+pub fn write_database(name: &str, rows: usize, rng: &mut Lcg) -> (PathBuf, Vec<u64>) {
+    let ouis: Vec<u64> = (0..rows).map(|_| rng.next() & 0xFF_FFFF).collect();
+    let csv: String = ouis
+        .iter()
+        .enumerate()
+        .map(|(i, oui)| format!("{:06X};Vendor {}\n", oui, i))
+        .collect();
+
+    let path = std::env::temp_dir().join(format!("oui-bench-{}-{}.csv", name, std::process::id()));
+    fs::write(&path, csv).expect("failed to write benchmark fixture");
+    (path, ouis)
+}
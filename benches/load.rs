@@ -0,0 +1,49 @@
+/*
+ * load.rs
+ *
+ * Benchmark loading a large database with buffered reads versus a memory map.
+ *
+ * OVERVIEW:
+ * Both loaders build the same HashMap; the only difference is how the file
+ * bytes reach the CSV parser. The fixture is sized like a merged database
+ * of several registries so the difference is visible.
+ */
+
+mod common;
+
+use std::fs;
+use std::hint::black_box;
+
+use common::{Lcg, write_database};
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use oui::{load_oui_map, load_oui_map_mmap};
+
+const DB_ROWS: usize = 500_000; /* Rows in the generated database */
+
+fn bench_load(c: &mut Criterion) {
+    let (path, _) = write_database("load", DB_ROWS, &mut Lcg(0x10AD));
+    let csv_path = path.to_str().expect("temp path is not UTF-8");
+
+    /* Both readers must agree before their timings mean anything */
+    assert_eq!(
+        load_oui_map(csv_path).unwrap(),
+        load_oui_map_mmap(csv_path).unwrap()
+    );
+
+    let mut group = c.benchmark_group("load");
+    group.throughput(Throughput::Bytes(fs::metadata(&path).unwrap().len()));
+    group.sample_size(10);
+
+    group.bench_function("buffered", |b| {
+        b.iter(|| black_box(load_oui_map(csv_path).unwrap()))
+    });
+    group.bench_function("mmap", |b| {
+        b.iter(|| black_box(load_oui_map_mmap(csv_path).unwrap()))
+    });
+
+    group.finish();
+    let _ = fs::remove_file(&path);
+}
+
+criterion_group!(benches, bench_load);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+/*
+ * single_lookup.rs
+ *
+ * Benchmark one lookup from a cold start, scanning versus building an index.
+ *
+ * OVERVIEW:
+ * A one-off `oui <MAC>` scans the CSV and stops at the first match, while
+ * --mmap and the other indexed modes parse the whole file into an
+ * OuiDatabase first and then hash the OUI. Both are timed end to end at
+ * several database sizes, to show where building an index stops costing
+ * more than it saves for a single address.
+ *
+ * The target is the row halfway down the file, the average position of a
+ * hit for the scan. A miss reads every row either way, so it is timed too.
+ */
+
+use std::fs;
+use std::hint::black_box;
+
+mod common;
+
+use common::{Lcg, write_database};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use oui::{OuiDatabase, lookup_oui};
+
+const DB_SIZES: [usize; 3] = [1_000, 10_000, 50_000]; /* Rows in each generated database */
+
+fn bench_single(c: &mut Criterion) {
+    let mut group = c.benchmark_group("single_lookup");
+    group.sample_size(20);
+
+    for rows in DB_SIZES {
+        let (path, ouis) = write_database(&format!("single-{}", rows), rows, &mut Lcg(0x0FF1CE));
+        let csv_path = path.to_str().expect("temp path is not UTF-8");
+
+        /* Generated OUIs can repeat, so take the first row holding the middle one */
+        let hit = format!("{:06X}", ouis[rows / 2]);
+        let miss = (0..=0xFF_FFFF)
+            .find(|oui| !ouis.contains(oui))
+            .map(|oui| format!("{:06X}", oui))
+            .expect("database covers every OUI");
+
+        /* Both paths must agree before their timings mean anything */
+        let db = OuiDatabase::open(csv_path).unwrap();
+        for oui in [&hit, &miss] {
+            assert_eq!(lookup_oui(csv_path, oui).unwrap(), db.lookup(oui));
+        }
+
+        for (name, oui) in [("hit", &hit), ("miss", &miss)] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("scan_{}", name), rows),
+                oui,
+                |b, oui| b.iter(|| black_box(lookup_oui(csv_path, oui).unwrap())),
+            );
+            group.bench_with_input(
+                BenchmarkId::new(format!("index_{}", name), rows),
+                oui,
+                |b, oui| b.iter(|| black_box(OuiDatabase::open(csv_path).unwrap().lookup(oui))),
+            );
+        }
+
+        let _ = fs::remove_file(&path);
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_single);
+criterion_main!(benches);
@@ -0,0 +1,241 @@
+/*!
+ * cache.rs
+ *
+ * A binary copy of a loaded database, so later runs skip parsing the CSV.
+ *
+ * FILE FORMAT:
+ * The file starts with the magic "OUIIDX02" and a little-endian u32 giving
+ * the version of the key normalization rules, then three little-endian u64
+ * values: the source file's length, its modification time in nanoseconds
+ * since the epoch, and the number of entries. Each entry follows as a u16
+ * key length, the key bytes, a u32 vendor length and the vendor bytes.
+ *
+ * A cache whose length or time doesn't match the source file, that was
+ * built under other normalization rules, or that can't be read for any
+ * reason, is simply rebuilt; it's only ever a copy.
+ */
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::error::OuiError;
+
+const MAGIC: &[u8; 8] = b"OUIIDX02"; /* Format name and version */
+
+/*
+ * Version of the rules keys are stored under. Cached keys are already
+ * normalized, so bump this whenever normalize_db_key changes, or caches
+ * built under the old rules would keep being read as valid.
+ */
+const KEY_RULES: u32 = 1;
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325; /* FNV-1a 64-bit starting value */
+const FNV_PRIME: u64 = 0x0100_0000_01B3; /* FNV-1a 64-bit multiplier */
+
+/* What a cache remembers about its source, to tell whether it is stale */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Stamp {
+    len: u64,
+    modified: u64,
+}
+
+impl Stamp {
+    /* The length and modification time of the source file as they are now */
+    pub(crate) fn of(csv_path: &str) -> Result<Self, OuiError> {
+        let metadata = fs::metadata(csv_path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |age| age.as_nanos() as u64);
+        Ok(Stamp {
+            len: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/**
+ * cache_file - Where the cache for a database lives inside a cache directory
+ * @cache_dir: Directory holding the caches
+ * @csv_path: Path of the source database
+ *
+ * NAMING:
+ * The name is an FNV-1a hash of the source path, which is the same on
+ * every run and every platform, so one directory can hold caches for
+ * several databases without them overwriting each other.
+ *
+ * Return: The cache file's path, e.g. "<dir>/oui-84d3e0a9c1f2b7e5.idx"
+ */
+pub fn cache_file(cache_dir: &Path, csv_path: &str) -> PathBuf {
+    let hash = csv_path.bytes().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    });
+    cache_dir.join(format!("oui-{:016x}.idx", hash))
+}
+
+/* The cached entries, or None if the file is missing, damaged or stale */
+pub(crate) fn read_cache(cache: &Path, stamp: Stamp) -> Option<HashMap<String, String>> {
+    let data = fs::read(cache).ok()?;
+    let mut reader = Reader { data: &data };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return None;
+    }
+    if u32::from_le_bytes(reader.take(4)?.try_into().ok()?) != KEY_RULES {
+        return None;
+    }
+    let saved = Stamp {
+        len: reader.u64()?,
+        modified: reader.u64()?,
+    };
+    if saved != stamp {
+        return None;
+    }
+
+    let count = reader.u64()? as usize;
+    let mut entries = HashMap::with_capacity(count.min(data.len()));
+    for _ in 0..count {
+        let key_length = u16::from_le_bytes(reader.take(2)?.try_into().ok()?) as usize;
+        let key = String::from_utf8(reader.take(key_length)?.to_vec()).ok()?;
+        let vendor_length = u32::from_le_bytes(reader.take(4)?.try_into().ok()?) as usize;
+        let vendor = String::from_utf8(reader.take(vendor_length)?.to_vec()).ok()?;
+        entries.insert(key, vendor);
+    }
+    reader.data.is_empty().then_some(entries)
+}
+
+/*
+ * write_cache - Save entries as the cache for a source file
+ *
+ * The directory is created if needed, and the file is written under a
+ * temporary name and renamed into place, so a run reading the cache at the
+ * same time never sees half of it.
+ *
+ * A key longer than a u16 or a vendor longer than a u32 can count can't be
+ * stored. No real database has one, but rather than wrap the length and
+ * corrupt the index, no cache is written and later runs keep reading the
+ * CSV itself.
+ */
+pub(crate) fn write_cache(
+    cache: &Path,
+    stamp: Stamp,
+    entries: &HashMap<String, String>,
+) -> Result<(), OuiError> {
+    let mut data = Vec::new();
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&KEY_RULES.to_le_bytes());
+    data.extend_from_slice(&stamp.len.to_le_bytes());
+    data.extend_from_slice(&stamp.modified.to_le_bytes());
+    data.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    for (key, vendor) in entries {
+        let (Ok(key_length), Ok(vendor_length)) =
+            (u16::try_from(key.len()), u32::try_from(vendor.len()))
+        else {
+            return Ok(());
+        };
+        data.extend_from_slice(&key_length.to_le_bytes());
+        data.extend_from_slice(key.as_bytes());
+        data.extend_from_slice(&vendor_length.to_le_bytes());
+        data.extend_from_slice(vendor.as_bytes());
+    }
+
+    if let Some(dir) = cache.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = cache.with_extension("idx.tmp");
+    fs::write(&partial, data)?;
+    fs::rename(&partial, cache)?;
+    Ok(())
+}
+
+/* Reads fields off the front of a cache file, failing at its end */
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Option<&'a [u8]> {
+        if self.data.len() < count {
+            return None;
+        }
+        let (field, rest) = self.data.split_at(count);
+        self.data = rest;
+        Some(field)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|&(oui, vendor)| (oui.to_string(), vendor.to_string()))
+            .collect()
+    }
+
+    const STAMP: Stamp = Stamp {
+        len: 42,
+        modified: 1_700_000_000,
+    };
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache_file(dir.path(), "/data/oui.csv");
+        let saved = entries(&[("00000C", "Cisco Systems, Inc"), ("001122", "CIMSYS Inc")]);
+
+        write_cache(&cache, STAMP, &saved).unwrap();
+        assert_eq!(read_cache(&cache, STAMP), Some(saved));
+        let newer = Stamp {
+            modified: STAMP.modified + 1,
+            ..STAMP
+        };
+        assert_eq!(read_cache(&cache, newer), None);
+    }
+
+    #[test]
+    fn names_depend_only_on_the_path() {
+        let dir = Path::new("/cache");
+        assert_eq!(cache_file(dir, "/a.csv"), cache_file(dir, "/a.csv"));
+        assert_ne!(cache_file(dir, "/a.csv"), cache_file(dir, "/b.csv"));
+    }
+
+    #[test]
+    fn other_key_rules_are_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache_file(dir.path(), "/data/oui.csv");
+        write_cache(&cache, STAMP, &entries(&[("00000C", "Cisco Systems, Inc")])).unwrap();
+
+        let mut data = fs::read(&cache).unwrap();
+        data[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(KEY_RULES + 1).to_le_bytes());
+        fs::write(&cache, &data).unwrap();
+        assert_eq!(read_cache(&cache, STAMP), None);
+
+        let mut old = b"OUIIDX01".to_vec();
+        old.extend_from_slice(&data[MAGIC.len() + 4..]);
+        fs::write(&cache, &old).unwrap();
+        assert_eq!(read_cache(&cache, STAMP), None);
+    }
+
+    #[test]
+    fn oversized_key_writes_no_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache_file(dir.path(), "/data/oui.csv");
+        let long = "A".repeat(u16::MAX as usize + 1);
+
+        write_cache(
+            &cache,
+            STAMP,
+            &entries(&[(&long, "Too Long"), ("00000C", "Cisco")]),
+        )
+        .unwrap();
+        assert!(!cache.exists());
+        assert_eq!(read_cache(&cache, STAMP), None);
+    }
+}
@@ -0,0 +1,234 @@
+/*!
+ * classify.rs
+ *
+ * Pluggable classifiers that describe an address from its bits alone.
+ *
+ * FIRST OCTET:
+ * The two lowest bits of the first octet carry meaning independent of the
+ * manufacturer:
+ * - bit 0 (I/G): set for group (multicast) addresses, clear for unicast
+ * - bit 1 (U/L): set for locally administered addresses, clear for ones
+ *   assigned by the manufacturer under its OUI
+ *
+ * IEEE 802c further splits locally administered addresses into four
+ * "SLAP" quadrants using bits 2 and 3.
+ */
+
+use serde::Serialize;
+
+use crate::mac::{MAC_HEX_LENGTH, MacAddress};
+
+/**
+ * Classifier - Something that can describe a MAC address in words
+ *
+ * TRAITS:
+ * A trait lists methods a type promises to provide, much like an interface
+ * in other languages. Every built-in classifier implements this one, and
+ * Box<dyn Classifier> lets the tool hold whichever ones the user picked
+ * without knowing their concrete types. Adding a classifier means adding a
+ * type that implements the trait and listing it in classifier_by_name.
+ */
+pub trait Classifier {
+    /* Name used to select the classifier on the command line */
+    fn name(&self) -> &'static str;
+
+    /* Describe the address, or None if the classifier has nothing to say */
+    fn classify(&self, mac: &MacAddress) -> Option<String>;
+}
+
+/**
+ * CLASSIFIER_NAMES - Names accepted by classifier_by_name
+ */
+pub const CLASSIFIER_NAMES: [&str; 4] = [
+    "locally-administered-type",
+    "cast-type",
+    "eui-64",
+    "randomized-mac",
+];
+
+/**
+ * classifier_by_name - Look up a built-in classifier
+ * @name: One of CLASSIFIER_NAMES
+ *
+ * Return: The classifier, or None if no built-in has that name
+ */
+pub fn classifier_by_name(name: &str) -> Option<Box<dyn Classifier>> {
+    match name {
+        "locally-administered-type" => Some(Box::new(LocallyAdministeredType)),
+        "cast-type" => Some(Box::new(CastType)),
+        "eui-64" => Some(Box::new(Eui64)),
+        "randomized-mac" => Some(Box::new(RandomizedMac)),
+        _ => None,
+    }
+}
+
+/**
+ * LocallyAdministeredType - Reports who assigned the address
+ *
+ * Universally administered addresses come from a manufacturer's OUI.
+ * Locally administered ones are reported with their SLAP quadrant:
+ * - AAI: administratively assigned, which includes randomized addresses
+ * - ELI: extended local, derived from a Company ID (CID)
+ * - SAI: standard assigned, allocated by a protocol such as IEEE 802.1CQ
+ * - reserved: not yet defined by IEEE
+ */
+pub struct LocallyAdministeredType;
+
+impl Classifier for LocallyAdministeredType {
+    fn name(&self) -> &'static str {
+        "locally-administered-type"
+    }
+
+    fn classify(&self, mac: &MacAddress) -> Option<String> {
+        let octet = mac.first_octet()?;
+        if octet & 0b10 == 0 {
+            return Some("universally administered".to_string());
+        }
+
+        /* Bits 3 (Y) and 2 (Z) select the quadrant */
+        let quadrant = match (octet >> 2) & 0b11 {
+            0b00 => "AAI",
+            0b01 => "ELI",
+            0b11 => "SAI",
+            _ => "reserved",
+        };
+        Some(format!("locally administered, {} quadrant", quadrant))
+    }
+}
+
+/**
+ * CastType - Reports whether the address is unicast, multicast or broadcast
+ */
+pub struct CastType;
+
+impl Classifier for CastType {
+    fn name(&self) -> &'static str {
+        "cast-type"
+    }
+
+    fn classify(&self, mac: &MacAddress) -> Option<String> {
+        let octet = mac.first_octet()?;
+        let cast = if mac.full == "FFFFFFFFFFFF" {
+            "broadcast"
+        } else if octet & 0b01 != 0 {
+            "multicast"
+        } else {
+            "unicast"
+        };
+        Some(cast.to_string())
+    }
+}
+
+/**
+ * Eui64 - Reports the IPv6 interface identifier the address would produce
+ *
+ * Seeing that identifier in an IPv6 address means the host put its
+ * hardware address on the wire; see MacAddress::modified_eui64.
+ */
+pub struct Eui64;
+
+impl Classifier for Eui64 {
+    fn name(&self) -> &'static str {
+        "eui-64"
+    }
+
+    fn classify(&self, mac: &MacAddress) -> Option<String> {
+        Some(format!("modified EUI-64 {}", mac.modified_eui64()?))
+    }
+}
+
+/*
+ * Locally administered prefixes with a well-known origin, checked in
+ * order so a longer, more specific prefix can come before a shorter one
+ */
+const KNOWN_LOCAL_PREFIXES: [(&str, &str); 5] = [
+    (
+        "020000000000",
+        "Android placeholder (real address hidden from apps)",
+    ),
+    ("DAA119", "Android 8-9 randomized address"),
+    ("0242", "Docker container"),
+    ("525400", "QEMU/KVM virtual machine"),
+    ("0A0027", "VirtualBox host-only adapter"),
+];
+
+/**
+ * RandomizedMac - Guesses where a locally administered address came from
+ *
+ * RANDOMIZATION:
+ * Phones and laptops now hide their hardware address behind random ones,
+ * which set the U/L bit and so never match an OUI. A few sources use a
+ * fixed prefix and are listed in KNOWN_LOCAL_PREFIXES. iOS 14+, Android
+ * 10+ and Windows 10+ private addresses are random in every other bit,
+ * ignoring the SLAP quadrants, so they can't be told apart from one
+ * another and any other local unicast address is reported as one of them.
+ * Plain output shows the guess in place of "No match.".
+ */
+pub struct RandomizedMac;
+
+impl Classifier for RandomizedMac {
+    fn name(&self) -> &'static str {
+        "randomized-mac"
+    }
+
+    fn classify(&self, mac: &MacAddress) -> Option<String> {
+        let octet = mac.first_octet()?;
+        if octet & 0b11 != 0b10 {
+            return None; /* Group or universally administered */
+        }
+        if let Some((_, origin)) = KNOWN_LOCAL_PREFIXES
+            .iter()
+            .find(|(prefix, _)| mac.full.starts_with(prefix))
+        {
+            return Some(origin.to_string());
+        }
+        Some("randomized (iOS 14+, Android 10+ or Windows 10+ private address)".to_string())
+    }
+}
+
+/**
+ * AddressKind - The special kinds of address JSON output names outright
+ *
+ * Classifiers describe an address in words for people; this is the fixed
+ * set of values scripts can match on, serialized in kebab-case, e.g.
+ * "locally-administered". Ordinary universally administered unicast
+ * addresses have no kind.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AddressKind {
+    /* FF:FF:FF:FF:FF:FF, every station on the link */
+    Broadcast,
+    /* 00:00:00:00:00:00, a placeholder for "no address" */
+    Null,
+    /* Any other group address */
+    Multicast,
+    /* A unicast address not assigned under an OUI */
+    LocallyAdministered,
+}
+
+impl AddressKind {
+    /**
+     * of - Which special kind an address is
+     * @mac: The parsed address
+     *
+     * Broadcast and null need all six octets; the others only the first.
+     *
+     * Return: The kind, or None for an ordinary address
+     */
+    pub fn of(mac: &MacAddress) -> Option<AddressKind> {
+        let complete = mac.full.len() == MAC_HEX_LENGTH;
+        let octet = mac.first_octet()?;
+        if complete && mac.full.chars().all(|c| c == 'F') {
+            Some(AddressKind::Broadcast)
+        } else if complete && mac.full.chars().all(|c| c == '0') {
+            Some(AddressKind::Null)
+        } else if octet & 0b01 != 0 {
+            Some(AddressKind::Multicast)
+        } else if octet & 0b10 != 0 {
+            Some(AddressKind::LocallyAdministered)
+        } else {
+            None
+        }
+    }
+}
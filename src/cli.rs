@@ -0,0 +1,1049 @@
+/*
+ * cli.rs
+ *
+ * Command-line interface definition.
+ *
+ * CLAP DERIVE:
+ * clap builds the argument parser from these struct definitions. Each field
+ * becomes an option or positional argument, and #[arg(...)] attributes set
+ * its name, help text and validation. Help and usage output are generated
+ * from the same definitions, so they can't drift from what is accepted.
+ */
+
+use std::ffi::OsString;
+use std::num::NonZeroUsize;
+
+use clap::builder::PossibleValuesParser;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+
+/*
+ * Cli - Top-level arguments
+ *
+ * A bare "oui <MAC>" is parsed into the flattened LookupArgs, so it keeps
+ * working alongside the subcommands. Cli::parse_args stops the two forms
+ * from being mixed in one invocation.
+ */
+#[derive(Parser)]
+#[command(
+    name = "oui",
+    version,
+    about = "Find the manufacturer of a NIC from its MAC address",
+    override_usage = "oui [OPTIONS] <MAC>...\n       oui [OPTIONS] <COMMAND>",
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub lookup: LookupArgs,
+
+    #[command(flatten)]
+    pub global: GlobalArgs,
+}
+
+impl Cli {
+    /*
+     * parse_args - Parse the command line, rejecting lookup options given with a subcommand
+     * @args: Full argument list, including the program name
+     *
+     * clap's args_conflicts_with_subcommands would do this, but it counts
+     * global options too, so "oui --path FILE search cisco" would stop
+     * recognizing "search". Checking where each LookupArgs value came from
+     * after parsing lets globals go anywhere while "oui --head 5 search x"
+     * is still refused instead of silently ignoring --head.
+     *
+     * Return: Result containing the parsed arguments, or clap's error ready to render
+     */
+    pub fn parse_args(args: Vec<OsString>) -> Result<Cli, clap::Error> {
+        let mut command = Cli::command();
+        let matches = command.try_get_matches_from_mut(args)?;
+
+        if matches.subcommand().is_some() {
+            let lookup = LookupArgs::augment_args(clap::Command::new("lookup"));
+            for arg in lookup.get_arguments() {
+                if matches.value_source(arg.get_id().as_str()) != Some(ValueSource::CommandLine) {
+                    continue;
+                }
+                let name = match (arg.get_long(), arg.get_value_names()) {
+                    (Some(long), _) => format!("--{}", long),
+                    (None, Some([value, ..])) => format!("<{}>", value),
+                    _ => arg.get_id().to_string(),
+                };
+                return Err(command.error(
+                    ErrorKind::ArgumentConflict,
+                    format!("{} can't be combined with a subcommand", name),
+                ));
+            }
+        }
+
+        if let Ok(value) = std::env::var(FORMAT_ENV)
+            && !value.is_empty()
+            && OutputFormat::from_str(&value, true).is_err()
+        {
+            return Err(command.error(
+                ErrorKind::InvalidValue,
+                format!(
+                    "{}=\"{}\" is not one of {}",
+                    FORMAT_ENV,
+                    value,
+                    format_names()
+                ),
+            ));
+        }
+
+        if let Ok(value) = std::env::var(STALE_DAYS_ENV)
+            && !value.is_empty()
+            && value.trim().parse::<u64>().is_err()
+        {
+            return Err(command.error(
+                ErrorKind::InvalidValue,
+                format!("{}=\"{}\" is not a number of days", STALE_DAYS_ENV, value),
+            ));
+        }
+
+        Cli::from_arg_matches(&matches)
+    }
+}
+
+/* Value parser for --registry-filter, so an unknown name is a usage error */
+fn parse_registry(name: &str) -> Result<oui::Registry, String> {
+    oui::Registry::from_name(name)
+        .ok_or_else(|| format!("unknown registry \"{}\"; expected MA-L, MA-M or MA-S", name))
+}
+
+const FORMAT_ENV: &str = "OUI_FORMAT"; /* Default for --output when neither it nor --json is given */
+const CACHE_DIR_ENV: &str = "OUI_CACHE_DIR"; /* Default for --cache-dir */
+const STALE_DAYS_ENV: &str = "OUI_WARN_STALE_DAYS"; /* Default for --warn-stale-days */
+const STALE_AFTER_DAYS: u64 = 90; /* Age at which a database counts as stale */
+
+/* Every --output name, for the OUI_FORMAT error, e.g. "plain, json or env" */
+fn format_names() -> String {
+    let names: Vec<String> = OutputFormat::value_variants()
+        .iter()
+        .filter_map(|format| Some(format.to_possible_value()?.get_name().to_string()))
+        .collect();
+    match names.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+/* The output format named by OUI_FORMAT, if it is set to one */
+fn env_format() -> Option<OutputFormat> {
+    let value = std::env::var(FORMAT_ENV).ok()?;
+    OutputFormat::from_str(&value, true).ok()
+}
+
+/*
+ * Command - The available subcommands
+ *
+ * LookupArgs is boxed because it is far larger than the other variants,
+ * and every Command would otherwise be as big as the largest one.
+ */
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Look up the manufacturer of one or more MAC addresses")]
+    Lookup(Box<LookupArgs>),
+    #[command(about = "List the OUIs registered to a manufacturer")]
+    Search(SearchArgs),
+    #[command(about = "Download the latest IEEE OUI database")]
+    Update(UpdateArgs),
+    #[command(about = "Print the path of the OUI database")]
+    Where,
+    #[command(about = "Check that the OUI database is installed and usable")]
+    Doctor,
+    #[command(about = "Convert a vendor database into the OUI;Vendor format lookups use")]
+    Normalize(NormalizeArgs),
+}
+
+/*
+ * GlobalArgs - Options shared by every subcommand
+ *
+ * global = true lets these appear before or after the subcommand name.
+ */
+#[derive(Args)]
+pub struct GlobalArgs {
+    #[arg(
+        short = 'p',
+        long,
+        global = true,
+        value_name = "FILE",
+        help = "Use this database file instead of the default (or $OUI_CSV_PATH); repeat to look up in several, in order"
+    )]
+    pub path: Vec<String>,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "FORMAT",
+        visible_alias = "format",
+        conflicts_with_all = ["json", "json_pretty"],
+        help = "How to print results"
+    )]
+    pub output: Option<OutputFormat>,
+
+    #[arg(
+        short = 'j',
+        long,
+        global = true,
+        help = "Print results as JSON (same as --output json)"
+    )]
+    pub json: bool,
+
+    #[arg(long, global = true, help = "Print results as indented JSON")]
+    pub json_pretty: bool,
+
+    #[arg(
+        short = 'v',
+        long,
+        global = true,
+        help = "Show normalized addresses alongside results"
+    )]
+    pub verbose: bool,
+
+    #[arg(
+        short = 'q',
+        long,
+        global = true,
+        conflicts_with = "verbose",
+        help = "Print nothing but errors; check the exit status instead"
+    )]
+    pub quiet: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "DAYS",
+        help = "Warn when the database is older than DAYS, 0 to never warn (or $OUI_WARN_STALE_DAYS; default 90)"
+    )]
+    pub warn_stale_days: Option<u64>,
+}
+
+impl GlobalArgs {
+    /* The first --path or OUI_CSV_PATH, else the first of HOME's and the system database that exists */
+    pub fn csv_path(&self) -> Result<String, oui::OuiError> {
+        oui::resolve_csv_path(self.path.first().map(String::as_str))
+    }
+
+    /* Databases after the first --path, which lookups fall back on in order */
+    pub fn extra_paths(&self) -> &[String] {
+        self.path.get(1..).unwrap_or_default()
+    }
+
+    /* Where update saves: the first --path, then OUI_CSV_PATH, then the default under HOME */
+    pub fn update_path(&self) -> Result<String, oui::OuiError> {
+        oui::update_target(self.path.first().map(String::as_str))
+    }
+
+    /* Age in days that counts as stale: the flag, then OUI_WARN_STALE_DAYS, then 90 */
+    pub fn stale_days(&self) -> u64 {
+        self.warn_stale_days
+            .or_else(|| std::env::var(STALE_DAYS_ENV).ok()?.trim().parse().ok())
+            .unwrap_or(STALE_AFTER_DAYS)
+    }
+
+    /*
+     * --json and --json-pretty are shorthands, so callers only need to check
+     * one value. Flags win over OUI_FORMAT, which wins over plain; parse_args
+     * has already rejected a value of OUI_FORMAT that isn't a format.
+     */
+    pub fn format(&self) -> OutputFormat {
+        self.format_with(env_format())
+    }
+
+    /* format() with the value of OUI_FORMAT passed in */
+    fn format_with(&self, from_env: Option<OutputFormat>) -> OutputFormat {
+        if self.json || self.json_pretty {
+            OutputFormat::Json
+        } else {
+            self.output.or(from_env).unwrap_or(OutputFormat::Plain)
+        }
+    }
+}
+
+/*
+ * OutputFormat - The ways results can be printed
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Plain,
+    Json,
+    Csv,
+    Table,
+    Yaml,
+    /* One compact JSON object per line */
+    Ndjson,
+    /* Shell variable assignments for eval, single lookups only */
+    Env,
+}
+
+/*
+ * InputFormat - How the lines of --file are laid out
+ */
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /* One address per line */
+    Plain,
+    /* Output of Windows' getmac, in any of its table, list or CSV forms */
+    Getmac,
+}
+
+/*
+ * Columns - How normalize finds the OUI and vendor columns
+ */
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Columns {
+    /* Use --oui-column and --vendor-column as given */
+    Positional,
+    /* Match the header row against common column names */
+    Auto,
+}
+
+/*
+ * LookupArgs - Options for resolving MAC addresses
+ */
+#[derive(Args)]
+pub struct LookupArgs {
+    #[arg(value_name = "MAC", help = "MAC addresses to look up")]
+    #[cfg_attr(
+        not(feature = "pcap"),
+        arg(required_unless_present_any = ["file", "self_", "head", "tail", "as_oui24", "benchmark_db", "diff", "count_vendors", "print_schema", "explain_format", "serve"])
+    )]
+    #[cfg_attr(
+        feature = "pcap",
+        arg(required_unless_present_any = ["file", "self_", "head", "tail", "as_oui24", "benchmark_db", "diff", "count_vendors", "print_schema", "explain_format", "serve", "pcap"])
+    )]
+    pub macs: Vec<String>,
+
+    #[arg(
+        short = 'f',
+        long,
+        value_name = "FILE",
+        help = "Read MAC addresses from FILE, one per line (- for stdin)"
+    )]
+    pub file: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value = "plain",
+        help = "How --file is laid out: one address per line, or getmac output"
+    )]
+    pub input_format: InputFormat,
+
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["macs", "tail"],
+        help = "Print the first N database rows instead of looking anything up"
+    )]
+    pub head: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with = "macs",
+        help = "Print the last N database rows instead of looking anything up"
+    )]
+    pub tail: Option<usize>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["macs", "head", "tail"],
+        help = "Print every distinct 24-bit prefix in the database, sorted"
+    )]
+    pub as_oui24: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["head", "tail", "as_oui24", "diff", "count_vendors", "no_db", "all", "serve", "exit_on_first_match", "field", "output_template", "echo_input", "count_only"],
+        help = "Print the distinct OUIs of the given addresses, sorted, without looking them up"
+    )]
+    pub trim_to_oui: bool,
+
+    #[arg(
+        long,
+        value_name = "LOOKUPS",
+        num_args = 0..=1,
+        default_missing_value = "100000",
+        conflicts_with_all = ["macs", "head", "tail", "as_oui24"],
+        help = "Time loading the database and LOOKUPS random lookups (default 100000)"
+    )]
+    pub benchmark_db: Option<usize>,
+
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["OLD", "NEW"],
+        conflicts_with_all = ["macs", "head", "tail", "as_oui24", "benchmark_db"],
+        help = "Report OUIs added, removed or renamed between two database files"
+    )]
+    pub diff: Option<Vec<String>>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["macs", "head", "tail", "as_oui24", "benchmark_db", "diff"],
+        help = "Print how many distinct vendors the database has and which hold the most OUIs"
+    )]
+    pub count_vendors: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "count_vendors",
+        conflicts_with = "macs",
+        help = "With --count-vendors, how many vendors to rank (default 10)"
+    )]
+    pub top: Option<usize>,
+
+    #[arg(
+        long = "self",
+        help = "Look up the MAC address of every network interface on this machine"
+    )]
+    pub self_: bool,
+
+    #[cfg(feature = "pcap")]
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Look up every MAC address seen in a pcap capture"
+    )]
+    pub pcap: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["no_db", "head", "tail", "as_oui24", "benchmark_db"],
+        help = "Stop at the first blocked result, or the first known vendor without a policy"
+    )]
+    pub exit_on_first_match: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["field", "summary", "no_newline", "exit_on_first_match", "no_db"],
+        help = "Print only matched=N total=M to stderr; exit 0 only if every address matched"
+    )]
+    pub count_only: bool,
+
+    #[arg(long, help = "Print a single result without a trailing newline")]
+    pub no_newline: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "FIELDS",
+        conflicts_with_all = ["json", "json_pretty"],
+        help = "Print only these comma-separated fields, tab-separated"
+    )]
+    pub field: Vec<Field>,
+
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with_all = ["json", "json_pretty", "field", "count_only", "no_db"],
+        help = "Print each result as TEMPLATE, e.g. \"{oui} => {vendor}\"; {{ and }} are literal braces"
+    )]
+    pub output_template: Option<String>,
+
+    #[arg(
+        long,
+        help = "Say which database file each vendor came from, for use with several --path files"
+    )]
+    pub show_source: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["json", "json_pretty", "count_only", "no_db", "all"],
+        help = "Start each result line with the address as given and a tab"
+    )]
+    pub echo_input: bool,
+
+    #[arg(
+        long,
+        help = "End CSV output with a comment line counting processed, matched, unmatched and invalid addresses"
+    )]
+    pub summary: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 40,
+        help = "Shorten vendor names in --output table to N characters"
+    )]
+    pub max_width: usize,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Shorten vendor names in plain and table output to N characters, ending in an ellipsis"
+    )]
+    pub truncate_vendor: Option<usize>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "KEY",
+        default_value_t = OutputOrder::Input,
+        help = "Order batch results by input position, OUI or vendor"
+    )]
+    pub sort_output: OutputOrder,
+
+    #[arg(
+        long,
+        help = "Ask an online vendor API about addresses the database doesn't know"
+    )]
+    pub online_fallback: bool,
+
+    #[arg(
+        long,
+        value_name = "URL",
+        default_value = oui::ONLINE_LOOKUP_URL,
+        requires = "online_fallback",
+        help = "Endpoint for --online-fallback, with {mac} where the address goes"
+    )]
+    pub online_url: String,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 5,
+        requires = "online_fallback",
+        help = "Give up on an --online-fallback request after SECONDS"
+    )]
+    pub online_timeout: u64,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Keep a binary copy of the database index in DIR to speed up later runs (or $OUI_CACHE_DIR)"
+    )]
+    pub cache_dir: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "REGISTRIES",
+        value_delimiter = ',',
+        value_parser = parse_registry,
+        conflicts_with = "low_memory",
+        help = "Only match assignments from these registries, e.g. MA-L,MA-S"
+    )]
+    pub registry_filter: Vec<oui::Registry>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_parser = PossibleValuesParser::new(oui::CLASSIFIER_NAMES),
+        help = "Describe each address with a built-in classifier (repeatable)"
+    )]
+    pub classifier: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Echo addresses with the casing and separators they were given in"
+    )]
+    pub preserve_input: bool,
+
+    #[arg(
+        long,
+        help = "Shell-quote the vendor in plain output so it can be captured safely"
+    )]
+    pub shell_escape: bool,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Add vendor details from a Vendor;Country;Founded file"
+    )]
+    pub metadata: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Resolve batches on N threads (default: one per CPU)"
+    )]
+    pub threads: Option<NonZeroUsize>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "NAME",
+        default_value = "long",
+        help = "With a Wireshark manuf database, report short or long vendor names"
+    )]
+    pub vendor_name: VendorNameArg,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ROW",
+        default_value = "first",
+        conflicts_with = "low_memory",
+        help = "Which row wins when the database lists an OUI twice"
+    )]
+    pub conflict: ConflictArg,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["head", "tail", "as_oui24", "benchmark_db", "mmap", "metadata", "allow_vendor", "block_vendor"],
+        help = "Only describe each address from its bits, without reading a database"
+    )]
+    pub no_db: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["no_db", "exit_on_first_match", "count_only", "field", "summary", "mmap", "low_memory"],
+        help = "List every vendor whose entry covers the address, not just the first"
+    )]
+    pub all: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "all",
+        help = "With --all, print at most N vendors per address"
+    )]
+    pub max_results_per_mac: Option<usize>,
+
+    #[arg(long, help = "Load the database through a memory map")]
+    pub mmap: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["mmap", "threads"],
+        help = "Scan the database for each address in a batch instead of indexing it"
+    )]
+    pub low_memory: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["mmap", "cache_dir", "registry_filter", "all", "no_db", "serve"],
+        help = "Give up scanning the database after N rows, reporting the address as not found within the limit"
+    )]
+    pub max_scan_rows: Option<usize>,
+
+    #[arg(
+        long,
+        overrides_with = "assume_mac",
+        help = "Reject bare hex that doesn't look like a MAC address"
+    )]
+    pub strict_mac: bool,
+
+    #[arg(
+        long,
+        overrides_with = "strict_mac",
+        help = "Accept any 12-17 character input as a MAC address (default)"
+    )]
+    pub assume_mac: bool,
+
+    #[arg(long, help = "Reject addresses that mix different separators")]
+    pub strict_separators: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "strict_mac",
+        help = "Pad an address that is one hex digit short with a leading zero, with a warning"
+    )]
+    pub allow_malformed_length: bool,
+
+    #[arg(
+        long,
+        help = "Read addresses as 48-bit integers, in decimal or with a 0x prefix"
+    )]
+    pub integer: bool,
+
+    #[arg(
+        long,
+        visible_alias = "bluetooth",
+        help = "Reverse the octet order before looking up, for Bluetooth addresses printed least significant octet first"
+    )]
+    pub reverse: bool,
+
+    #[arg(
+        long,
+        help = "Reverse the bits of each octet before looking up, for non-canonical Token Ring and FDDI addresses"
+    )]
+    pub bit_reverse: bool,
+
+    #[arg(
+        long,
+        hide = true,
+        help = "Print the JSON Schema of a lookup result and exit"
+    )]
+    pub print_schema: bool,
+
+    #[arg(
+        long,
+        help = "List the MAC address notations that are accepted, with examples, and exit"
+    )]
+    pub explain_format: bool,
+
+    #[arg(
+        long,
+        value_name = "SOCKET",
+        conflicts_with_all = ["file", "no_db"],
+        help = "Load the database once and answer one MAC per line over a Unix socket at SOCKET until interrupted"
+    )]
+    pub serve: Option<String>,
+
+    #[arg(
+        long,
+        help = "Remove corporate suffixes like Inc, Ltd and GmbH from vendor names"
+    )]
+    pub strip_vendor_suffix: bool,
+
+    #[arg(
+        long,
+        value_name = "SUFFIX",
+        requires = "strip_vendor_suffix",
+        help = "Also remove SUFFIX with --strip-vendor-suffix (repeatable)"
+    )]
+    pub vendor_suffix: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "VENDOR",
+        help = "Block results whose vendor doesn't contain VENDOR"
+    )]
+    pub allow_vendor: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "VENDOR",
+        help = "Block results whose vendor contains VENDOR"
+    )]
+    pub block_vendor: Vec<String>,
+}
+
+impl LookupArgs {
+    /* The registries lookups may match: those from --registry-filter, or all of them */
+    pub fn registries(&self) -> &[oui::Registry] {
+        if self.registry_filter.is_empty() {
+            &oui::Registry::LONGEST_FIRST
+        } else {
+            &self.registry_filter
+        }
+    }
+
+    /* Where to cache the index: --cache-dir, then a non-empty OUI_CACHE_DIR */
+    pub fn cache_dir(&self) -> Option<String> {
+        self.cache_dir.clone().or_else(|| {
+            std::env::var(CACHE_DIR_ENV)
+                .ok()
+                .filter(|dir| !dir.is_empty())
+        })
+    }
+
+    /* The --max-scan-rows cap for scans that don't build an index */
+    pub fn scan_options(&self) -> oui::ScanOptions {
+        oui::ScanOptions {
+            max_rows: self.max_scan_rows,
+        }
+    }
+
+    /* Collect the flags that affect MAC parsing */
+    pub fn parse_options(&self) -> oui::ParseOptions {
+        oui::ParseOptions {
+            strict_mac: self.strict_mac,
+            strict_separators: self.strict_separators,
+            integer: self.integer,
+            reverse: self.reverse,
+            bit_reverse: self.bit_reverse,
+            allow_malformed_length: self.allow_malformed_length,
+        }
+    }
+}
+
+/*
+ * Field - Result fields that --field can select
+ *
+ * VALUE ENUM:
+ * Deriving ValueEnum lets clap parse each comma-separated name straight
+ * into a variant and reject unknown names with a list of valid ones.
+ */
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Field {
+    Interface,
+    Input,
+    Mac,
+    Oui,
+    Vendor,
+    Found,
+    Verdict,
+    Source,
+}
+
+impl Field {
+    /* Columns written by --output csv when --field isn't given */
+    pub const CSV_DEFAULT: [Field; 5] = [
+        Field::Input,
+        Field::Mac,
+        Field::Oui,
+        Field::Found,
+        Field::Vendor,
+    ];
+
+    /* Columns shown by --output table when --field isn't given */
+    pub const TABLE_DEFAULT: [Field; 3] = [Field::Input, Field::Oui, Field::Vendor];
+
+    /* The name used on the command line and in CSV headers */
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Interface => "interface",
+            Field::Input => "input",
+            Field::Mac => "mac",
+            Field::Oui => "oui",
+            Field::Vendor => "vendor",
+            Field::Found => "found",
+            Field::Verdict => "verdict",
+            Field::Source => "source",
+        }
+    }
+}
+
+/*
+ * VendorNameArg - Command-line spelling of oui::VendorName
+ *
+ * The library type can't derive ValueEnum without depending on clap, so
+ * this mirror is converted with From.
+ */
+#[derive(Clone, Copy, ValueEnum)]
+pub enum VendorNameArg {
+    Short,
+    Long,
+}
+
+impl From<VendorNameArg> for oui::VendorName {
+    fn from(arg: VendorNameArg) -> Self {
+        match arg {
+            VendorNameArg::Short => oui::VendorName::Short,
+            VendorNameArg::Long => oui::VendorName::Long,
+        }
+    }
+}
+
+/*
+ * ConflictArg - Command-line spelling of oui::Conflict
+ */
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConflictArg {
+    First,
+    Last,
+}
+
+impl From<ConflictArg> for oui::Conflict {
+    fn from(arg: ConflictArg) -> Self {
+        match arg {
+            ConflictArg::First => oui::Conflict::First,
+            ConflictArg::Last => oui::Conflict::Last,
+        }
+    }
+}
+
+/*
+ * SearchArgs - Options for finding OUIs by manufacturer
+ */
+#[derive(Args)]
+pub struct SearchArgs {
+    #[arg(
+        value_name = "VENDOR",
+        required_unless_present = "stdin",
+        help = "Text to look for in manufacturer names"
+    )]
+    pub vendor: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "vendor",
+        help = "Read one query per line from stdin and group the matches under each"
+    )]
+    pub stdin: bool,
+
+    #[arg(long, value_name = "N", help = "Print at most N matches")]
+    pub limit: Option<usize>,
+
+    #[arg(long, help = "List the OUIs whose manufacturer does NOT match")]
+    pub invert: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "KEY",
+        help = "Sort matches before printing"
+    )]
+    pub sort: Option<SortKey>,
+}
+
+impl SearchArgs {
+    /* Collect the flags that affect matching */
+    pub fn search_options(&self) -> oui::SearchOptions {
+        oui::SearchOptions {
+            invert: self.invert,
+        }
+    }
+}
+
+/*
+ * SortKey - What search --sort orders matches by
+ */
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SortKey {
+    Oui,
+    Vendor,
+}
+
+/*
+ * OutputOrder - What lookup --sort-output orders batch results by
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputOrder {
+    #[default]
+    Input,
+    Oui,
+    Vendor,
+}
+
+/*
+ * NormalizeArgs - Options for converting a database to the canonical format
+ *
+ * Columns are numbered from 1 here, as in cut and awk, and converted to
+ * the library's 0-based NormalizeOptions by normalize_options().
+ */
+#[derive(Args)]
+pub struct NormalizeArgs {
+    #[arg(long = "in", value_name = "FILE", help = "Database to convert")]
+    pub in_file: String,
+
+    #[arg(
+        long = "out",
+        value_name = "FILE",
+        help = "Where to write the converted database"
+    )]
+    pub out_file: String,
+
+    #[arg(
+        long,
+        value_name = "CHAR",
+        value_parser = parse_delimiter,
+        help = "Field separator of the input, a single character or \"tab\" (default ;, or guessed with --columns auto)"
+    )]
+    pub delimiter: Option<u8>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        default_value = "positional",
+        help = "Find the OUI and vendor columns by position or, with auto, from the header row"
+    )]
+    pub columns: Columns,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u16).range(1..),
+        help = "Column of the input holding the OUI"
+    )]
+    pub oui_column: u16,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 2,
+        value_parser = clap::value_parser!(u16).range(1..),
+        help = "Column of the input holding the vendor name"
+    )]
+    pub vendor_column: u16,
+
+    #[arg(long, help = "Skip the first row of the input as column names")]
+    pub header: bool,
+}
+
+impl NormalizeArgs {
+    pub fn normalize_options(&self) -> oui::NormalizeOptions {
+        oui::NormalizeOptions {
+            delimiter: self.delimiter.unwrap_or(b';'),
+            oui_column: usize::from(self.oui_column) - 1,
+            vendor_column: usize::from(self.vendor_column) - 1,
+            has_header: self.header,
+        }
+    }
+}
+
+/* Value parser for --delimiter: one ASCII character, with "tab" spelled out */
+fn parse_delimiter(value: &str) -> Result<u8, String> {
+    match value {
+        "tab" | "\\t" => Ok(b'\t'),
+        _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+        _ => Err(format!("\"{}\" is not a single ASCII character", value)),
+    }
+}
+
+/*
+ * UpdateArgs - Options for downloading the database
+ */
+#[derive(Args)]
+pub struct UpdateArgs {
+    #[arg(long, value_name = "URL", default_value = oui::IEEE_OUI_URL, help = "Where to download the IEEE CSV from")]
+    pub url: String,
+
+    #[arg(
+        short = 'y',
+        long,
+        help = "Replace an existing database without asking"
+    )]
+    pub assume_yes: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn global(args: &[&str]) -> GlobalArgs {
+        let argv = ["oui"].iter().chain(args).map(OsString::from).collect();
+        Cli::parse_args(argv).unwrap().global
+    }
+
+    #[test]
+    fn format_precedence() {
+        let bare = global(&["00:11:22:33:44:55"]);
+        assert_eq!(bare.format_with(None), OutputFormat::Plain);
+        assert_eq!(
+            bare.format_with(Some(OutputFormat::Ndjson)),
+            OutputFormat::Ndjson
+        );
+
+        let flag = global(&["--output", "csv", "00:11:22:33:44:55"]);
+        assert_eq!(
+            flag.format_with(Some(OutputFormat::Ndjson)),
+            OutputFormat::Csv
+        );
+
+        let json = global(&["--json", "00:11:22:33:44:55"]);
+        assert_eq!(
+            json.format_with(Some(OutputFormat::Yaml)),
+            OutputFormat::Json
+        );
+    }
+
+    #[test]
+    fn env_format_names() {
+        assert_eq!(OutputFormat::from_str("JSON", true), Ok(OutputFormat::Json));
+        assert_eq!(
+            OutputFormat::from_str("ndjson", true),
+            Ok(OutputFormat::Ndjson)
+        );
+        assert!(OutputFormat::from_str("xml", true).is_err());
+        assert_eq!(
+            format_names(),
+            "plain, json, csv, table, yaml, ndjson or env"
+        );
+    }
+}
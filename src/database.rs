@@ -0,0 +1,1015 @@
+/*!
+ * database.rs
+ *
+ * In-memory index of the OUI database for resolving many addresses at once.
+ */
+
+use std::collections::{BTreeSet, HashMap, hash_map};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use memmap2::Mmap;
+use serde::Serialize;
+
+use crate::cache::{Stamp, cache_file, read_cache, write_cache};
+use crate::error::OuiError;
+use crate::lookup::{
+    LookupResult, Registry, UNKNOWN_VENDOR, VendorMatch, database_reader, database_records,
+    normalize_db_key, open_database,
+};
+use crate::mac::{OUI_LENGTH, parse_mac};
+use crate::manuf::{VendorName, load_manuf_map};
+use crate::paths::ensure_not_directory;
+
+/**
+ * Conflict - Which row wins when the database lists an OUI more than once
+ *
+ * DUPLICATES:
+ * The IEEE registry shouldn't repeat a prefix, but merged or hand-edited
+ * databases do. First matches the plain scan in lookup_oui, which stops at
+ * the first matching row, so a lookup gives the same vendor whether or not
+ * the database was indexed. Last suits a file where later rows are local
+ * corrections appended to a downloaded copy.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Conflict {
+    /* The earliest row in the file, as lookup_oui finds it */
+    #[default]
+    First,
+    /* The latest row in the file */
+    Last,
+}
+
+/**
+ * load_oui_map - Read the whole OUI database into a HashMap
+ * @csv_path: Path to the IEEE OUI CSV file
+ *
+ * HASHMAP:
+ * HashMap<K, V> stores key/value pairs and finds a key in constant time on
+ * average, instead of walking every row like lookup_oui does. Building it
+ * costs one full pass over the file, which pays off as soon as more than a
+ * handful of addresses are resolved against the same database.
+ *
+ * Keys go through normalize_db_key, as in lookup_oui, so "aa-bb-cc",
+ * "aAbBcC" and " AABBCC " land on the same entry as "AABBCC".
+ *
+ * Rows without a manufacturer column map to UNKNOWN_VENDOR, matching
+ * what lookup_oui reports for them. A file with no rows at all is an
+ * EmptyDatabase error, as it is for lookup_oui. An OUI listed twice keeps
+ * its first row; see Conflict.
+ *
+ * Return: Result containing the OUI to manufacturer map, or an error if the database can't be read or is empty
+ */
+pub fn load_oui_map(csv_path: &str) -> Result<HashMap<String, String>, OuiError> {
+    load_oui_map_with(csv_path, Conflict::First)
+}
+
+/**
+ * load_oui_map_with - load_oui_map with a choice of which duplicate row wins
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @conflict: Whether the first or last row for a repeated OUI is kept
+ *
+ * Return: Result containing the OUI to manufacturer map, or an error if the database can't be read or is empty
+ */
+pub fn load_oui_map_with(
+    csv_path: &str,
+    conflict: Conflict,
+) -> Result<HashMap<String, String>, OuiError> {
+    non_empty(
+        read_oui_map(open_database(csv_path)?, csv_path, conflict)?,
+        csv_path,
+    )
+}
+
+/**
+ * load_oui_map_mmap - Read the whole OUI database into a HashMap via a memory map
+ * @csv_path: Path to the IEEE OUI CSV file
+ *
+ * MEMORY MAPPING:
+ * Mapping the file asks the operating system to make its contents appear
+ * as a byte slice in memory, paging it in on demand. The CSV parser then
+ * works directly on those bytes, skipping the copies a buffered read makes,
+ * which helps most with very large merged databases.
+ *
+ * UNSAFE:
+ * Mmap::map is unsafe because the compiler can't stop another process from
+ * changing the file while it is mapped. The map only lives until parsing
+ * finishes and every value is copied out into owned Strings.
+ *
+ * Return: Result containing the OUI to manufacturer map, or an error if the database can't be read or is empty
+ */
+pub fn load_oui_map_mmap(csv_path: &str) -> Result<HashMap<String, String>, OuiError> {
+    load_oui_map_mmap_with(csv_path, Conflict::First)
+}
+
+/**
+ * load_oui_map_mmap_with - load_oui_map_mmap with a choice of which duplicate row wins
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @conflict: Whether the first or last row for a repeated OUI is kept
+ *
+ * Return: Result containing the OUI to manufacturer map, or an error if the database can't be read or is empty
+ */
+pub fn load_oui_map_mmap_with(
+    csv_path: &str,
+    conflict: Conflict,
+) -> Result<HashMap<String, String>, OuiError> {
+    ensure_not_directory(csv_path)?;
+    let file = File::open(csv_path)?;
+    /* Mapping a zero-length file fails, so report it as empty up front */
+    if file.metadata()?.len() == 0 {
+        return Err(OuiError::EmptyDatabase(csv_path.to_string()));
+    }
+    let map = unsafe { Mmap::map(&file)? };
+    non_empty(
+        read_oui_map(database_reader().from_reader(&map[..]), csv_path, conflict)?,
+        csv_path,
+    )
+}
+
+/*
+ * non_empty - Turn a map with no entries into an EmptyDatabase error
+ * @map: The freshly loaded map
+ * @csv_path: Where it was loaded from, for the error message
+ */
+fn non_empty(
+    map: HashMap<String, String>,
+    csv_path: &str,
+) -> Result<HashMap<String, String>, OuiError> {
+    if map.is_empty() {
+        return Err(OuiError::EmptyDatabase(csv_path.to_string()));
+    }
+    Ok(map)
+}
+
+/*
+ * read_oui_map - Collect every record of an already-open reader into a HashMap
+ * @rdr: CSV reader over the database, from a file or from memory
+ * @csv_path: Where the reader's data came from, for error messages
+ * @conflict: Which row to keep when an OUI repeats
+ */
+fn read_oui_map<R: Read>(
+    mut rdr: csv::Reader<R>,
+    csv_path: &str,
+    conflict: Conflict,
+) -> Result<HashMap<String, String>, OuiError> {
+    let mut map = HashMap::new();
+    for result in database_records(&mut rdr, csv_path) {
+        let record = result?;
+        if let Some(oui) = record.get(0).map(normalize_db_key) {
+            let vendor = record.get(1).unwrap_or(UNKNOWN_VENDOR);
+            match conflict {
+                Conflict::First => {
+                    map.entry(oui.into_owned())
+                        .or_insert_with(|| vendor.to_string());
+                }
+                Conflict::Last => {
+                    map.insert(oui.into_owned(), vendor.to_string());
+                }
+            }
+        }
+    }
+
+    Ok(map)
+}
+
+/**
+ * OuiDatabase - The OUI database loaded into memory
+ *
+ * STRUCTS:
+ * A struct groups related values under one name. The fields are private, so
+ * the only way to build an OuiDatabase is through open(), which guarantees
+ * the map was actually loaded from a file.
+ */
+#[derive(Debug, Clone)]
+pub struct OuiDatabase {
+    path: String,
+    entries: HashMap<String, String>,
+    source: Source, /* How to read the file again on reload */
+}
+
+/**
+ * Entries - Iterator over the prefixes of an OuiDatabase, from iter()
+ *
+ * A named type rather than impl Iterator, so callers can store it, while
+ * the HashMap behind it stays private.
+ */
+#[derive(Debug, Clone)]
+pub struct Entries<'a> {
+    inner: hash_map::Iter<'a, String, String>,
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|(oui, vendor)| (oui.as_str(), vendor.as_str()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Entries<'_> {}
+
+impl<'a> IntoIterator for &'a OuiDatabase {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Entries<'a>;
+
+    fn into_iter(self) -> Entries<'a> {
+        self.iter()
+    }
+}
+
+/* The loaders an OuiDatabase can be built with */
+#[derive(Debug, Clone)]
+enum Source {
+    Csv(Conflict),
+    CsvMmap(Conflict),
+    Manuf(VendorName),
+    Cached(PathBuf, Conflict), /* The cache directory */
+}
+
+impl Source {
+    fn load(&self, path: &str) -> Result<HashMap<String, String>, OuiError> {
+        match self {
+            Source::Csv(conflict) => load_oui_map_with(path, *conflict),
+            Source::CsvMmap(conflict) => load_oui_map_mmap_with(path, *conflict),
+            Source::Manuf(which) => load_manuf_map(path, *which),
+            Source::Cached(cache_dir, conflict) => {
+                /* The two rules can give different maps, so each gets its own cache */
+                let mut cache = cache_file(cache_dir, path);
+                if *conflict == Conflict::Last {
+                    cache.set_extension("last.idx");
+                }
+                let stamp = Stamp::of(path)?;
+                if let Some(entries) = read_cache(&cache, stamp) {
+                    return Ok(entries);
+                }
+                let entries = load_oui_map_with(path, *conflict)?;
+                write_cache(&cache, stamp, &entries)?;
+                Ok(entries)
+            }
+        }
+    }
+}
+
+impl OuiDatabase {
+    /**
+     * open - Load the database at the given path
+     * @csv_path: Path to the IEEE OUI CSV file
+     *
+     * Return: Result containing the loaded database, or an error if it can't be read
+     */
+    pub fn open(csv_path: &str) -> Result<Self, OuiError> {
+        OuiDatabase::open_with(csv_path, Conflict::First)
+    }
+
+    /**
+     * open_with - open() with a choice of which duplicate row wins
+     * @csv_path: Path to the IEEE OUI CSV file
+     * @conflict: Whether the first or last row for a repeated OUI is kept
+     *
+     * Return: Result containing the loaded database, or an error if it can't be read
+     */
+    pub fn open_with(csv_path: &str, conflict: Conflict) -> Result<Self, OuiError> {
+        OuiDatabase::load(csv_path, Source::Csv(conflict))
+    }
+
+    /**
+     * open_mmap - Load the database at the given path through a memory map
+     * @csv_path: Path to the IEEE OUI CSV file
+     *
+     * The result is identical to open(); see load_oui_map_mmap for when it
+     * is faster.
+     *
+     * Return: Result containing the loaded database, or an error if it can't be read
+     */
+    pub fn open_mmap(csv_path: &str) -> Result<Self, OuiError> {
+        OuiDatabase::open_mmap_with(csv_path, Conflict::First)
+    }
+
+    /**
+     * open_mmap_with - open_mmap() with a choice of which duplicate row wins
+     * @csv_path: Path to the IEEE OUI CSV file
+     * @conflict: Whether the first or last row for a repeated OUI is kept
+     *
+     * Return: Result containing the loaded database, or an error if it can't be read
+     */
+    pub fn open_mmap_with(csv_path: &str, conflict: Conflict) -> Result<Self, OuiError> {
+        OuiDatabase::load(csv_path, Source::CsvMmap(conflict))
+    }
+
+    /**
+     * open_cached - Load the database through a binary cache
+     * @csv_path: Path to the IEEE OUI CSV file
+     * @cache_dir: Directory for the cache, created if it doesn't exist
+     *
+     * The first run parses the CSV as open() does and saves the index in
+     * cache_dir; later runs read it straight back until the CSV changes.
+     * The cache can live anywhere, so a database on read-only media still
+     * gets one.
+     *
+     * Return: Result containing the loaded database, or an error if it can't be read or the cache can't be written
+     */
+    pub fn open_cached(csv_path: &str, cache_dir: &Path) -> Result<Self, OuiError> {
+        OuiDatabase::open_cached_with(csv_path, cache_dir, Conflict::First)
+    }
+
+    /**
+     * open_cached_with - open_cached() with a choice of which duplicate row wins
+     * @csv_path: Path to the IEEE OUI CSV file
+     * @cache_dir: Directory for the cache, created if it doesn't exist
+     * @conflict: Whether the first or last row for a repeated OUI is kept
+     *
+     * Return: Result containing the loaded database, or an error if it can't be read or the cache can't be written
+     */
+    pub fn open_cached_with(
+        csv_path: &str,
+        cache_dir: &Path,
+        conflict: Conflict,
+    ) -> Result<Self, OuiError> {
+        OuiDatabase::load(csv_path, Source::Cached(cache_dir.to_path_buf(), conflict))
+    }
+
+    /**
+     * open_manuf - Load a Wireshark manuf file
+     * @path: Path to the manuf file
+     * @which: Report short names ("Cisco") or full ones ("Cisco Systems, Inc")
+     *
+     * Prefixes longer than 24 bits are kept at their full length, so only
+     * whole-OUI entries answer lookup(); lookup_longest() finds the rest.
+     *
+     * Return: Result containing the loaded database, or an error if it can't be read
+     */
+    pub fn open_manuf(path: &str, which: VendorName) -> Result<Self, OuiError> {
+        OuiDatabase::load(path, Source::Manuf(which))
+    }
+
+    /* Read a database with the given loader, keeping it for reload() */
+    fn load(path: &str, source: Source) -> Result<Self, OuiError> {
+        Ok(OuiDatabase {
+            path: path.to_string(),
+            entries: source.load(path)?,
+            source,
+        })
+    }
+
+    /**
+     * reload - Re-read the database from the file it was opened from
+     *
+     * Lets a long-running process pick up a refreshed database (for example
+     * after "oui update") without restarting. The new map is built first and
+     * only then swapped in, so if reading fails the current entries are
+     * kept and stay usable.
+     *
+     * Return: Result indicating whether the reload succeeded
+     */
+    pub fn reload(&mut self) -> Result<(), OuiError> {
+        self.entries = self.source.load(&self.path)?;
+        Ok(())
+    }
+
+    /**
+     * path - The file this database was loaded from
+     */
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /**
+     * len - Number of OUIs in the database
+     */
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /**
+     * is_empty - Whether the database holds no OUIs at all
+     */
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /**
+     * memory_estimate - Rough number of bytes the loaded entries occupy
+     *
+     * Counts the text of every key and vendor plus the HashMap's own table
+     * of String pairs at its current capacity. Allocator overhead isn't
+     * visible from here, so the real figure is somewhat higher.
+     */
+    pub fn memory_estimate(&self) -> usize {
+        let text: usize = self
+            .entries
+            .iter()
+            .map(|(oui, vendor)| oui.capacity() + vendor.capacity())
+            .sum();
+        /* One control byte per bucket on top of the pair itself */
+        let table = self.entries.capacity() * (std::mem::size_of::<(String, String)>() + 1);
+        text + table
+    }
+
+    /**
+     * lookup - Find the manufacturer for an OUI
+     * @oui: The 6-character OUI to search for, as found in MacAddress::oui
+     *
+     * Keys are stored uppercase, so a lowercase OUI matches too.
+     *
+     * Return: The lookup outcome; a hash lookup cannot fail
+     */
+    pub fn lookup(&self, oui: &str) -> LookupResult {
+        match self.get(oui) {
+            Some(vendor) => LookupResult::Found(vendor.clone()),
+            None => LookupResult::NotFound,
+        }
+    }
+
+    /*
+     * get - The vendor stored under a key, however the key is written
+     *
+     * Stored keys went through normalize_db_key when loading, so the query
+     * does too, and "aa-bb-cc" or "0xAABBCC" finds "AABBCC". parse_mac
+     * already gives normalized keys, so only a caller passing its own
+     * spelling pays for the second try.
+     */
+    fn get(&self, key: &str) -> Option<&String> {
+        self.entries.get(key).or_else(|| {
+            let normalized = normalize_db_key(key);
+            (normalized != key)
+                .then(|| self.entries.get(normalized.as_ref()))
+                .flatten()
+        })
+    }
+
+    /**
+     * iter - Every prefix in the database with its vendor
+     *
+     * Entries come out in no particular order; sort them if the order
+     * matters, e.g. for an export.
+     *
+     * Return: An iterator of (prefix, vendor) pairs, as normalized keys
+     */
+    pub fn iter(&self) -> Entries<'_> {
+        Entries {
+            inner: self.entries.iter(),
+        }
+    }
+
+    /**
+     * lookup_longest - Find the most specific assignment covering an address
+     * @full: The address's hex digits, as found in MacAddress::full
+     *
+     * Tries the MA-S, MA-M and MA-L prefixes in turn, so an MA-S block
+     * carved out of an IEEE-held MA-L reports its real owner.
+     *
+     * Return: The registry and vendor of the longest matching prefix, or None
+     */
+    pub fn lookup_longest(&self, full: &str) -> Option<(Registry, &str)> {
+        self.lookup_longest_in(full, &Registry::LONGEST_FIRST)
+    }
+
+    /**
+     * lookup_longest_in - Like lookup_longest, but only consulting some registries
+     * @full: The address's hex digits, as found in MacAddress::full
+     * @registries: Which registries may match, in any order
+     *
+     * Prefixes are still tried longest first, whatever order registries is in.
+     *
+     * Return: The registry and vendor of the longest matching prefix, or None
+     */
+    pub fn lookup_longest_in(
+        &self,
+        full: &str,
+        registries: &[Registry],
+    ) -> Option<(Registry, &str)> {
+        Registry::LONGEST_FIRST
+            .into_iter()
+            .filter(|registry| registries.contains(registry))
+            .find_map(|registry| {
+                let prefix = full.get(..registry.prefix_digits())?;
+                Some((registry, self.get(prefix)?.as_str()))
+            })
+    }
+}
+
+/**
+ * contains_oui - Whether a prefix is in the database, without its vendor
+ * @db: The loaded database
+ * @oui: The prefix in normalized form, as found in MacAddress::oui
+ *
+ * Unlike lookup, nothing is cloned, so this is the cheap test for callers
+ * that only filter addresses by whether they are registered.
+ *
+ * Return: true if the prefix has an entry
+ */
+pub fn contains_oui(db: &OuiDatabase, oui: &str) -> bool {
+    db.get(oui).is_some()
+}
+
+/**
+ * lookup_many - Resolve a batch of MAC addresses against one database load
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @macs: MAC address strings in any format accepted by parse_mac
+ *
+ * GENERICS:
+ * S: AsRef<str> accepts anything that can be borrowed as a &str, so callers
+ * can pass a slice of String or of &str without converting first.
+ *
+ * The database is read once up front. Each address then gets its own Result,
+ * so one malformed address doesn't stop the rest of the batch.
+ *
+ * Return: Result containing one outcome per address in input order, or an error if the database can't be read
+ */
+pub fn lookup_many<S: AsRef<str>>(
+    csv_path: &str,
+    macs: &[S],
+) -> Result<Vec<Result<LookupResult, OuiError>>, OuiError> {
+    let mut results = Vec::with_capacity(macs.len());
+    lookup_stream(csv_path, macs, |result| results.push(result))?;
+    Ok(results)
+}
+
+/**
+ * lookup_stream - Resolve MAC addresses one at a time, handing each result to a closure
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @macs: Any iterator of MAC address strings, consumed lazily
+ * @each: Called once per address, in input order, with its outcome
+ *
+ * CLOSURES:
+ * FnMut is a function that may change state it captured, such as a
+ * counter or an open file, so the caller decides what happens to each
+ * result as it is produced.
+ *
+ * Use this instead of lookup_many when the batch is too large to hold all
+ * the results at once, or when the addresses themselves come from a lazy
+ * source like the lines of a file. Only the database is kept in memory.
+ *
+ * Return: Result indicating whether the database could be read; per-address errors go to each
+ */
+pub fn lookup_stream<I, F>(csv_path: &str, macs: I, mut each: F) -> Result<(), OuiError>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+    F: FnMut(Result<LookupResult, OuiError>),
+{
+    let db = OuiDatabase::open(csv_path)?;
+    for mac in macs {
+        each(parse_mac(mac.as_ref()).map(|mac| db.lookup(&mac.oui)));
+    }
+    Ok(())
+}
+
+/**
+ * database_age - How long ago the database file was last written
+ * @csv_path: Path to the IEEE OUI CSV file
+ *
+ * "oui update" rewrites the whole file, so its modification time is when
+ * the registry was last fetched. A timestamp in the future (clock skew,
+ * a copied file) counts as brand new rather than as an error.
+ *
+ * Return: Result containing the age, or an error if the file's metadata can't be read
+ */
+pub fn database_age(csv_path: &str) -> Result<Duration, OuiError> {
+    let modified = fs::metadata(csv_path)?.modified()?;
+    Ok(SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or(Duration::ZERO))
+}
+
+/**
+ * oui24_prefixes - The distinct 24-bit prefixes in a database, sorted
+ * @csv_path: Path to the IEEE OUI CSV file
+ *
+ * Every key is reduced to 6 hex digits so two databases can be compared
+ * prefix by prefix. Keys are first normalized as for lookups (see
+ * normalize_db_key), so "AA-BB-CC" and "0xAABBCC" count as AABBCC. MA-M
+ * and MA-S assignments (7 and 9 digits) are cut down to the MA-L block
+ * they sit in. Keys shorter than 6 digits come from spreadsheets dropping
+ * leading zeros, so they are padded back on the left. Keys that aren't
+ * hex at all, like "2.04E+073", can't be recovered and are skipped.
+ *
+ * BTREESET:
+ * A BTreeSet keeps its items sorted and ignores duplicates, which is
+ * exactly "sorted and unique" without a separate sort and dedup pass.
+ *
+ * Return: Result containing the prefixes, or an error if the database can't be read
+ */
+pub fn oui24_prefixes(csv_path: &str) -> Result<Vec<String>, OuiError> {
+    let mut rdr = open_database(csv_path)?;
+    let mut prefixes = BTreeSet::new();
+
+    for result in database_records(&mut rdr, csv_path) {
+        let record = result?;
+        let Some(key) = record.get(0).map(normalize_db_key) else {
+            continue;
+        };
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_hexdigit()) {
+            continue;
+        }
+        let prefix = match key.get(..OUI_LENGTH) {
+            Some(prefix) => prefix.to_string(),
+            None => format!("{:0>width$}", key, width = OUI_LENGTH),
+        };
+        prefixes.insert(prefix);
+    }
+
+    Ok(prefixes.into_iter().collect())
+}
+
+/**
+ * VendorCounts - How the rows of a database are shared among vendors
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VendorCounts {
+    pub rows: usize,           /* Rows read from the database */
+    pub distinct: usize,       /* Different vendor names among them */
+    pub top: Vec<VendorCount>, /* The vendors with the most rows, most first */
+}
+
+/**
+ * VendorCount - One vendor and how many prefixes it holds
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VendorCount {
+    pub vendor: String, /* Name as written in the database */
+    pub ouis: usize,    /* Rows naming this vendor */
+}
+
+/**
+ * count_vendors - Count the prefixes held by each vendor
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @top: How many of the largest vendors to return
+ *
+ * Every row counts, so a vendor with MA-M or MA-S blocks as well as whole
+ * OUIs gets one for each. Names are compared after trimming but are
+ * otherwise exact, matching how the IEEE spells them. Vendors with the
+ * same count are listed alphabetically so the ranking doesn't change
+ * from run to run.
+ *
+ * Return: Result containing the totals and ranking, or an error if the database can't be read or is empty
+ */
+pub fn count_vendors(csv_path: &str, top: usize) -> Result<VendorCounts, OuiError> {
+    let mut rdr = open_database(csv_path)?;
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut rows = 0;
+
+    for result in database_records(&mut rdr, csv_path) {
+        let record = result?;
+        rows += 1;
+        let vendor = record.get(1).map_or(UNKNOWN_VENDOR, str::trim);
+        *counts.entry(vendor.to_string()).or_default() += 1;
+    }
+    if rows == 0 {
+        return Err(OuiError::EmptyDatabase(csv_path.to_string()));
+    }
+
+    let distinct = counts.len();
+    let mut ranked: Vec<VendorCount> = counts
+        .into_iter()
+        .map(|(vendor, ouis)| VendorCount { vendor, ouis })
+        .collect();
+    ranked.sort_by(|a, b| b.ouis.cmp(&a.ouis).then_with(|| a.vendor.cmp(&b.vendor)));
+    ranked.truncate(top);
+
+    Ok(VendorCounts {
+        rows,
+        distinct,
+        top: ranked,
+    })
+}
+
+/**
+ * DatabaseDiff - What changed between two copies of the database
+ *
+ * Each list is sorted by OUI so two runs over the same files always print
+ * the same report.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DatabaseDiff {
+    pub added: Vec<VendorMatch>,    /* OUIs only in the new file */
+    pub removed: Vec<VendorMatch>,  /* OUIs only in the old file */
+    pub changed: Vec<VendorChange>, /* OUIs in both whose vendor differs */
+}
+
+/**
+ * VendorChange - An OUI whose registered name differs between two files
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VendorChange {
+    pub oui: String, /* The OUI as written in the new file */
+    pub old: String, /* Vendor name in the old file */
+    pub new: String, /* Vendor name in the new file */
+}
+
+impl DatabaseDiff {
+    /**
+     * is_empty - Whether the two files hold the same assignments
+     */
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/**
+ * diff_databases - Compare two database files
+ * @old_path: The earlier copy
+ * @new_path: The later copy
+ *
+ * Both files are loaded with load_oui_map, so a key repeated within one
+ * file counts with its first vendor, just as it would for a lookup.
+ *
+ * Return: Result containing the differences, or an error if either file can't be read or is empty
+ */
+pub fn diff_databases(old_path: &str, new_path: &str) -> Result<DatabaseDiff, OuiError> {
+    let old = load_oui_map(old_path)?;
+    let new = load_oui_map(new_path)?;
+    let mut diff = DatabaseDiff::default();
+
+    for (oui, vendor) in &new {
+        match old.get(oui) {
+            None => diff.added.push(VendorMatch {
+                oui: oui.clone(),
+                vendor: vendor.clone(),
+            }),
+            Some(before) if before != vendor => diff.changed.push(VendorChange {
+                oui: oui.clone(),
+                old: before.clone(),
+                new: vendor.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (oui, vendor) in &old {
+        if !new.contains_key(oui) {
+            diff.removed.push(VendorMatch {
+                oui: oui.clone(),
+                vendor: vendor.clone(),
+            });
+        }
+    }
+
+    /* HashMap iteration order is arbitrary */
+    diff.added.sort_by(|a, b| a.oui.cmp(&b.oui));
+    diff.removed.sort_by(|a, b| a.oui.cmp(&b.oui));
+    diff.changed.sort_by(|a, b| a.oui.cmp(&b.oui));
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* A database under a temp dir, kept alive for as long as the dir is */
+    fn temp_database(contents: &str) -> (tempfile::TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("oui.csv");
+        fs::write(&path, contents).unwrap();
+        (dir, path.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn reload_picks_up_new_rows() {
+        let (_dir, path) = temp_database("00000C;Cisco Systems, Inc\n");
+        let mut db = OuiDatabase::open(&path).unwrap();
+        assert_eq!(db.lookup("001122"), LookupResult::NotFound);
+
+        fs::write(&path, "00000C;Cisco Systems, Inc\n001122;CIMSYS Inc\n").unwrap();
+        db.reload().unwrap();
+        assert_eq!(
+            db.lookup("001122"),
+            LookupResult::Found("CIMSYS Inc".to_string())
+        );
+        assert_eq!(db.len(), 2);
+    }
+
+    #[test]
+    fn failed_reload_keeps_entries() {
+        let (_dir, path) = temp_database("00000C;Cisco Systems, Inc\n");
+        let mut db = OuiDatabase::open(&path).unwrap();
+
+        fs::remove_file(&path).unwrap();
+        assert!(db.reload().is_err());
+        assert_eq!(
+            db.lookup("00000C"),
+            LookupResult::Found("Cisco Systems, Inc".to_string())
+        );
+    }
+
+    const EMPTY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/empty.csv");
+
+    #[test]
+    fn empty_database_is_an_error() {
+        for result in [OuiDatabase::open(EMPTY), OuiDatabase::open_mmap(EMPTY)] {
+            assert!(matches!(result, Err(OuiError::EmptyDatabase(path)) if path == EMPTY));
+        }
+    }
+
+    #[test]
+    fn oui24_prefixes_normalize_keys() {
+        let (_dir, path) = temp_database(concat!(
+            "AA-BB-CC;Dashed\n",
+            "0xAABBCC;Integer\n",
+            "aabbcc000000;Padded\n",
+            "0011223;MA-M block\n",
+            "00112233A;MA-S block\n",
+            "ABC;Lost leading zeros\n",
+            "2.04E+073;Spreadsheet damage\n",
+        ));
+        assert_eq!(
+            oui24_prefixes(&path).unwrap(),
+            ["000ABC", "001122", "AABBCC"]
+        );
+    }
+
+    const BASIC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/basic.csv");
+
+    #[test]
+    fn lookup_stream_calls_back_in_order() {
+        let mut results = Vec::new();
+        let macs = ["00:00:0c:12:34:56", "zz", "00:11:23:44:55:66"];
+        lookup_stream(BASIC, macs, |result| results.push(result)).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].as_ref().unwrap(),
+            &LookupResult::Found("Cisco Systems, Inc".to_string())
+        );
+        assert!(matches!(results[1], Err(OuiError::InvalidMac)));
+        assert_eq!(results[2].as_ref().unwrap(), &LookupResult::NotFound);
+        assert_eq!(lookup_many(BASIC, &macs).unwrap().len(), results.len());
+    }
+
+    fn entry(oui: &str, vendor: &str) -> VendorMatch {
+        VendorMatch {
+            oui: oui.to_string(),
+            vendor: vendor.to_string(),
+        }
+    }
+
+    #[test]
+    fn diff_of_two_fixtures() {
+        let old = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/diff_old.csv");
+        let new = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/diff_new.csv");
+        let diff = diff_databases(old, new).unwrap();
+
+        assert_eq!(
+            diff.added,
+            [
+                entry("000001", "Xerox Again"),
+                entry("AABBCC", "Newcomer GmbH")
+            ]
+        );
+        assert_eq!(diff.removed, [entry("000000", "XEROX CORPORATION")]);
+        assert_eq!(
+            diff.changed,
+            [VendorChange {
+                oui: "0000C1".to_string(),
+                old: "Madge Ltd.".to_string(),
+                new: "Madge Networks Ltd.".to_string(),
+            }]
+        );
+        assert!(diff_databases(old, old).unwrap().is_empty());
+    }
+
+    #[test]
+    fn contains_oui_membership() {
+        let db = OuiDatabase::open(BASIC).unwrap();
+        assert!(contains_oui(&db, "00000C"));
+        assert!(contains_oui(
+            &db,
+            &parse_mac("00:11:22:33:44:55").unwrap().oui
+        ));
+        assert!(!contains_oui(&db, "AABBCC"));
+    }
+
+    #[test]
+    fn iter_yields_every_entry() {
+        let db = OuiDatabase::open(BASIC).unwrap();
+        assert_eq!(db.iter().count(), 4);
+        assert_eq!(db.iter().len(), db.len());
+        assert!(
+            db.iter()
+                .any(|pair| pair == ("00000C", "Cisco Systems, Inc"))
+        );
+
+        let mut ouis: Vec<&str> = (&db).into_iter().map(|(oui, _)| oui).collect();
+        ouis.sort_unstable();
+        assert_eq!(ouis, ["000000", "00000C", "0000C1", "001122"]);
+    }
+
+    #[test]
+    fn cache_in_a_custom_dir_is_reused() {
+        let (dir, path) = temp_database("00000C;Cisco Systems, Inc\n");
+        let cache_dir = dir.path().join("cache").join("nested");
+        let db = OuiDatabase::open_cached(&path, &cache_dir).unwrap();
+        assert_eq!(
+            db.lookup("00000C"),
+            LookupResult::Found("Cisco Systems, Inc".to_string())
+        );
+        let cache = cache_file(&cache_dir, &path);
+        assert!(cache.is_file());
+
+        /* Rewrite the cache with a vendor the CSV doesn't have, to see which one is read */
+        let marked = HashMap::from([("00000C".to_string(), "From the cache".to_string())]);
+        write_cache(&cache, Stamp::of(&path).unwrap(), &marked).unwrap();
+        let db = OuiDatabase::open_cached(&path, &cache_dir).unwrap();
+        assert_eq!(
+            db.lookup("00000C"),
+            LookupResult::Found("From the cache".to_string())
+        );
+
+        /* Changing the CSV makes the cache stale, and it is rebuilt */
+        fs::write(&path, "00000C;Cisco Systems, Inc.\n").unwrap();
+        let db = OuiDatabase::open_cached(&path, &cache_dir).unwrap();
+        assert_eq!(
+            db.lookup("00000C"),
+            LookupResult::Found("Cisco Systems, Inc.".to_string())
+        );
+    }
+
+    const DUPLICATES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/duplicates.csv");
+
+    #[test]
+    fn first_row_wins_by_default() {
+        let cisco = LookupResult::Found("Cisco Systems, Inc".to_string());
+        for db in [
+            OuiDatabase::open(DUPLICATES).unwrap(),
+            OuiDatabase::open_mmap(DUPLICATES).unwrap(),
+            OuiDatabase::open_with(DUPLICATES, Conflict::First).unwrap(),
+        ] {
+            assert_eq!(db.lookup("00000C"), cisco);
+            assert_eq!(db.len(), 2);
+        }
+        /* The index agrees with the plain scan */
+        assert_eq!(crate::lookup_oui(DUPLICATES, "00000C").unwrap(), cisco);
+    }
+
+    #[test]
+    fn last_row_wins_on_request() {
+        for db in [
+            OuiDatabase::open_with(DUPLICATES, Conflict::Last).unwrap(),
+            OuiDatabase::open_mmap_with(DUPLICATES, Conflict::Last).unwrap(),
+        ] {
+            assert_eq!(
+                db.lookup("00000C"),
+                LookupResult::Found("Dashed Duplicate".to_string())
+            );
+            assert_eq!(
+                db.lookup("001122"),
+                LookupResult::Found("CIMSYS Inc".to_string())
+            );
+        }
+    }
+
+    fn count(vendor: &str, ouis: usize) -> VendorCount {
+        VendorCount {
+            vendor: vendor.to_string(),
+            ouis,
+        }
+    }
+
+    #[test]
+    fn vendor_distribution() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/vendor_counts.csv"
+        );
+        let counts = count_vendors(path, 3).unwrap();
+        assert_eq!(counts.rows, 10);
+        /* Cisco, Intel, Novell, CIMSYS and the row with no vendor column */
+        assert_eq!(counts.distinct, 5);
+        assert_eq!(
+            counts.top,
+            [
+                count("Cisco Systems, Inc", 4),
+                count("Intel Corporation", 3),
+                count("CIMSYS Inc", 1)
+            ]
+        );
+
+        let all = count_vendors(path, usize::MAX).unwrap();
+        assert_eq!(all.top.len(), all.distinct);
+        assert_eq!(all.top.iter().map(|c| c.ouis).sum::<usize>(), all.rows);
+    }
+
+    #[test]
+    fn index_reports_an_unterminated_quote() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/bad_quote.csv");
+        for result in [OuiDatabase::open(path), OuiDatabase::open_mmap(path)] {
+            assert!(matches!(
+                result,
+                Err(OuiError::MalformedRow { line: 2, .. })
+            ));
+        }
+        let quoted = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/quoted.csv");
+        let db = OuiDatabase::open_mmap(quoted).unwrap();
+        assert_eq!(
+            db.lookup("00000C"),
+            LookupResult::Found("Smith; Jones Ltd".to_string())
+        );
+    }
+}
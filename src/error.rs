@@ -0,0 +1,217 @@
+/*!
+ * error.rs
+ *
+ * Error type shared by the library and the command-line front end.
+ */
+
+use std::fmt;
+
+/**
+ * OuiError - Everything that can go wrong while resolving a MAC address
+ *
+ * ENUMS:
+ * A Rust enum is a type that can be exactly one of several variants, and each
+ * variant can carry its own data. This makes it a natural fit for errors,
+ * since each kind of failure can keep whatever context it needs.
+ */
+#[derive(Debug)]
+pub enum OuiError {
+    /* The supplied string could not be parsed as a MAC address */
+    InvalidMac,
+    /* The digits are grouped in a way no MAC notation uses */
+    MalformedGroups(&'static str),
+    /* Strict parsing rejected input that doesn't look like a real MAC */
+    UnlikelyMac(&'static str),
+    /* An IPv6 address whose interface identifier wasn't built from a MAC */
+    NotEui64,
+    /* The command line was not used correctly */
+    Usage(String),
+    /* HOME is needed to locate the database but is not available */
+    HomeNotSet,
+    /* None of the places a database can live has one */
+    DatabaseNotFound(Vec<String>),
+    /* The database could not be opened or read */
+    Csv(csv::Error),
+    /* A row of a semicolon-separated file can't be parsed, e.g. a quote left open */
+    MalformedRow {
+        path: String,   /* File the row is in */
+        line: u64,      /* Line the row starts on, counting from 1 */
+        reason: String, /* What is wrong with it */
+    },
+    /* A directory was given where a file is expected */
+    IsDirectory(String),
+    /* The database was read but has no rows to match against */
+    EmptyDatabase(String),
+    /* An input file could not be read */
+    Io(std::io::Error),
+    /* The vendor metadata file has a value that can't be used */
+    Metadata(String),
+    /* A packet capture could not be parsed */
+    #[cfg(feature = "pcap")]
+    Pcap(String),
+    /* Fetching a fresh copy of the database failed */
+    Download(String),
+    /* Asking an online vendor API about an address failed */
+    Online(String),
+}
+
+/**
+ * Display for OuiError
+ *
+ * DISPLAY TRAIT:
+ * Implementing std::fmt::Display is what allows a type to be used with "{}"
+ * in println!, format! and friends. The wording here is what users see, so
+ * every message lives in this one place.
+ */
+impl fmt::Display for OuiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OuiError::InvalidMac => write!(f, "Invalid MAC Address."),
+            OuiError::MalformedGroups(reason) => write!(f, "Invalid MAC Address: {}.", reason),
+            OuiError::UnlikelyMac(reason) => {
+                write!(f, "Input doesn't look like a MAC address: {}.", reason)
+            }
+            OuiError::NotEui64 => write!(
+                f,
+                "IPv6 address has no embedded MAC; its interface identifier isn't EUI-64 (privacy or random address)."
+            ),
+            OuiError::Usage(message) => write!(f, "{}", message),
+            OuiError::HomeNotSet => write!(f, "HOME environment variable is not set."),
+            OuiError::DatabaseNotFound(tried) => write!(
+                f,
+                "No OUI database found (tried {}); run \"oui update\" to download one.",
+                tried.join(", ")
+            ),
+            OuiError::Csv(e) => write!(f, "Failed to read OUI database: {}", e),
+            OuiError::MalformedRow { path, line, reason } => {
+                write!(f, "Malformed row in {} at line {}: {}.", path, line, reason)
+            }
+            OuiError::IsDirectory(path) => {
+                write!(f, "Expected a file but found a directory: {}", path)
+            }
+            OuiError::EmptyDatabase(path) => {
+                write!(f, "OUI database {} contains no usable rows.", path)
+            }
+            OuiError::Io(e) => write!(f, "{}", e),
+            OuiError::Metadata(message) => write!(f, "Invalid metadata file: {}", message),
+            #[cfg(feature = "pcap")]
+            OuiError::Pcap(message) => write!(f, "Failed to read capture: {}", message),
+            OuiError::Download(message) => write!(f, "Failed to download database: {}", message),
+            OuiError::Online(message) => write!(f, "Online lookup failed: {}", message),
+        }
+    }
+}
+
+/*
+ * ERROR TRAIT:
+ * std::error::Error marks the type as a proper error so it can be boxed,
+ * chained and used with the ? operator alongside errors from other crates.
+ * source() exposes the underlying cause when there is one.
+ */
+impl std::error::Error for OuiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OuiError::Csv(e) => Some(e),
+            OuiError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/*
+ * FROM TRAIT:
+ * Implementing From lets the ? operator convert a csv::Error into an
+ * OuiError automatically when propagating it.
+ */
+impl From<csv::Error> for OuiError {
+    fn from(e: csv::Error) -> Self {
+        OuiError::Csv(e)
+    }
+}
+
+impl From<std::io::Error> for OuiError {
+    fn from(e: std::io::Error) -> Self {
+        OuiError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* The messages are what users see, so any change to them should be deliberate */
+    #[test]
+    fn display_strings() {
+        let cases = [
+            (OuiError::InvalidMac, "Invalid MAC Address."),
+            (
+                OuiError::MalformedGroups("mixes different separators"),
+                "Invalid MAC Address: mixes different separators.",
+            ),
+            (
+                OuiError::UnlikelyMac("all digits are the same"),
+                "Input doesn't look like a MAC address: all digits are the same.",
+            ),
+            (
+                OuiError::NotEui64,
+                "IPv6 address has no embedded MAC; its interface identifier isn't EUI-64 (privacy or random address).",
+            ),
+            (OuiError::Usage("Bad flags.".to_string()), "Bad flags."),
+            (
+                OuiError::HomeNotSet,
+                "HOME environment variable is not set.",
+            ),
+            (
+                OuiError::DatabaseNotFound(vec!["/a.csv".to_string(), "/b.csv".to_string()]),
+                "No OUI database found (tried /a.csv, /b.csv); run \"oui update\" to download one.",
+            ),
+            (
+                OuiError::MalformedRow {
+                    path: "db.csv".to_string(),
+                    line: 3,
+                    reason: "a quote opened on this line is never closed".to_string(),
+                },
+                "Malformed row in db.csv at line 3: a quote opened on this line is never closed.",
+            ),
+            (
+                OuiError::IsDirectory("/tmp".to_string()),
+                "Expected a file but found a directory: /tmp",
+            ),
+            (
+                OuiError::EmptyDatabase("db.csv".to_string()),
+                "OUI database db.csv contains no usable rows.",
+            ),
+            (
+                OuiError::Io(std::io::Error::other("disk on fire")),
+                "disk on fire",
+            ),
+            (
+                OuiError::Metadata("line 2: \"x\" is not a year".to_string()),
+                "Invalid metadata file: line 2: \"x\" is not a year",
+            ),
+            (
+                OuiError::Download("HTTP 500".to_string()),
+                "Failed to download database: HTTP 500",
+            ),
+            (
+                OuiError::Online("timed out".to_string()),
+                "Online lookup failed: timed out",
+            ),
+        ];
+        for (error, expected) in cases {
+            assert_eq!(error.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn csv_errors_keep_their_cause() {
+        let csv_error = csv::Reader::from_path("/nonexistent/oui.csv").unwrap_err();
+        let error = OuiError::from(csv_error);
+        assert!(
+            error
+                .to_string()
+                .starts_with("Failed to read OUI database: ")
+        );
+        assert!(std::error::Error::source(&error).is_some());
+    }
+}
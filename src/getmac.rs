@@ -0,0 +1,94 @@
+/*!
+ * getmac.rs
+ *
+ * Pulling MAC addresses out of the output of Windows' getmac command.
+ *
+ * FILE FORMAT:
+ * getmac prints a table with the address in hyphenated form next to the
+ * adapter's transport name, for example
+ * "00-11-22-33-44-55   \Device\Tcpip_{4A1B...}". With /v there are extra
+ * columns for the connection and adapter names, and with /fo csv every
+ * field is quoted and separated by commas. Header rows, the "====" rule
+ * under them and adapters listed as "N/A" or "Disabled" hold no address.
+ */
+
+const GETMAC_LENGTH: usize = 17; /* "00-11-22-33-44-55" */
+
+/**
+ * getmac_address - Find the address on one line of getmac output
+ * @line: A line of getmac output, in table, list or CSV form
+ *
+ * The address is the first field that is exactly six pairs of hex digits
+ * joined by hyphens, wherever it sits, so the same code handles every
+ * column layout getmac can print. Transport names contain hex too, but
+ * never in that shape.
+ *
+ * Return: The address as printed, or None for lines without one
+ */
+pub fn getmac_address(line: &str) -> Option<&str> {
+    line.split(|c: char| c.is_whitespace() || c == ',' || c == '"')
+        .find(|field| is_hyphenated_mac(field))
+}
+
+/* Whether a field is written like "00-11-22-33-44-55" */
+fn is_hyphenated_mac(field: &str) -> bool {
+    field.len() == GETMAC_LENGTH
+        && field.bytes().enumerate().all(|(i, b)| {
+            if i % 3 == 2 {
+                b == b'-'
+            } else {
+                b.is_ascii_hexdigit()
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* Each sample lists the same three adapters with an address, CRLF as Windows writes it */
+    fn addresses(fixture: &str) -> Vec<String> {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), fixture);
+        let output = std::fs::read_to_string(path).unwrap();
+        output
+            .lines()
+            .filter_map(getmac_address)
+            .map(String::from)
+            .collect()
+    }
+
+    const EXPECTED: [&str; 3] = [
+        "00-15-5D-01-02-03",
+        "A4-BB-6D-11-22-33",
+        "0A-00-27-00-00-0C",
+    ];
+
+    #[test]
+    fn table_output() {
+        assert_eq!(addresses("getmac_table.txt"), EXPECTED);
+    }
+
+    #[test]
+    fn verbose_output() {
+        assert_eq!(addresses("getmac_verbose.txt"), EXPECTED);
+    }
+
+    #[test]
+    fn csv_output() {
+        assert_eq!(addresses("getmac_csv.txt"), EXPECTED);
+    }
+
+    #[test]
+    fn lines_without_an_address() {
+        for line in [
+            "Physical Address    Transport Name",
+            "=================== ==========================================================",
+            "N/A                 Hardware not present",
+            r"Disabled            \Device\Tcpip_{3F2504E0-4F89-11D3-9A0C-0305E82C3301}",
+            "\"Wi-Fi\",\"Intel(R) Wi-Fi 6 AX201 160MHz\",\"N/A\",\"Disconnected\"",
+            "",
+        ] {
+            assert_eq!(getmac_address(line), None, "{}", line);
+        }
+    }
+}
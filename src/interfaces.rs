@@ -0,0 +1,77 @@
+/*!
+ * interfaces.rs
+ *
+ * Listing the MAC addresses of this machine's own network interfaces.
+ */
+
+#[cfg(target_os = "linux")]
+use std::fs;
+#[cfg(not(target_os = "linux"))]
+use std::io;
+
+use crate::error::OuiError;
+
+#[cfg(target_os = "linux")]
+const SYSFS_NET: &str = "/sys/class/net"; /* One directory per interface */
+#[cfg(target_os = "linux")]
+const ARPHRD_ETHER: &str = "1"; /* Interface type for Ethernet and Wi-Fi */
+#[cfg(target_os = "linux")]
+const NULL_MAC: &str = "00:00:00:00:00:00"; /* Placeholder for "no address" */
+
+/**
+ * Interface - A network interface and its hardware address
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interface {
+    pub name: String, /* Kernel name, e.g. "eth0" */
+    pub mac: String,  /* Address as the kernel reports it, e.g. "00:11:22:33:44:55" */
+}
+
+/**
+ * local_interfaces - List the interfaces that have a MAC address
+ *
+ * SYSFS:
+ * Linux describes each interface in /sys/class/net/<name>, including its
+ * hardware type and address as plain text files. Only Ethernet-type
+ * interfaces are kept, which excludes loopback, tunnels and other links
+ * whose addresses aren't MACs, and an all-zero address counts as none.
+ *
+ * Return: Result containing the interfaces sorted by name, or an error if they can't be listed
+ */
+#[cfg(target_os = "linux")]
+pub fn local_interfaces() -> Result<Vec<Interface>, OuiError> {
+    let mut interfaces = Vec::new();
+
+    for entry in fs::read_dir(SYSFS_NET)? {
+        let path = entry?.path();
+        let read = |file: &str| fs::read_to_string(path.join(file)).map(|s| s.trim().to_string());
+
+        /* An interface can vanish between read_dir and reading its files */
+        let (Ok(kind), Ok(mac)) = (read("type"), read("address")) else {
+            continue;
+        };
+        if kind != ARPHRD_ETHER || mac.is_empty() || mac == NULL_MAC {
+            continue;
+        }
+
+        interfaces.push(Interface {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            mac,
+        });
+    }
+
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(interfaces)
+}
+
+/* Other systems need their own interface APIs, which aren't wired up yet */
+#[cfg(not(target_os = "linux"))]
+pub fn local_interfaces() -> Result<Vec<Interface>, OuiError> {
+    Err(OuiError::Io(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "listing network interfaces is only supported on Linux",
+    )))
+}
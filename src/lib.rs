@@ -0,0 +1,72 @@
+/*!
+ * lib.rs
+ *
+ * OUI (Organizationally Unique Identifier) lookup library.
+ *
+ * OVERVIEW:
+ * Matches MAC addresses to network interface card (NIC) manufacturers using
+ * the IEEE OUI database. The first 6 hexadecimal digits of a MAC address
+ * identify the manufacturer. The command-line tool in main.rs is a thin
+ * wrapper around the functions exported here.
+ *
+ * MODULES:
+ * Each "mod" declaration pulls in the file of the same name, and "pub use"
+ * re-exports its items so callers can write oui::parse_mac instead of
+ * oui::mac::parse_mac.
+ */
+
+mod cache;
+mod classify;
+mod database;
+mod error;
+mod getmac;
+mod interfaces;
+mod lookup;
+mod mac;
+mod manuf;
+mod metadata;
+mod normalize;
+mod paths;
+#[cfg(feature = "pcap")]
+mod pcap;
+mod policy;
+mod record;
+mod serve;
+mod update;
+
+pub use cache::cache_file;
+pub use classify::{
+    AddressKind, CLASSIFIER_NAMES, CastType, Classifier, Eui64, LocallyAdministeredType,
+    RandomizedMac, classifier_by_name,
+};
+pub use database::{
+    Conflict, DatabaseDiff, Entries, OuiDatabase, VendorChange, VendorCount, VendorCounts,
+    contains_oui, count_vendors, database_age, diff_databases, load_oui_map, load_oui_map_mmap,
+    load_oui_map_mmap_with, load_oui_map_with, lookup_many, lookup_stream, oui24_prefixes,
+};
+pub use error::OuiError;
+pub use getmac::getmac_address;
+pub use interfaces::{Interface, local_interfaces};
+pub use lookup::{
+    LookupResult, NEGATIVE_CACHE_SIZE, Registry, ScanLookup, ScanOptions, SearchOptions,
+    UNKNOWN_VENDOR, VENDOR_SUFFIXES, VendorMatch, VendorTable, database_head, database_tail,
+    lookup_by_prefix, lookup_by_vendor, lookup_by_vendor_with, lookup_longest_with, lookup_oui,
+    lookup_oui_with, normalize_oui_key, strip_vendor_suffix, vendorless_rows,
+};
+pub use mac::{
+    MAC_FORMATS, MacAddress, MacFormat, OUI_LENGTH, ParseOptions, format_oui, is_wildcard,
+    parse_ipv6_eui64, parse_mac, parse_mac_integer, parse_mac_with, parse_wildcard,
+};
+pub use manuf::{ManufEntry, VendorName, is_manuf, load_manuf, load_manuf_map};
+pub use metadata::{MetadataTable, VendorMetadata};
+pub use normalize::{NormalizeOptions, detect_columns, normalize_database};
+pub use paths::{
+    SYSTEM_CSV_PATH, canonical_path, csv_path_candidates, get_csv_path, resolve_csv_path,
+    update_target,
+};
+#[cfg(feature = "pcap")]
+pub use pcap::read_pcap_macs;
+pub use policy::{VendorPolicy, Verdict};
+pub use record::{AnalysisRecord, LookupRecord, lookup_record_schema};
+pub use serve::{answer, serve};
+pub use update::{IEEE_OUI_URL, ONLINE_LOOKUP_URL, download_database, lookup_online};
@@ -0,0 +1,1403 @@
+/*!
+ * lookup.rs
+ *
+ * Scanning the IEEE OUI database for a manufacturer.
+ */
+
+use std::borrow::Cow;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+
+use serde::Serialize;
+
+use crate::error::OuiError;
+use crate::mac::{MAC_HEX_LENGTH, OUI_LENGTH, SEPARATORS};
+use crate::paths::ensure_not_directory;
+
+const ELI_MASK: u8 = 0x0F; /* The quadrant is the low four bits of the first octet */
+const ELI_BITS: u8 = 0x0A; /* Locally administered unicast, in the ELI quadrant */
+
+/**
+ * LookupResult - Outcome of searching the database for an OUI
+ *
+ * A miss is not an error: the database was read successfully, it simply
+ * has no entry for the prefix. Keeping it as a value lets callers decide
+ * how to report it.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LookupResult {
+    /* The OUI is registered to this manufacturer */
+    Found(String),
+    /* The OUI is not present in the database */
+    NotFound,
+    /* The scan gave up after this many rows, so the OUI may still be further down */
+    ScanLimit(usize),
+}
+
+/**
+ * UNKNOWN_VENDOR - The manufacturer reported for a row with no vendor column
+ */
+pub const UNKNOWN_VENDOR: &str = "Unknown vendor.";
+
+/**
+ * VENDOR_SUFFIXES - Corporate suffixes strip_vendor_suffix removes by default
+ *
+ * Written without trailing dots, since those are trimmed before matching.
+ */
+pub const VENDOR_SUFFIXES: &[&str] = &[
+    "Inc",
+    "Incorporated",
+    "Ltd",
+    "Limited",
+    "Co",
+    "Corp",
+    "Corporation",
+    "Company",
+    "LLC",
+    "L.L.C",
+    "GmbH",
+    "AG",
+    "KG",
+    "S.A",
+    "SA",
+    "S.p.A",
+    "SpA",
+    "S.R.L",
+    "SRL",
+    "B.V",
+    "BV",
+    "N.V",
+    "NV",
+    "Pty",
+    "PLC",
+    "AB",
+    "Oy",
+    "A/S",
+    "K.K",
+];
+
+/**
+ * strip_vendor_suffix - Remove corporate suffixes from a vendor name
+ * @vendor: Manufacturer name as the database lists it
+ * @suffixes: Suffixes to remove, compared ignoring case
+ *
+ * Suffixes are removed repeatedly, so "Samsung Electronics Co.,Ltd" loses
+ * both "Ltd" and "Co". A suffix only counts as a whole word after a space
+ * or comma, and a name is never stripped down to nothing.
+ *
+ * Return: The name without its suffixes or the separators before them
+ */
+pub fn strip_vendor_suffix<'a, S: AsRef<str>>(vendor: &'a str, suffixes: &[S]) -> &'a str {
+    let trim = |name: &'a str| name.trim_end_matches([' ', ',', '.']);
+    let mut name = trim(vendor.trim());
+    loop {
+        let stripped = suffixes.iter().find_map(|suffix| {
+            let suffix = suffix.as_ref();
+            let cut = name.len().checked_sub(suffix.len())?;
+            let (rest, tail) = (name.get(..cut)?, name.get(cut..)?);
+            let whole_word = rest.ends_with([' ', ',']);
+            (tail.eq_ignore_ascii_case(suffix) && whole_word && !trim(rest).is_empty())
+                .then(|| trim(rest))
+        });
+        match stripped {
+            Some(rest) => name = rest,
+            None => return name,
+        }
+    }
+}
+
+/**
+ * Display for LookupResult
+ *
+ * Prints the manufacturer name for a hit and "No match." for a miss, which
+ * is exactly what the command-line tool writes to stdout. A scan cut short
+ * by ScanOptions::max_rows says so instead, since it isn't a real miss.
+ */
+impl fmt::Display for LookupResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LookupResult::Found(vendor) => write!(f, "{}", vendor),
+            LookupResult::NotFound => write!(f, "No match."),
+            LookupResult::ScanLimit(1) => write!(f, "Not found within scan limit (1 row)."),
+            LookupResult::ScanLimit(rows) => {
+                write!(f, "Not found within scan limit ({} rows).", rows)
+            }
+        }
+    }
+}
+
+/**
+ * Registry - The IEEE registry an assignment was made from
+ *
+ * BLOCK SIZES:
+ * The IEEE hands out three sizes of block. An MA-L (the classic OUI) fixes
+ * the first 24 bits and leaves 2^24 addresses to the vendor, an MA-M fixes
+ * 28 and leaves 2^20, and an MA-S fixes 36 and leaves 2^12. Smaller blocks
+ * are carved out of MA-L ranges the IEEE keeps for the purpose, so the
+ * longest matching prefix is the real assignee.
+ *
+ * COMPANY IDS:
+ * A CID is a 24-bit identifier the IEEE issues from the locally
+ * administered range, with a first octet ending in hex A (the ELI
+ * quadrant). It lets a company build local addresses, but doesn't make
+ * them globally unique, so a CID hit is reported apart from MA-L hits.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Registry {
+    #[serde(rename = "MA-L")]
+    MaL,
+    #[serde(rename = "MA-M")]
+    MaM,
+    #[serde(rename = "MA-S")]
+    MaS,
+    #[serde(rename = "CID")]
+    Cid,
+}
+
+impl Registry {
+    /**
+     * LONGEST_FIRST - Every registry, in the order prefixes should be tried
+     */
+    pub const LONGEST_FIRST: [Registry; 3] = [Registry::MaS, Registry::MaM, Registry::MaL];
+
+    /**
+     * prefix_digits - How many hex digits of the address the assignment fixes
+     */
+    pub fn prefix_digits(self) -> usize {
+        match self {
+            Registry::MaL | Registry::Cid => OUI_LENGTH,
+            Registry::MaM => 7,
+            Registry::MaS => 9,
+        }
+    }
+
+    /**
+     * block_size - How many addresses one assignment covers
+     *
+     * Return: 2^24, 2^20 or 2^12, for MA-L, MA-M and MA-S
+     */
+    pub fn block_size(self) -> u64 {
+        1 << ((MAC_HEX_LENGTH - self.prefix_digits()) * 4)
+    }
+
+    /**
+     * name - The registry's IEEE name, e.g. "MA-L"
+     */
+    pub fn name(self) -> &'static str {
+        match self {
+            Registry::MaL => "MA-L",
+            Registry::MaM => "MA-M",
+            Registry::MaS => "MA-S",
+            Registry::Cid => "CID",
+        }
+    }
+
+    /**
+     * for_hit - The registry to report for a prefix that matched
+     * @full: The address's hex digits, as found in MacAddress::full
+     *
+     * The database doesn't say which registry a row came from, and CIDs
+     * are listed as 24-bit prefixes like MA-L ones, so they are told apart
+     * by their first octet.
+     *
+     * Return: Cid for an MA-L match in the ELI quadrant, otherwise the registry unchanged
+     */
+    pub fn for_hit(self, full: &str) -> Registry {
+        let eli = full
+            .get(..2)
+            .and_then(|octet| u8::from_str_radix(octet, 16).ok())
+            .is_some_and(|octet| octet & ELI_MASK == ELI_BITS);
+        if self == Registry::MaL && eli {
+            Registry::Cid
+        } else {
+            self
+        }
+    }
+
+    /**
+     * from_name - The registry with an IEEE name, ignoring case
+     * @name: "MA-L", "MA-M" or "MA-S"
+     *
+     * Return: The registry, or None for any other name
+     */
+    pub fn from_name(name: &str) -> Option<Registry> {
+        Registry::LONGEST_FIRST
+            .into_iter()
+            .find(|registry| registry.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+impl fmt::Display for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/**
+ * database_reader - CSV reader settings matching the database format
+ *
+ * BUILDER PATTERN:
+ * ReaderBuilder collects settings through chained method calls and then
+ * produces a reader with from_path() or from_reader(). Returning the
+ * configured builder lets every caller read the database the same way,
+ * whether it comes from a file or from memory.
+ *
+ * - b';' is a byte literal (semicolon as u8) for the custom delimiter
+ * - The database has no header row, so the first line is a record too
+ * - flexible() tolerates rows with a missing manufacturer column
+ */
+pub(crate) fn database_reader() -> csv::ReaderBuilder {
+    let mut builder = csv::ReaderBuilder::new();
+    builder.delimiter(b';').has_headers(false).flexible(true);
+    builder
+}
+
+/*
+ * open_database - Open a database file with the database_reader settings
+ * @csv_path: Path to the IEEE OUI CSV file
+ *
+ * Return: Result containing the reader, or an error if the path is a directory or can't be opened
+ */
+pub(crate) fn open_database(csv_path: &str) -> Result<csv::Reader<File>, OuiError> {
+    ensure_not_directory(csv_path)?;
+    Ok(database_reader().from_path(csv_path)?)
+}
+
+/*
+ * database_records - The rows of a database reader, with located errors
+ * @rdr: Reader from open_database or database_reader
+ * @path: File being read, for error messages
+ *
+ * QUOTING:
+ * A vendor name holding the delimiter or a quote is written quoted, as in
+ * 000001;"Smith; Jones Ltd", and the reader handles that on its own. A
+ * quote that is opened and never closed is different: the CSV format lets
+ * a quoted field span lines, so the reader quietly swallows the rest of
+ * the file into one vendor name. No database row spans lines, so a field
+ * with a line break in it is reported as a MalformedRow at the line the
+ * quote was opened, and so are the reader's own errors, like invalid
+ * UTF-8, that know their position.
+ */
+pub(crate) fn database_records<'a, R: Read>(
+    rdr: &'a mut csv::Reader<R>,
+    path: &'a str,
+) -> impl Iterator<Item = Result<csv::StringRecord, OuiError>> + 'a {
+    rdr.records().map(move |result| {
+        let malformed = |line: u64, reason: String| OuiError::MalformedRow {
+            path: path.to_string(),
+            line,
+            reason,
+        };
+        match result {
+            Ok(record) if record.iter().any(|field| field.contains(['\n', '\r'])) => {
+                let line = record.position().map_or(0, |p| p.line());
+                Err(malformed(
+                    line,
+                    "a quote opened on this line is never closed".to_string(),
+                ))
+            }
+            Ok(record) => Ok(record),
+            Err(e) => match (e.position().map(|p| p.line()), e.kind()) {
+                (Some(line), csv::ErrorKind::Utf8 { err, .. }) => Err(malformed(
+                    line,
+                    format!("field {} is not valid UTF-8", err.field() + 1),
+                )),
+                (Some(line), _) => Err(malformed(line, e.to_string())),
+                (None, _) => Err(e.into()),
+            },
+        }
+    })
+}
+
+/**
+ * normalize_oui_key - Bring an OUI into the form lookups compare
+ * @key: OUI as stored in a database or given in a query
+ *
+ * Every comparison between a query and a database key goes through this,
+ * on both sides, so "aa-bb-cc", "0xAABBCC" and "AABBCC000000" all match a
+ * row written "AABBCC". See normalize_db_key for the rules.
+ *
+ * Return: The normalized key, e.g. "AABBCC"
+ */
+pub fn normalize_oui_key(key: &str) -> String {
+    normalize_db_key(key).into_owned()
+}
+
+/*
+ * normalize_db_key - Bring a database OUI column into the form parse_mac produces
+ * @key: OUI as stored in the database
+ *
+ * Exports differ in how they write the key: "AABBCC", "aa-bb-cc", "AA BB CC",
+ * "0xAABBCC" from a tool that stores prefixes as integers, padded to a
+ * column width like " AABBCC\t", or a full zero-padded address like
+ * "AABBCC000000". Surrounding whitespace, a 0x prefix and separators are
+ * removed, letters uppercased, and a 12-digit key ending in six zeros is
+ * cut to its OUI.
+ * Any other length is left alone so longer MA-M and MA-S keys stay
+ * distinct from the MA-L block they belong to.
+ *
+ * COW:
+ * Cow (clone on write) holds either a borrowed &str or an owned String.
+ * Nearly every key is already normalized, so it is passed through without
+ * allocating, and a new String is only built for keys that need changing.
+ * Trimming and dropping the 0x only narrow the borrowed slice.
+ */
+pub(crate) fn normalize_db_key(key: &str) -> Cow<'_, str> {
+    let key = key.trim();
+    let key = key
+        .strip_prefix("0x")
+        .or_else(|| key.strip_prefix("0X"))
+        .unwrap_or(key);
+    let padded =
+        key.len() == MAC_HEX_LENGTH && key.as_bytes()[OUI_LENGTH..].iter().all(|&b| b == b'0');
+    if !padded && !key.contains(SEPARATORS) && !key.bytes().any(|b| b.is_ascii_lowercase()) {
+        return Cow::Borrowed(key);
+    }
+
+    let mut cleaned: String = key
+        .chars()
+        .filter(|c| !SEPARATORS.contains(c))
+        .collect::<String>()
+        .to_ascii_uppercase();
+    if cleaned.len() == MAC_HEX_LENGTH
+        && cleaned.as_bytes()[OUI_LENGTH..].iter().all(|&b| b == b'0')
+    {
+        cleaned.truncate(OUI_LENGTH);
+    }
+    Cow::Owned(cleaned)
+}
+
+/**
+ * ScanOptions - Settings that change how lookup_oui_with reads the database
+ *
+ * The Default impl gives the full scan of lookup_oui.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScanOptions {
+    /* Stop after this many rows, answering ScanLimit instead of NotFound */
+    pub max_rows: Option<usize>,
+}
+
+/**
+ * lookup_oui - Search the IEEE OUI database for a matching manufacturer
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @mac: The 6-character OUI to search for
+ *
+ * Equivalent to lookup_oui_with() using the default ScanOptions.
+ *
+ * Return: Result containing the lookup outcome, or an error if the database can't be read or is empty
+ */
+pub fn lookup_oui(csv_path: &str, mac: &str) -> Result<LookupResult, OuiError> {
+    lookup_oui_with(csv_path, mac, &ScanOptions::default())
+}
+
+/**
+ * lookup_oui_with - Search the database for a manufacturer using the given options
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @mac: The 6-character OUI to search for
+ * @options: Scan settings
+ *
+ * CSV FORMAT
+ * The database is a semicolon-delimited CSV file:
+ * Column 0: OUI
+ * Column 1: Manufacturer name
+ *
+ * Keys are compared after normalize_oui_key(), so a database that writes
+ * them as "aa-bb-cc", "aAbBcC", "0xAABBCC" or "AABBCC000000" still
+ * matches. The OUI goes through the same function, in case it didn't come
+ * from parse_mac.
+ *
+ * A database without a single row (a truncated download, say) would make
+ * every lookup a quiet miss, so it is reported as EmptyDatabase instead.
+ *
+ * SCAN LIMIT:
+ * With max_rows set, the scan stops once that many rows have been checked
+ * and another one follows, and the answer is ScanLimit rather than
+ * NotFound: the OUI wasn't in the rows read, but may be in the rest. A
+ * database no longer than the limit is read to its end as usual, so its
+ * misses are still definite.
+ *
+ * Return: Result containing the lookup outcome, or an error if the database can't be read or is empty
+ */
+pub fn lookup_oui_with(
+    csv_path: &str,
+    mac: &str,
+    options: &ScanOptions,
+) -> Result<LookupResult, OuiError> {
+    let mut rdr = open_database(csv_path)?;
+    let mut rows = 0;
+    let mac = normalize_oui_key(mac);
+
+    /*
+     * Iterate through each record in the CSV
+     * records() returns an iterator over Result<StringRecord, Error>
+     */
+    for result in database_records(&mut rdr, csv_path) {
+        let record = result?; // Propagate any read errors
+        if options.max_rows == Some(rows) {
+            return Ok(LookupResult::ScanLimit(rows));
+        }
+        rows += 1;
+        if record.get(0).map(normalize_db_key).as_deref() == Some(mac.as_str()) {
+            /* Manufacturer name is the second column.
+             * unwrap_or provides a default if column doesn't exist
+             */
+            let vendor = record.get(1).unwrap_or(UNKNOWN_VENDOR);
+            return Ok(LookupResult::Found(vendor.to_string()));
+        }
+    }
+
+    if rows == 0 {
+        return Err(OuiError::EmptyDatabase(csv_path.to_string()));
+    }
+
+    /* No match found after searching entire database */
+    Ok(LookupResult::NotFound)
+}
+
+/**
+ * lookup_longest_with - Scan the database for the longest prefix of an address
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @full: The address's hex digits, as found in MacAddress::full
+ * @options: Scan settings
+ *
+ * LONGEST PREFIX:
+ * A database mixing registries can list an MA-L block and an MA-M or
+ * MA-S block inside it, and the longer key is the one that was assigned
+ * to the address, as OuiDatabase::lookup_longest decides. Keys are
+ * compared after normalize_oui_key(), and only 6, 7 and 9 digit keys can
+ * match. A shorter hit doesn't end the scan, since a longer key may come
+ * later in the file, so a hit costs a whole scan unless it is an MA-S
+ * key; for the same length, the first row wins, as with lookup_oui_with.
+ *
+ * When max_rows stops the scan, a hit found so far is still the answer,
+ * and only an address with no hit at all gets ScanLimit.
+ *
+ * Return: Result containing the lookup outcome and the registry of the key that matched, or an error if the database can't be read or is empty
+ */
+pub fn lookup_longest_with(
+    csv_path: &str,
+    full: &str,
+    options: &ScanOptions,
+) -> Result<(LookupResult, Option<Registry>), OuiError> {
+    let (result, registry, _) = scan_longest(csv_path, full, options)?;
+    Ok((result, registry))
+}
+
+/*
+ * scan_longest - The scan behind lookup_longest_with
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @full: The address's hex digits, or just its OUI
+ * @options: Scan settings
+ *
+ * Also says whether any key longer than an OUI starts with the address's
+ * OUI, since ScanLookup can only remember a miss for the whole OUI when
+ * none does.
+ */
+fn scan_longest(
+    csv_path: &str,
+    full: &str,
+    options: &ScanOptions,
+) -> Result<(LookupResult, Option<Registry>, bool), OuiError> {
+    let mut rdr = open_database(csv_path)?;
+    let mut rows = 0;
+    let full = normalize_oui_key(full);
+    let oui = full.get(..OUI_LENGTH).unwrap_or(&full);
+    let mut best: Option<(Registry, String)> = None;
+    let mut longer_keys = false;
+
+    for result in database_records(&mut rdr, csv_path) {
+        let record = result?;
+        if options.max_rows == Some(rows) {
+            return Ok(match best {
+                Some((registry, vendor)) => (LookupResult::Found(vendor), Some(registry), true),
+                None => (LookupResult::ScanLimit(rows), None, true),
+            });
+        }
+        rows += 1;
+        let Some(key) = record.get(0).map(normalize_db_key) else {
+            continue;
+        };
+        if key.len() > OUI_LENGTH && key.starts_with(oui) {
+            longer_keys = true;
+        }
+        let Some(registry) = Registry::LONGEST_FIRST
+            .into_iter()
+            .find(|registry| registry.prefix_digits() == key.len())
+        else {
+            continue;
+        };
+        let longer = best
+            .as_ref()
+            .is_none_or(|(found, _)| registry.prefix_digits() > found.prefix_digits());
+        if longer && full.starts_with(key.as_ref()) {
+            let vendor = record.get(1).unwrap_or(UNKNOWN_VENDOR).to_string();
+            if registry == Registry::LONGEST_FIRST[0] {
+                return Ok((LookupResult::Found(vendor), Some(registry), true));
+            }
+            best = Some((registry, vendor));
+        }
+    }
+
+    if rows == 0 {
+        return Err(OuiError::EmptyDatabase(csv_path.to_string()));
+    }
+    Ok(match best {
+        Some((registry, vendor)) => (LookupResult::Found(vendor), Some(registry), longer_keys),
+        None => (LookupResult::NotFound, None, longer_keys),
+    })
+}
+
+/**
+ * NEGATIVE_CACHE_SIZE - How many absent OUIs a ScanLookup remembers
+ */
+pub const NEGATIVE_CACHE_SIZE: usize = 1024;
+
+/**
+ * ScanLookup - Repeated lookup_oui scans that remember which OUIs are absent
+ *
+ * NEGATIVE CACHE:
+ * Without an index, a hit ends its scan early but a miss reads the whole
+ * file, and batches of unknown addresses (randomized MACs, say) tend to
+ * repeat. Misses are kept in a small set so a repeated unknown OUI is
+ * answered without another scan. The set holds at most its capacity;
+ * once full, the oldest miss makes room, tracked by a VecDeque in the
+ * order they were added. Hits aren't cached: they are cheap already, and
+ * keeping vendors around is what this mode exists to avoid.
+ */
+#[derive(Debug, Clone)]
+pub struct ScanLookup {
+    csv_path: String,
+    misses: HashSet<String>,
+    order: VecDeque<String>,
+    capacity: usize,
+    scans: usize,
+    options: ScanOptions,
+}
+
+impl ScanLookup {
+    /**
+     * new - Scan a database with the default cache size
+     * @csv_path: Path to the IEEE OUI CSV file
+     */
+    pub fn new(csv_path: &str) -> Self {
+        Self::with_capacity(csv_path, NEGATIVE_CACHE_SIZE)
+    }
+
+    /**
+     * with_capacity - Scan a database, remembering up to capacity misses
+     * @csv_path: Path to the IEEE OUI CSV file
+     * @capacity: Most absent OUIs to keep; 0 disables the cache
+     */
+    pub fn with_capacity(csv_path: &str, capacity: usize) -> Self {
+        Self::with_options(csv_path, capacity, ScanOptions::default())
+    }
+
+    /**
+     * with_options - Scan a database with the given cache size and scan settings
+     * @csv_path: Path to the IEEE OUI CSV file
+     * @capacity: Most absent OUIs to keep; 0 disables the cache
+     * @options: Settings passed to every lookup_oui_with scan
+     *
+     * Only NotFound answers are cached; a ScanLimit answer isn't a miss.
+     */
+    pub fn with_options(csv_path: &str, capacity: usize, options: ScanOptions) -> Self {
+        ScanLookup {
+            csv_path: csv_path.to_string(),
+            misses: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+            scans: 0,
+            options,
+        }
+    }
+
+    /**
+     * lookup - Find the manufacturer for an OUI
+     * @oui: The 6-character OUI to search for
+     *
+     * Return: Result containing the lookup outcome, or an error if the database can't be read or is empty
+     */
+    pub fn lookup(&mut self, oui: &str) -> Result<LookupResult, OuiError> {
+        Ok(self.lookup_longest(oui)?.0)
+    }
+
+    /**
+     * lookup_longest - Find the manufacturer for an address, as lookup_longest_with does
+     * @full: The address's hex digits, as found in MacAddress::full
+     *
+     * A miss is remembered for the address's whole OUI only when the
+     * database has no longer key under that OUI, since another address in
+     * the same MA-L block could still match an MA-M or MA-S one.
+     *
+     * Return: Result containing the lookup outcome and the registry of the key that matched, or an error if the database can't be read or is empty
+     */
+    pub fn lookup_longest(
+        &mut self,
+        full: &str,
+    ) -> Result<(LookupResult, Option<Registry>), OuiError> {
+        let oui = full.get(..OUI_LENGTH).unwrap_or(full);
+        if self.misses.contains(oui) {
+            return Ok((LookupResult::NotFound, None));
+        }
+        self.scans += 1;
+        let (result, registry, longer_keys) = scan_longest(&self.csv_path, full, &self.options)?;
+        if result == LookupResult::NotFound && !longer_keys && self.capacity > 0 {
+            if self.order.len() == self.capacity
+                && let Some(oldest) = self.order.pop_front()
+            {
+                self.misses.remove(&oldest);
+            }
+            self.misses.insert(oui.to_string());
+            self.order.push_back(oui.to_string());
+        }
+        Ok((result, registry))
+    }
+
+    /**
+     * scans - How many times the database file has been read so far
+     */
+    pub fn scans(&self) -> usize {
+        self.scans
+    }
+}
+
+/**
+ * VendorMatch - One database row whose manufacturer matched a search
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VendorMatch {
+    pub oui: String,    /* OUI as stored in the database */
+    pub vendor: String, /* Manufacturer name */
+}
+
+/**
+ * SearchOptions - Settings that change how lookup_by_vendor_with matches
+ *
+ * The Default impl gives the plain substring search of lookup_by_vendor.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchOptions {
+    /* Return the rows whose manufacturer does NOT match instead */
+    pub invert: bool,
+}
+
+/**
+ * lookup_by_vendor - Find every OUI registered to a matching manufacturer
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @query: Text to look for in the manufacturer name
+ *
+ * Equivalent to lookup_by_vendor_with() using the default SearchOptions.
+ *
+ * Return: Result containing the matching rows in database order, or an error if the database can't be read or is empty
+ */
+pub fn lookup_by_vendor(csv_path: &str, query: &str) -> Result<Vec<VendorMatch>, OuiError> {
+    lookup_by_vendor_with(csv_path, query, &SearchOptions::default())
+}
+
+/**
+ * lookup_by_vendor_with - Find OUIs by manufacturer using the given options
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @query: Text to look for in the manufacturer name
+ * @options: Search settings
+ *
+ * The match is a case-insensitive substring search, so "apple" finds
+ * "Apple, Inc." as well as every other Apple entry. With invert set the
+ * test is negated, so "cisco" finds everything except Cisco; rows with no
+ * manufacturer column count as not matching.
+ *
+ * Return: Result containing the matching rows in database order, or an error if the database can't be read or is empty
+ */
+pub fn lookup_by_vendor_with(
+    csv_path: &str,
+    query: &str,
+    options: &SearchOptions,
+) -> Result<Vec<VendorMatch>, OuiError> {
+    let mut rdr = open_database(csv_path)?;
+
+    let query = query.to_lowercase();
+    let mut matches = Vec::new();
+    let mut rows = 0;
+
+    for result in database_records(&mut rdr, csv_path) {
+        let record = result?;
+        rows += 1;
+        let Some(oui) = record.get(0) else {
+            continue;
+        };
+        let vendor = record.get(1);
+        if vendor_hit(vendor, &query, options) {
+            matches.push(VendorMatch {
+                oui: oui.to_string(),
+                vendor: vendor.unwrap_or(UNKNOWN_VENDOR).to_string(),
+            });
+        }
+    }
+
+    if rows == 0 {
+        return Err(OuiError::EmptyDatabase(csv_path.to_string()));
+    }
+    Ok(matches)
+}
+
+/*
+ * vendor_hit - Whether a row's manufacturer satisfies a search
+ * @vendor: The row's manufacturer column, if it has one
+ * @query: The search text, already lowercased
+ * @options: Search settings
+ */
+fn vendor_hit(vendor: Option<&str>, query: &str, options: &SearchOptions) -> bool {
+    let hit = vendor.is_some_and(|v| v.to_lowercase().contains(query));
+    hit != options.invert
+}
+
+/**
+ * VendorTable - The database rows kept in file order for repeated searches
+ *
+ * lookup_by_vendor reads the file for every query, which is fine once but
+ * adds up over a list of them. This reads it once, and search() then
+ * matches exactly like lookup_by_vendor_with without touching the disk.
+ */
+#[derive(Debug, Clone)]
+pub struct VendorTable {
+    rows: Vec<(String, Option<String>)>,
+}
+
+impl VendorTable {
+    /**
+     * open - Load every row of a database
+     * @csv_path: Path to the IEEE OUI CSV file
+     *
+     * Return: Result containing the table, or an error if the database can't be read or is empty
+     */
+    pub fn open(csv_path: &str) -> Result<Self, OuiError> {
+        let mut rdr = open_database(csv_path)?;
+        let mut rows = Vec::new();
+        for result in database_records(&mut rdr, csv_path) {
+            let record = result?;
+            if let Some(oui) = record.get(0) {
+                rows.push((oui.to_string(), record.get(1).map(String::from)));
+            }
+        }
+        if rows.is_empty() {
+            return Err(OuiError::EmptyDatabase(csv_path.to_string()));
+        }
+        Ok(VendorTable { rows })
+    }
+
+    /**
+     * search - Find OUIs by manufacturer
+     * @query: Text to look for in the manufacturer name
+     * @options: Search settings, as for lookup_by_vendor_with
+     *
+     * Return: The matching rows in database order
+     */
+    pub fn search(&self, query: &str, options: &SearchOptions) -> Vec<VendorMatch> {
+        let query = query.to_lowercase();
+        self.rows
+            .iter()
+            .filter(|(_, vendor)| vendor_hit(vendor.as_deref(), &query, options))
+            .map(|(oui, vendor)| VendorMatch {
+                oui: oui.clone(),
+                vendor: vendor.as_deref().unwrap_or(UNKNOWN_VENDOR).to_string(),
+            })
+            .collect()
+    }
+
+    /**
+     * closest - The known OUI nearest to one that isn't in the database
+     * @oui: The 6-character OUI that missed, as found in MacAddress::oui
+     *
+     * A miss is often a typo or a damaged address, one digit away from a
+     * real assignment. OUIs sharing the first five digits are tried first,
+     * then those sharing four, and the numerically nearest wins.
+     *
+     * Return: The nearest OUI and its vendor, or None if no OUI shares four digits
+     */
+    pub fn closest(&self, oui: &str) -> Option<VendorMatch> {
+        let target = u32::from_str_radix(oui, 16).ok()?;
+        for digits in [OUI_LENGTH - 1, OUI_LENGTH - 2] {
+            let shared = oui.get(..digits)?;
+            let nearest = self
+                .rows
+                .iter()
+                .filter_map(|(key, vendor)| {
+                    let key = normalize_db_key(key);
+                    if key.len() != OUI_LENGTH || !key.starts_with(shared) || key == oui {
+                        return None;
+                    }
+                    let value = u32::from_str_radix(&key, 16).ok()?;
+                    Some((value.abs_diff(target), key.into_owned(), vendor))
+                })
+                .min_by_key(|(distance, _, _)| *distance);
+            if let Some((_, key, vendor)) = nearest {
+                return Some(VendorMatch {
+                    oui: key,
+                    vendor: vendor.as_deref().unwrap_or(UNKNOWN_VENDOR).to_string(),
+                });
+            }
+        }
+        None
+    }
+
+    /**
+     * assignments - Every row whose prefix covers an address
+     * @full: The address's hex digits, as found in MacAddress::full
+     *
+     * A merged database can list the same prefix more than once, and an
+     * MA-S or MA-M block sits inside an MA-L row, so several vendors may
+     * claim one address. Rows are returned most specific first, then in
+     * file order, with keys compared after normalize_db_key().
+     *
+     * Return: The covering rows, empty when nothing matches
+     */
+    pub fn assignments(&self, full: &str) -> Vec<VendorMatch> {
+        self.assignments_in(full, &Registry::LONGEST_FIRST)
+    }
+
+    /**
+     * assignments_in - Like assignments, but only from some registries
+     * @full: The address's hex digits, as found in MacAddress::full
+     * @registries: Which registries' rows may match, in any order
+     *
+     * Return: The covering rows from those registries, most specific first
+     */
+    pub fn assignments_in(&self, full: &str, registries: &[Registry]) -> Vec<VendorMatch> {
+        let mut found = Vec::new();
+        for registry in Registry::LONGEST_FIRST
+            .into_iter()
+            .filter(|registry| registries.contains(registry))
+        {
+            let Some(prefix) = full.get(..registry.prefix_digits()) else {
+                continue;
+            };
+            for (oui, vendor) in &self.rows {
+                if normalize_db_key(oui) == prefix {
+                    found.push(VendorMatch {
+                        oui: oui.clone(),
+                        vendor: vendor.as_deref().unwrap_or(UNKNOWN_VENDOR).to_string(),
+                    });
+                }
+            }
+        }
+        found
+    }
+}
+
+/**
+ * lookup_by_prefix - Find every OUI that overlaps a hex prefix
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @prefix: Uppercase hex digits, as returned by parse_wildcard
+ *
+ * A row matches when its key starts with the prefix ("0011" finds every
+ * OUI in 00:11:xx) or when the prefix starts with its key, so a pattern
+ * narrower than an OUI, like 00:11:22:3?, still finds the 001122 block
+ * it falls in. Keys shorter than an OUI are damaged and only match the
+ * first way.
+ *
+ * Return: Result containing the matching rows in database order, or an error if the database can't be read or is empty
+ */
+pub fn lookup_by_prefix(csv_path: &str, prefix: &str) -> Result<Vec<VendorMatch>, OuiError> {
+    let mut rdr = open_database(csv_path)?;
+    let mut matches = Vec::new();
+    let mut rows = 0;
+
+    for result in database_records(&mut rdr, csv_path) {
+        let record = result?;
+        rows += 1;
+        let Some(key) = record.get(0).map(normalize_db_key) else {
+            continue;
+        };
+        if key.starts_with(prefix) || (key.len() >= OUI_LENGTH && prefix.starts_with(&*key)) {
+            matches.push(vendor_match(&record));
+        }
+    }
+
+    if rows == 0 {
+        return Err(OuiError::EmptyDatabase(csv_path.to_string()));
+    }
+    Ok(matches)
+}
+
+/**
+ * database_head - The first rows of the database, in file order
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @count: How many rows to return
+ *
+ * Reading stops as soon as enough rows have been seen.
+ *
+ * Return: Result containing up to count rows, or an error if the database can't be read
+ */
+pub fn database_head(csv_path: &str, count: usize) -> Result<Vec<VendorMatch>, OuiError> {
+    let mut rdr = open_database(csv_path)?;
+    let mut rows = Vec::new();
+    for result in database_records(&mut rdr, csv_path).take(count) {
+        rows.push(vendor_match(&result?));
+    }
+    Ok(rows)
+}
+
+/**
+ * database_tail - The last rows of the database, in file order
+ * @csv_path: Path to the IEEE OUI CSV file
+ * @count: How many rows to return
+ *
+ * RING BUFFER:
+ * The CSV reader can only go forwards, so the whole file is read once
+ * while a VecDeque holds the most recent rows: each new row is pushed on
+ * the back and, once there are count of them, the oldest falls off the
+ * front. Memory stays proportional to count rather than to the file, and
+ * there's no second pass to count rows first.
+ *
+ * Return: Result containing up to count rows, or an error if the database can't be read
+ */
+pub fn database_tail(csv_path: &str, count: usize) -> Result<Vec<VendorMatch>, OuiError> {
+    let mut rdr = open_database(csv_path)?;
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let mut ring = VecDeque::with_capacity(count);
+    for result in database_records(&mut rdr, csv_path) {
+        if ring.len() == count {
+            ring.pop_front();
+        }
+        ring.push_back(vendor_match(&result?));
+    }
+    Ok(ring.into())
+}
+
+/**
+ * vendorless_rows - Find the rows that have no manufacturer column at all
+ * @csv_path: Path to the IEEE OUI CSV file
+ *
+ * Lookups report such a row as UNKNOWN_VENDOR, which reads like an answer
+ * but means the row is broken, usually a lost delimiter. This names the
+ * rows so the damage can be found; an empty vendor after the delimiter is
+ * a real, if unhelpful, entry and isn't included.
+ *
+ * Return: Result containing each row's key, after normalize_db_key(), and its line number, in file order
+ */
+pub fn vendorless_rows(csv_path: &str) -> Result<Vec<(String, u64)>, OuiError> {
+    let mut rdr = open_database(csv_path)?;
+    let mut rows = Vec::new();
+    for result in database_records(&mut rdr, csv_path) {
+        let record = result?;
+        if record.len() < 2
+            && let Some(key) = record.get(0)
+        {
+            let line = record.position().map_or(0, |p| p.line());
+            rows.push((normalize_db_key(key).into_owned(), line));
+        }
+    }
+    Ok(rows)
+}
+
+/* Turn a raw database row into a VendorMatch, as lookup_oui would report it */
+fn vendor_match(record: &csv::StringRecord) -> VendorMatch {
+    VendorMatch {
+        oui: record.get(0).unwrap_or_default().to_string(),
+        vendor: record.get(1).unwrap_or(UNKNOWN_VENDOR).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_result_display() {
+        assert_eq!(
+            LookupResult::Found("Cisco Systems, Inc".to_string()).to_string(),
+            "Cisco Systems, Inc"
+        );
+        assert_eq!(LookupResult::NotFound.to_string(), "No match.");
+        assert_eq!(
+            LookupResult::ScanLimit(1).to_string(),
+            "Not found within scan limit (1 row)."
+        );
+        assert_eq!(
+            LookupResult::ScanLimit(500).to_string(),
+            "Not found within scan limit (500 rows)."
+        );
+    }
+
+    const EMPTY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/empty.csv");
+
+    #[test]
+    fn scanning_an_empty_database_is_an_error() {
+        assert!(matches!(
+            lookup_oui(EMPTY, "00000C"),
+            Err(OuiError::EmptyDatabase(_))
+        ));
+    }
+
+    /* The fixture's AABBCC row, found from a query in the usual parse_mac form */
+    fn key_format(fixture: &str, vendor: &str) {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), fixture);
+        assert_eq!(
+            lookup_oui(&path, "AABBCC").unwrap(),
+            LookupResult::Found(vendor.to_string())
+        );
+        assert_eq!(
+            lookup_oui(&path, "00000C").unwrap(),
+            LookupResult::Found("Cisco Systems, Inc".to_string())
+        );
+        assert_eq!(lookup_oui(&path, "001122").unwrap(), LookupResult::NotFound);
+    }
+
+    #[test]
+    fn plain_keys() {
+        key_format("keys_plain.csv", "Plain Key Ltd");
+    }
+
+    #[test]
+    fn dashed_keys() {
+        key_format("keys_dashed.csv", "Dashed Key Ltd");
+    }
+
+    #[test]
+    fn padded_keys() {
+        key_format("keys_padded.csv", "Padded Key Ltd");
+    }
+
+    const BASIC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/basic.csv");
+
+    #[test]
+    fn repeated_unknowns_scan_once() {
+        let mut scan = ScanLookup::new(BASIC);
+        for _ in 0..5 {
+            assert_eq!(scan.lookup("AABBCC").unwrap(), LookupResult::NotFound);
+        }
+        assert_eq!(scan.scans(), 1);
+
+        /* Hits aren't cached, since the index they'd need is what this avoids */
+        for _ in 0..3 {
+            assert!(matches!(
+                scan.lookup("00000C").unwrap(),
+                LookupResult::Found(_)
+            ));
+        }
+        assert_eq!(scan.scans(), 4);
+    }
+
+    #[test]
+    fn negative_cache_is_bounded() {
+        let mut scan = ScanLookup::with_capacity(BASIC, 1);
+        for oui in ["AABBCC", "DDEEFF", "AABBCC"] {
+            scan.lookup(oui).unwrap();
+        }
+        assert_eq!(scan.scans(), 3);
+
+        let mut uncached = ScanLookup::with_capacity(BASIC, 0);
+        uncached.lookup("AABBCC").unwrap();
+        uncached.lookup("AABBCC").unwrap();
+        assert_eq!(uncached.scans(), 2);
+    }
+
+    #[test]
+    fn cid_detected_from_the_first_octet() {
+        for full in ["0A1B2C000001", "4A1B2C000001", "FA0000000000"] {
+            assert_eq!(Registry::MaL.for_hit(full), Registry::Cid, "{}", full);
+        }
+        for full in ["00000C000001", "021B2C000001", "0B1B2C000001"] {
+            assert_eq!(Registry::MaL.for_hit(full), Registry::MaL, "{}", full);
+        }
+        /* Only 24-bit matches can be CIDs */
+        assert_eq!(Registry::MaS.for_hit("0A1B2C000001"), Registry::MaS);
+    }
+
+    fn closest_oui(table: &VendorTable, oui: &str) -> Option<String> {
+        table.closest(oui).map(|hit| hit.oui)
+    }
+
+    #[test]
+    fn closest_known_prefix() {
+        let table = VendorTable::open(BASIC).unwrap();
+        /* One nibble off in the last digit */
+        assert_eq!(table.closest("001123").unwrap().vendor, "CIMSYS Inc");
+        /* 000000 and 00000C both share five digits, and 00000C is nearer */
+        assert_eq!(closest_oui(&table, "00000D").as_deref(), Some("00000C"));
+        /* Four shared digits only when five find nothing */
+        assert_eq!(closest_oui(&table, "0011F0").as_deref(), Some("001122"));
+        assert_eq!(closest_oui(&table, "123456"), None);
+        /* A hit isn't its own suggestion */
+        assert_eq!(closest_oui(&table, "001122"), None);
+    }
+
+    #[test]
+    fn suffixes_are_stripped_repeatedly() {
+        assert_eq!(
+            strip_vendor_suffix("Samsung Electronics Co.,Ltd", VENDOR_SUFFIXES),
+            "Samsung Electronics"
+        );
+        assert_eq!(
+            strip_vendor_suffix("Cisco Systems, Inc.", VENDOR_SUFFIXES),
+            "Cisco Systems"
+        );
+        assert_eq!(
+            strip_vendor_suffix("Siemens AG", VENDOR_SUFFIXES),
+            "Siemens"
+        );
+        /* Whole words only, and never down to nothing */
+        assert_eq!(strip_vendor_suffix("Zinc", VENDOR_SUFFIXES), "Zinc");
+        assert_eq!(strip_vendor_suffix("Inc", VENDOR_SUFFIXES), "Inc");
+    }
+
+    #[test]
+    fn mixed_case_keys() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/mixed_case.csv");
+        let oui = |mac: &str| crate::parse_mac(mac).unwrap().oui;
+        for (mac, vendor) in [
+            ("aa:bb:cc:00:00:01", "Mixed Case Ltd"),
+            ("00:00:0C:00:00:01", "Lower Cisco"),
+            ("00:11:aa:00:00:01", "Mixed Tail Inc"),
+        ] {
+            let expected = LookupResult::Found(vendor.to_string());
+            assert_eq!(lookup_oui(path, &oui(mac)).unwrap(), expected);
+            assert_eq!(
+                crate::OuiDatabase::open(path).unwrap().lookup(&oui(mac)),
+                expected
+            );
+        }
+        assert_eq!(normalize_db_key("aAbBcC"), "AABBCC");
+    }
+
+    const QUOTED: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/quoted.csv");
+    const BAD_QUOTE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/bad_quote.csv");
+
+    #[test]
+    fn quoted_vendor_names() {
+        assert_eq!(
+            lookup_oui(QUOTED, "00000C").unwrap(),
+            LookupResult::Found("Smith; Jones Ltd".to_string())
+        );
+        assert_eq!(
+            lookup_oui(QUOTED, "001122").unwrap(),
+            LookupResult::Found("Say \"Hi\" Inc".to_string())
+        );
+        assert_eq!(
+            lookup_oui(QUOTED, "0000C1").unwrap(),
+            LookupResult::Found("Plain Ltd".to_string())
+        );
+    }
+
+    #[test]
+    fn unterminated_quote_is_located() {
+        /* A hit before the bad row is still found, since the scan stops there */
+        assert!(matches!(
+            lookup_oui(BAD_QUOTE, "00000C"),
+            Ok(LookupResult::Found(_))
+        ));
+        let error = lookup_oui(BAD_QUOTE, "0000C1").unwrap_err();
+        assert!(
+            matches!(&error, OuiError::MalformedRow { path, line: 2, .. } if path == BAD_QUOTE)
+        );
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Malformed row in {} at line 2: a quote opened on this line is never closed.",
+                BAD_QUOTE
+            )
+        );
+    }
+
+    #[test]
+    fn scan_limit_is_not_a_miss() {
+        let limit = |max_rows| ScanOptions {
+            max_rows: Some(max_rows),
+        };
+
+        /* 0000C1 is the fourth row, so three rows aren't enough to rule it out */
+        assert_eq!(
+            lookup_oui_with(BASIC, "0000C1", &limit(3)).unwrap(),
+            LookupResult::ScanLimit(3)
+        );
+        assert_eq!(
+            lookup_oui_with(BASIC, "0000C1", &limit(4)).unwrap(),
+            LookupResult::Found("Madge Ltd.".to_string())
+        );
+        assert_eq!(
+            lookup_oui_with(BASIC, "AABBCC", &limit(0)).unwrap(),
+            LookupResult::ScanLimit(0)
+        );
+
+        /* A limit covering the whole file still gives a definite miss */
+        assert_eq!(
+            lookup_oui_with(BASIC, "AABBCC", &limit(4)).unwrap(),
+            LookupResult::NotFound
+        );
+        assert_eq!(
+            lookup_oui_with(BASIC, "AABBCC", &limit(100)).unwrap(),
+            LookupResult::NotFound
+        );
+    }
+
+    #[test]
+    fn scan_limit_answers_are_not_cached() {
+        let mut scan = ScanLookup::with_options(BASIC, 8, ScanOptions { max_rows: Some(2) });
+        for _ in 0..2 {
+            assert_eq!(scan.lookup("AABBCC").unwrap(), LookupResult::ScanLimit(2));
+        }
+        assert_eq!(scan.scans(), 2);
+    }
+
+    #[test]
+    fn rows_without_a_vendor_column() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/missing_vendor.csv"
+        );
+        assert_eq!(vendorless_rows(path).unwrap(), [("00000C".to_string(), 2)]);
+        assert_eq!(
+            lookup_oui(path, "00000C").unwrap(),
+            LookupResult::Found(UNKNOWN_VENDOR.to_string())
+        );
+        assert!(vendorless_rows(BASIC).unwrap().is_empty());
+    }
+
+    #[test]
+    fn oui_key_normalization() {
+        for key in [
+            "AABBCC",
+            "aabbcc",
+            "0xAABBCC",
+            "0Xaabbcc",
+            "aa-bb-cc",
+            "AA:BB:CC",
+            "AA BB CC",
+            " AABBCC\t",
+            "AABBCC000000",
+        ] {
+            assert_eq!(normalize_oui_key(key), "AABBCC", "{:?}", key);
+        }
+        /* Longer blocks keep their length, and a nonzero tail isn't an OUI */
+        assert_eq!(normalize_oui_key("aa-bb-cc-d"), "AABBCCD");
+        assert_eq!(normalize_oui_key("AABBCC123456"), "AABBCC123456");
+    }
+
+    #[test]
+    fn scan_and_index_agree_on_key_formats() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/keys_mixed.csv");
+        let db = crate::database::OuiDatabase::open(path).unwrap();
+        for (query, vendor) in [
+            ("AABBC1", "Hex Prefix Ltd"),
+            ("aabbc2", "Lowercase Ltd"),
+            ("0xAABBC3", "Spaced Ltd"),
+            ("aa-bb-c4", "Padded Column Ltd"),
+            ("AABBC5", "Full Address Ltd"),
+        ] {
+            let found = LookupResult::Found(vendor.to_string());
+            assert_eq!(lookup_oui(path, query).unwrap(), found, "{}", query);
+            assert_eq!(db.lookup(&normalize_oui_key(query)), found, "{}", query);
+        }
+
+        /* The MA-M key is its own block, not the MA-L prefix it starts with */
+        assert_eq!(lookup_oui(path, "AABBC6").unwrap(), LookupResult::NotFound);
+        assert_eq!(db.lookup("AABBC6"), LookupResult::NotFound);
+    }
+
+    const NESTED: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/nested_blocks.csv"
+    );
+
+    #[test]
+    fn scan_prefers_the_longest_prefix() {
+        let scan = |full| lookup_longest_with(NESTED, full, &ScanOptions::default()).unwrap();
+        let found =
+            |vendor: &str, registry| (LookupResult::Found(vendor.to_string()), Some(registry));
+
+        assert_eq!(scan("AABBCCD00001"), found("Medium Vendor", Registry::MaM));
+        assert_eq!(scan("AABBCCE12001"), found("Small Vendor", Registry::MaS));
+        assert_eq!(scan("AABBCC000001"), found("Big Vendor", Registry::MaL));
+        assert_eq!(scan("AABBCC"), found("Big Vendor", Registry::MaL));
+        assert_eq!(scan("AABBCDE12001"), (LookupResult::NotFound, None));
+
+        /* A limit keeps the hit found so far rather than answering ScanLimit */
+        let limited = |max_rows| ScanOptions {
+            max_rows: Some(max_rows),
+        };
+        assert_eq!(
+            lookup_longest_with(NESTED, "AABBCCD00001", &limited(1)).unwrap(),
+            found("Big Vendor", Registry::MaL)
+        );
+        assert_eq!(
+            lookup_longest_with(NESTED, "00000C000001", &limited(2)).unwrap(),
+            (LookupResult::ScanLimit(2), None)
+        );
+    }
+
+    #[test]
+    fn misses_under_a_longer_key_are_not_cached() {
+        let mut scan = ScanLookup::new(NESTED);
+        for _ in 0..2 {
+            assert_eq!(
+                scan.lookup_longest("AABBCDE12001").unwrap(),
+                (LookupResult::NotFound, None)
+            );
+        }
+        assert_eq!(scan.scans(), 1);
+
+        /* 112233 only has an MA-M key, so a miss for one address says nothing of the next */
+        assert_eq!(
+            scan.lookup_longest("112233000001").unwrap(),
+            (LookupResult::NotFound, None)
+        );
+        assert_eq!(
+            scan.lookup_longest("112233A00001").unwrap(),
+            (
+                LookupResult::Found("Only Medium Ltd".to_string()),
+                Some(Registry::MaM)
+            )
+        );
+        assert_eq!(scan.scans(), 3);
+    }
+
+    #[test]
+    fn wildcard_prefixes_against_a_database() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/wildcards.csv");
+        let vendors = |pattern| {
+            let prefix = crate::mac::parse_wildcard(pattern).unwrap();
+            lookup_by_prefix(path, &prefix)
+                .unwrap()
+                .into_iter()
+                .map(|found| found.vendor)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vendors("AA:BB:*"), ["One Ltd", "Two Ltd", "Medium Ltd"]);
+        assert_eq!(vendors("AA-BB-*"), vendors("AA:BB:*"));
+        assert_eq!(
+            vendors("AA:*:*"),
+            ["One Ltd", "Two Ltd", "Three Ltd", "Medium Ltd"]
+        );
+        /* Narrower than an OUI, the pattern still finds the blocks it falls in */
+        assert_eq!(vendors("AA:BB:CC:D?"), ["One Ltd", "Medium Ltd"]);
+        assert!(vendors("BB:*").is_empty());
+    }
+
+    #[test]
+    fn head_and_tail_rows() {
+        let ouis = |rows: Vec<VendorMatch>| rows.into_iter().map(|row| row.oui).collect::<Vec<_>>();
+
+        assert_eq!(ouis(database_head(BASIC, 2).unwrap()), ["000000", "00000C"]);
+        assert_eq!(ouis(database_tail(BASIC, 2).unwrap()), ["001122", "0000C1"]);
+        assert_eq!(
+            database_head(BASIC, 1).unwrap(),
+            [VendorMatch {
+                oui: "000000".to_string(),
+                vendor: "XEROX CORPORATION".to_string()
+            }]
+        );
+
+        /* Asking for more rows than there are gives the whole file, still in order */
+        let all = ["000000", "00000C", "001122", "0000C1"];
+        assert_eq!(ouis(database_head(BASIC, 10).unwrap()), all);
+        assert_eq!(ouis(database_tail(BASIC, 10).unwrap()), all);
+
+        assert!(database_head(BASIC, 0).unwrap().is_empty());
+        assert!(database_tail(BASIC, 0).unwrap().is_empty());
+    }
+}
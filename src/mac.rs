@@ -0,0 +1,1035 @@
+/*!
+ * mac.rs
+ *
+ * Parsing and validation of user-supplied MAC addresses.
+ */
+
+use std::net::Ipv6Addr;
+
+use crate::error::OuiError;
+
+/**
+ * MAC address validation constants.
+ * usize is Rust's unsigned integer type.
+ */
+const MIN_MAC_LENGTH: usize = 12; /* Minimum length without separators */
+const MAX_MAC_LENGTH: usize = 17; /* Maximum length with separators */
+pub const OUI_LENGTH: usize = 6; /* OUI is first 6 hex digits */
+pub(crate) const MAC_HEX_LENGTH: usize = 12; /* A full MAC is 12 hex digits */
+pub(crate) const SEPARATORS: [char; 4] = ['-', ':', '.', ' ']; /* Accepted between digit groups */
+const MAX_MAC_VALUE: u64 = 0xFFFF_FFFF_FFFF; /* Largest 48-bit integer */
+const DOTTED_GROUPS: usize = 3; /* Cisco notation is AABB.CCDD.EEFF */
+const DOTTED_GROUP_LENGTH: usize = 4; /* Hex digits per dotted group */
+
+/**
+ * ParseOptions - Settings that change how strictly parse_mac_with validates
+ *
+ * The Default impl (all false) gives the lenient behaviour of parse_mac.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    /* Reject bare hex that is unlikely to be a MAC address */
+    pub strict_mac: bool,
+    /* Reject input that mixes separators, like "00:11-22.33 44:55" */
+    pub strict_separators: bool,
+    /* Read plain decimal input as a 48-bit integer, see parse_mac_integer */
+    pub integer: bool,
+    /* Reverse the octet order before taking the OUI, see MacAddress::reversed */
+    pub reverse: bool,
+    /* Reverse the bits within each octet, see MacAddress::bit_reversed */
+    pub bit_reverse: bool,
+    /* Pad an address one digit short with a leading zero instead of rejecting it */
+    pub allow_malformed_length: bool,
+}
+
+/**
+ * MacFormat - One input notation parse_mac_with understands
+ *
+ * MAC_FORMATS lists every notation with an example that is parsed with
+ * the format's options, so the list can't claim more than the parser does.
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct MacFormat {
+    pub name: &'static str,    /* Short description, e.g. "Cisco dotted" */
+    pub example: &'static str, /* Input in this notation, e.g. "0011.2233.4455" */
+    pub note: &'static str,    /* Anything else worth knowing, possibly empty */
+    pub options: ParseOptions, /* Options the example needs to parse */
+}
+
+const LENIENT: ParseOptions = ParseOptions {
+    strict_mac: false,
+    strict_separators: false,
+    integer: false,
+    reverse: false,
+    bit_reverse: false,
+    allow_malformed_length: false,
+};
+
+/**
+ * MAC_FORMATS - Every notation parse_mac_with accepts, most common first
+ *
+ * All the examples are the same address, 00:11:22:33:44:55, apart from
+ * the reversed ones, which are that address written backwards.
+ */
+pub const MAC_FORMATS: &[MacFormat] = &[
+    MacFormat {
+        name: "Colon-separated",
+        example: "00:11:22:33:44:55",
+        note: "either case, as most tools print them",
+        options: LENIENT,
+    },
+    MacFormat {
+        name: "Hyphen-separated",
+        example: "00-11-22-33-44-55",
+        note: "as the IEEE and Windows write them",
+        options: LENIENT,
+    },
+    MacFormat {
+        name: "Space-separated",
+        example: "00 11 22 33 44 55",
+        note: "quote it on the command line",
+        options: LENIENT,
+    },
+    MacFormat {
+        name: "Cisco dotted",
+        example: "0011.2233.4455",
+        note: "three groups of four digits",
+        options: LENIENT,
+    },
+    MacFormat {
+        name: "Bare hex",
+        example: "001122334455",
+        note: "12 digits with no separators",
+        options: LENIENT,
+    },
+    MacFormat {
+        name: "Hex integer",
+        example: "0x1122334455",
+        note: "leading zeros may be dropped",
+        options: LENIENT,
+    },
+    MacFormat {
+        name: "Decimal integer",
+        example: "73588229205",
+        note: "with --integer",
+        options: ParseOptions {
+            integer: true,
+            ..LENIENT
+        },
+    },
+    MacFormat {
+        name: "IPv6 (EUI-64)",
+        example: "fe80::211:22ff:fe33:4455",
+        note: "any prefix, a zone like %eth0 is ignored",
+        options: LENIENT,
+    },
+    MacFormat {
+        name: "Reversed",
+        example: "55:44:33:22:11:00",
+        note: "with --reverse, for Bluetooth tools",
+        options: ParseOptions {
+            reverse: true,
+            ..LENIENT
+        },
+    },
+    MacFormat {
+        name: "Bit-reversed",
+        example: "00:88:44:CC:22:AA",
+        note: "with --bit-reverse, for Token Ring and FDDI tools",
+        options: ParseOptions {
+            bit_reverse: true,
+            ..LENIENT
+        },
+    },
+    MacFormat {
+        name: "Leading zero lost",
+        example: "0:11:22:33:44:55",
+        note: "with --allow-malformed-length, 11 digits",
+        options: ParseOptions {
+            allow_malformed_length: true,
+            ..LENIENT
+        },
+    },
+];
+
+/**
+ * MacAddress - A parsed MAC address in normalized form
+ *
+ * oui and full hold uppercase hex digits with the separators removed:
+ * oui is the 6-digit manufacturer prefix used for database lookups, and
+ * full is the complete address used for display. raw keeps the input as
+ * it was typed, so output can mirror its casing and separators, with
+ * the leading zero added when padded is set.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacAddress {
+    pub oui: String,  /* First 6 hex digits, e.g. "001122" */
+    pub full: String, /* All 12 hex digits, e.g. "001122334455" */
+    pub raw: String,  /* Address as supplied, e.g. "00-11-22-33-44-55" */
+    pub padded: bool, /* A missing leading digit was filled in, see allow_malformed_length */
+}
+
+impl MacAddress {
+    /**
+     * canonical - The full address in colon-separated form
+     *
+     * Return: The address as e.g. "00:11:22:33:44:55"
+     */
+    pub fn canonical(&self) -> String {
+        colon_separated(&self.full)
+    }
+
+    /**
+     * first_octet - The first byte of the address
+     *
+     * This is the byte whose low bits mark multicast and locally
+     * administered addresses.
+     *
+     * Return: The octet, or None if the first two characters aren't hex
+     */
+    pub fn first_octet(&self) -> Option<u8> {
+        u8::from_str_radix(self.full.get(..2)?, 16).ok()
+    }
+
+    /**
+     * modified_eui64 - The IPv6 interface identifier built from the address
+     *
+     * MODIFIED EUI-64:
+     * Stateless IPv6 autoconfiguration turns a 48-bit MAC into a 64-bit
+     * interface identifier by inserting FFFE between the OUI and the rest
+     * of the address and flipping the U/L bit, so 00:11:22:33:44:55
+     * becomes 0211:22ff:fe33:4455. Group addresses never identify an
+     * interface, so they have no identifier.
+     *
+     * Return: The identifier in IPv6 notation, or None for group or incomplete addresses
+     */
+    pub fn modified_eui64(&self) -> Option<String> {
+        let octet = self.first_octet()?;
+        if octet & 0b01 != 0 || self.full.len() != MAC_HEX_LENGTH {
+            return None;
+        }
+        let rest = self.full.get(2..)?.to_ascii_lowercase();
+        Some(format!(
+            "{:02x}{}:{}ff:fe{}:{}",
+            octet ^ 0b10,
+            &rest[..2],
+            &rest[2..4],
+            &rest[4..6],
+            &rest[6..]
+        ))
+    }
+
+    /**
+     * reversed - The address with its six octets in the opposite order
+     *
+     * BYTE ORDER:
+     * Bluetooth device addresses (BD_ADDR) are drawn from the same IEEE
+     * assignments as MACs, but the Bluetooth stack sends them least
+     * significant octet first, and some tools print them that way, so the
+     * OUI ends up in the last three octets: 55:44:33:22:11:00 is the
+     * address 00:11:22:33:44:55.
+     *
+     * Return: The reversed address, or None if the address isn't all six octets
+     */
+    pub fn reversed(&self) -> Option<MacAddress> {
+        if self.full.len() != MAC_HEX_LENGTH {
+            return None;
+        }
+        let octets: Vec<&str> = (0..MAC_HEX_LENGTH)
+            .step_by(2)
+            .rev()
+            .map(|i| &self.full[i..i + 2])
+            .collect();
+        let full = octets.concat();
+        Some(MacAddress {
+            oui: full[..OUI_LENGTH].to_string(),
+            raw: colon_separated(&full),
+            full,
+            padded: self.padded,
+        })
+    }
+
+    /**
+     * bit_reversed - The address with the bits of each octet in the opposite order
+     *
+     * BIT ORDER:
+     * Ethernet sends each octet least significant bit first, while Token
+     * Ring and FDDI send the most significant bit first. Tools from those
+     * networks often print addresses in that non-canonical form, where
+     * every octet has its bits mirrored: Cisco's 00:00:0C appears as
+     * 00:00:30, and 0x11 (00010001) becomes 0x88 (10001000). Mirroring
+     * each octet again gives the canonical address the registry lists.
+     *
+     * Return: The canonical address, or None if the digits aren't whole octets
+     */
+    pub fn bit_reversed(&self) -> Option<MacAddress> {
+        if !self.full.len().is_multiple_of(2) || self.full.len() < OUI_LENGTH {
+            return None;
+        }
+        let full = (0..self.full.len())
+            .step_by(2)
+            .map(|i| {
+                let octet = u8::from_str_radix(&self.full[i..i + 2], 16).ok()?;
+                Some(format!("{:02X}", octet.reverse_bits()))
+            })
+            .collect::<Option<String>>()?;
+        Some(MacAddress {
+            oui: full[..OUI_LENGTH].to_string(),
+            raw: colon_separated(&full),
+            full,
+            padded: self.padded,
+        })
+    }
+
+    /**
+     * full_as_input - The full address written the way it was supplied
+     *
+     * Return: The address with the input's casing and separators, e.g. "aa-bb-cc-dd-ee-ff"
+     */
+    pub fn full_as_input(&self) -> String {
+        raw_prefix(&self.raw, self.full.len())
+    }
+
+    /**
+     * oui_as_input - The OUI written the way it was supplied
+     *
+     * Return: The OUI with the input's casing and separators, e.g. "aa-bb-cc"
+     */
+    pub fn oui_as_input(&self) -> String {
+        raw_prefix(&self.raw, OUI_LENGTH)
+    }
+}
+
+/*
+ * raw_prefix - Cut raw input down to its first few address digits
+ * @raw: Address as supplied, separators and all
+ * @digits: How many non-separator characters to keep
+ *
+ * Digits map one-to-one onto the normalized form, so keeping the raw
+ * characters preserves the original casing exactly, even when mixed.
+ * Separators between the kept digits stay; a trailing one is dropped.
+ */
+fn raw_prefix(raw: &str, digits: usize) -> String {
+    let mut kept = String::new();
+    let mut count = 0;
+    for c in raw.chars() {
+        if count == digits {
+            break;
+        }
+        if !SEPARATORS.contains(&c) {
+            count += 1;
+        }
+        kept.push(c);
+    }
+    kept
+}
+
+/**
+ * format_oui - Render a 6-digit OUI in colon-separated form
+ * @oui: OUI hex digits, e.g. "001122"
+ *
+ * Return: The OUI as e.g. "00:11:22"
+ */
+pub fn format_oui(oui: &str) -> String {
+    colon_separated(oui)
+}
+
+/*
+ * colon_separated - Insert a colon between every pair of hex digits
+ * @hex: Hex digits without separators
+ *
+ * as_bytes().chunks(2) walks the string two bytes at a time. The input is
+ * plain ASCII hex, so every chunk is valid UTF-8 on its own.
+ */
+fn colon_separated(hex: &str) -> String {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| String::from_utf8_lossy(pair))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/**
+ * parse_mac - Clean and validate a MAC address, extracting the OUI portion
+ * @mac: MAC address string in any common format
+ *
+ * Equivalent to parse_mac_with() using the default, lenient ParseOptions.
+ *
+ * Return: Result containing the normalized address, or an error for invalid input
+ */
+pub fn parse_mac(mac: &str) -> Result<MacAddress, OuiError> {
+    parse_mac_with(mac, &ParseOptions::default())
+}
+
+/**
+ * parse_mac_with - Clean and validate a MAC address using the given options
+ * @mac: MAC address string in any common format
+ * @options: Validation settings
+ *
+ * RUST STRINGS:
+ * &str is a string slice (a reference to a string), while String is an owned string.
+ * String can be modified, &str cannot. We accept &str because we only need to read it.
+ *
+ * ITERATOR CHAINS:
+ * Rust's iterator methods can be chained to transform data efficiently:
+ * - chars() - convert string to iterator of characters
+ * - filter() - keep only characters that aren't separators
+ * - collect() - gather filtered characters back into a String
+ *
+ * Return: Result containing the normalized address, or an error for invalid input
+ */
+pub fn parse_mac_with(mac: &str, options: &ParseOptions) -> Result<MacAddress, OuiError> {
+    /*
+     * MACs are ASCII, and the length checks and slicing below count bytes,
+     * so a multi-byte character could otherwise be cut in half
+     */
+    if !mac.is_ascii() {
+        return Err(OuiError::MalformedGroups("contains non-ASCII characters"));
+    }
+
+    if options.reverse {
+        let forward = ParseOptions {
+            reverse: false,
+            ..*options
+        };
+        return parse_mac_with(mac, &forward)?
+            .reversed()
+            .ok_or(OuiError::MalformedGroups("needs all six octets to reverse"));
+    }
+
+    if options.bit_reverse {
+        let canonical = ParseOptions {
+            bit_reverse: false,
+            ..*options
+        };
+        return parse_mac_with(mac, &canonical)?
+            .bit_reversed()
+            .ok_or(OuiError::MalformedGroups(
+                "needs whole octets to bit-reverse",
+            ));
+    }
+
+    /* No MAC notation is also a valid IPv6 address, so this can't misfire */
+    if let Some(ip) = ipv6_address(mac) {
+        return parse_ipv6_eui64(ip);
+    }
+
+    /* A 0x prefix can't start a MAC, so it always means an integer */
+    if options.integer || hex_integer(mac).is_some() {
+        return parse_mac_integer(mac);
+    }
+
+    /*
+     * Validate length (must be 12-17 characters). The lenient padding
+     * counts digits instead, after the separators are gone, below.
+     */
+    if !options.allow_malformed_length && (mac.len() < MIN_MAC_LENGTH || mac.len() > MAX_MAC_LENGTH)
+    {
+        return Err(OuiError::InvalidMac);
+    }
+
+    if options.strict_separators && mixes_separators(mac) {
+        return Err(OuiError::MalformedGroups("mixes different separators"));
+    }
+
+    /*
+     * Dots as the only separator mean Cisco notation, so hold them to its
+     * layout; mixed pastes are left to the lenient default above
+     */
+    if mac.contains('.')
+        && !mixes_separators(mac)
+        && let Some(reason) = dotted_grouping_error(mac)
+    {
+        return Err(OuiError::MalformedGroups(reason));
+    }
+
+    /* Bare hex is where pasted hashes and IDs get mistaken for addresses */
+    if options.strict_mac
+        && !mac.contains(SEPARATORS)
+        && let Some(reason) = unlikely_mac_reason(mac)
+    {
+        return Err(OuiError::UnlikelyMac(reason));
+    }
+
+    /*
+     * Remove all common separators using an iterator chain
+     * chars() creates an iterator over each character
+     * filter() keeps only characters not in the separator list
+     * collect() gathers the filtered characters into a String
+     */
+    let cleaned: String = mac.chars().filter(|c| !SEPARATORS.contains(c)).collect();
+
+    /* Convert result to uppercase for consistent matching with database */
+    let mut uppered = cleaned.to_ascii_uppercase();
+
+    /*
+     * Input that is mostly separators, like "----------AB", passes the
+     * length check with fewer digits than an OUI; unlike an integer it has
+     * no place value to pad from, so it is rejected.
+     */
+    if uppered.len() < OUI_LENGTH {
+        return Err(OuiError::MalformedGroups(
+            "has fewer hex digits than an OUI",
+        ));
+    }
+
+    /*
+     * MISSING DIGIT:
+     * A pasted address that lost its leading zero, like 0:11:22:33:44:55,
+     * has 11 digits, and putting the zero back in front gives the address
+     * it most likely was. Both raw and the digits are padded so the
+     * --preserve-input forms still line up. That is only done when asked
+     * for; any other count, with or without the option, is rejected, since
+     * padding more than one digit is guesswork and extra digits can't be
+     * told apart from the address.
+     */
+    let mut padded = false;
+    if options.allow_malformed_length && uppered.len() == MAC_HEX_LENGTH - 1 {
+        uppered.insert(0, '0');
+        padded = true;
+    }
+    if uppered.len() != MAC_HEX_LENGTH || !uppered.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(OuiError::InvalidMac);
+    }
+
+    /* Extract first 6 characters for the OUI */
+    Ok(MacAddress {
+        oui: uppered[..OUI_LENGTH].to_string(),
+        full: uppered,
+        raw: if padded {
+            format!("0{}", mac)
+        } else {
+            mac.to_string()
+        },
+        padded,
+    })
+}
+
+/**
+ * parse_mac_integer - Read a MAC address stored as a 48-bit integer
+ * @value: Decimal digits, or hex digits after "0x", e.g. "281474976710655" or "0x1122"
+ *
+ * INTEGER INPUT:
+ * Databases and logs often keep addresses as plain numbers, which drops
+ * the leading zeros a MAC is written with: 00:00:00:00:11:22 is stored as
+ * 0x1122, or 4386. The value is zero-padded back to 12 hex digits before
+ * the OUI is taken, so any value up to 0xFFFFFFFFFFFF is a full address.
+ *
+ * A value too small to reach the OUI, anything below 0x1000000 such as
+ * 0x12, is still a valid address rather than an error: it is the low end
+ * of the 00:00:00 block, 00:00:00:00:00:12, and is looked up as OUI
+ * 000000 like any other.
+ *
+ * Return: Result containing the normalized address, or an error if the value isn't a number or needs more than 48 bits
+ */
+pub fn parse_mac_integer(value: &str) -> Result<MacAddress, OuiError> {
+    let value = value.trim();
+    let number = match hex_integer(value) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse::<u64>(),
+    }
+    .map_err(|_| OuiError::InvalidMac)?;
+    if number > MAX_MAC_VALUE {
+        return Err(OuiError::MalformedGroups("integer is wider than 48 bits"));
+    }
+
+    let full = format!("{:012X}", number);
+    Ok(MacAddress {
+        oui: full[..OUI_LENGTH].to_string(),
+        raw: full.clone(),
+        full,
+        padded: false,
+    })
+}
+
+/**
+ * parse_ipv6_eui64 - Recover the MAC embedded in an IPv6 address
+ * @ip: An address whose interface identifier may be modified EUI-64
+ *
+ * This undoes MacAddress::modified_eui64: fe80::211:22ff:fe33:4455 holds
+ * 00:11:22:33:44:55. Only identifiers with FFFE in the middle were built
+ * from a MAC; privacy extensions and stable random identifiers are just
+ * random bits, so they have no vendor to find. Any prefix works, not only
+ * link-local fe80::/64.
+ *
+ * Return: Result containing the address, or NotEui64 if the identifier wasn't built from a MAC
+ */
+pub fn parse_ipv6_eui64(ip: Ipv6Addr) -> Result<MacAddress, OuiError> {
+    let octets = ip.octets();
+    if octets[11..13] != [0xFF, 0xFE] {
+        return Err(OuiError::NotEui64);
+    }
+    let full: String = [
+        octets[8] ^ 0b10,
+        octets[9],
+        octets[10],
+        octets[13],
+        octets[14],
+        octets[15],
+    ]
+    .iter()
+    .map(|byte| format!("{:02X}", byte))
+    .collect();
+    Ok(MacAddress {
+        oui: full[..OUI_LENGTH].to_string(),
+        raw: colon_separated(&full),
+        full,
+        padded: false,
+    })
+}
+
+/* The input as an IPv6 address, ignoring a zone such as "%eth0", if it is one */
+fn ipv6_address(input: &str) -> Option<Ipv6Addr> {
+    let address = input.split_once('%').map_or(input, |(address, _)| address);
+    address.parse().ok()
+}
+
+/* The digits after a 0x or 0X prefix, if the input has one */
+fn hex_integer(value: &str) -> Option<&str> {
+    value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+}
+
+/**
+ * is_wildcard - Whether the input is a wildcard pattern rather than an address
+ * @input: Address or pattern as supplied
+ */
+pub fn is_wildcard(input: &str) -> bool {
+    input.contains(['*', '?'])
+}
+
+/**
+ * parse_wildcard - Turn a wildcard pattern into the hex prefix it stands for
+ * @pattern: Address with trailing wildcards, e.g. "00:11:*" or "00:11:??:??:??:??"
+ *
+ * '?' stands for one hex digit and '*' for all the remaining ones, so
+ * wildcards after a '*' add nothing: "00:*:*", typed to mirror the
+ * groups of an address, is read as "00:*". Every wildcard has to follow
+ * all of the literal digits: "00:??:22" has a hole in the middle, which
+ * no single prefix can describe, and is rejected.
+ *
+ * Return: Result containing the uppercase hex digits before the first wildcard, or an error if the pattern is malformed
+ */
+pub fn parse_wildcard(pattern: &str) -> Result<String, OuiError> {
+    let chars: Vec<char> = pattern
+        .chars()
+        .filter(|c| !SEPARATORS.contains(c))
+        .collect();
+    let literal = chars.iter().take_while(|c| c.is_ascii_hexdigit()).count();
+    let (digits, wildcards) = chars.split_at(literal);
+
+    if digits.is_empty() {
+        return Err(OuiError::MalformedGroups(
+            "a wildcard needs at least one leading hex digit",
+        ));
+    }
+    if wildcards.iter().any(|&c| c != '*' && c != '?') {
+        return Err(OuiError::MalformedGroups(
+            "wildcards may only appear at the end",
+        ));
+    }
+    /* Up to a '*', each wildcard is one digit; the '*' itself is at least one more */
+    let fixed = wildcards.iter().take_while(|&&c| c == '?').count();
+    let rest = usize::from(wildcards.len() > fixed);
+    if digits.len() + fixed + rest > MAC_HEX_LENGTH {
+        return Err(OuiError::InvalidMac);
+    }
+    Ok(digits.iter().collect::<String>().to_ascii_uppercase())
+}
+
+/*
+ * mixes_separators - Whether more than one kind of separator appears
+ * @mac: Input as supplied
+ *
+ * Every separator has to match the first one found, so "00:11:22" and
+ * bare hex pass while "00:11-22" does not.
+ */
+fn mixes_separators(mac: &str) -> bool {
+    let mut found = mac.chars().filter(|c| SEPARATORS.contains(c));
+    match found.next() {
+        Some(first) => found.any(|c| c != first),
+        None => false,
+    }
+}
+
+/*
+ * dotted_grouping_error - Check dotted input against Cisco's AABB.CCDD.EEFF layout
+ * @mac: Input containing at least one dot
+ *
+ * Stripping separators first would let AAB.BCCDD.EEFF through, since the
+ * digit count is right even though the grouping isn't.
+ *
+ * Return: A short reason when the grouping is wrong, None when it is valid
+ */
+fn dotted_grouping_error(mac: &str) -> Option<&'static str> {
+    let groups: Vec<&str> = mac.split('.').collect();
+    if groups.len() != DOTTED_GROUPS {
+        return Some("dotted notation needs exactly three groups");
+    }
+    if groups.iter().any(|group| {
+        group.len() != DOTTED_GROUP_LENGTH || !group.chars().all(|c| c.is_ascii_hexdigit())
+    }) {
+        return Some("each dotted group must be four hex digits");
+    }
+    None
+}
+
+/*
+ * unlikely_mac_reason - Explain why a bare hex string doesn't look like a MAC
+ * @bare: Input without any separators
+ *
+ * HEURISTIC:
+ * A real address written without separators is exactly 12 hex digits.
+ * Anything longer is more likely the start of a hash or serial number,
+ * and a run of one repeated digit is more likely filler or a test value
+ * than hardware. This is deliberately conservative: it only looks at
+ * separator-free input, since separators are strong evidence of a MAC.
+ *
+ * Return: A short reason when the input looks suspicious, None otherwise
+ */
+fn unlikely_mac_reason(bare: &str) -> Option<&'static str> {
+    if bare.len() != MAC_HEX_LENGTH {
+        return Some("bare hex must be exactly 12 digits");
+    }
+    if !bare.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Some("contains non-hex characters");
+    }
+    /* windows(2) yields every pair of neighbouring bytes */
+    if bare.as_bytes().windows(2).all(|pair| pair[0] == pair[1]) {
+        return Some("every digit is the same");
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_of_a_parsed_address() {
+        let mac = parse_mac("001122334455").unwrap();
+        assert_eq!(mac.oui, "001122");
+        assert_eq!(mac.full, "001122334455");
+        assert_eq!(mac.raw, "001122334455");
+        assert!(!mac.padded);
+        assert_eq!(mac.canonical(), "00:11:22:33:44:55");
+    }
+
+    #[test]
+    fn separators_and_case_are_normalized() {
+        for input in ["00:11:22:aa:BB:cc", "00-11-22-AA-BB-CC", "0011.22aa.bbcc"] {
+            let mac = parse_mac(input).unwrap();
+            assert_eq!(mac.oui, "001122");
+            assert_eq!(mac.full, "001122AABBCC");
+            assert_eq!(mac.raw, input);
+        }
+    }
+
+    #[test]
+    fn dotted_notation() {
+        let mac = parse_mac("0011.2233.4455").unwrap();
+        assert_eq!(mac.full, "001122334455");
+        assert_eq!(parse_mac("aabb.ccdd.eeff").unwrap().oui, "AABBCC");
+    }
+
+    #[test]
+    fn malformed_dotted_groupings() {
+        for input in ["AAB.BCCDD.EEFF", "AABBC.CDD.EEFF"] {
+            assert_eq!(
+                parse_mac(input).unwrap_err().to_string(),
+                "Invalid MAC Address: each dotted group must be four hex digits."
+            );
+        }
+        assert_eq!(
+            parse_mac("AABB.CCDDEEFF").unwrap_err().to_string(),
+            "Invalid MAC Address: dotted notation needs exactly three groups."
+        );
+        assert!(parse_mac("AABB.CCDD.EEF.F").is_err());
+    }
+
+    #[test]
+    fn separators_under_both_modes() {
+        let strict = ParseOptions {
+            strict_separators: true,
+            ..ParseOptions::default()
+        };
+        for consistent in [
+            "00:11:22:33:44:55",
+            "00-11-22-33-44-55",
+            "00 11 22 33 44 55",
+        ] {
+            assert!(parse_mac(consistent).is_ok());
+            assert!(parse_mac_with(consistent, &strict).is_ok());
+        }
+
+        let mixed = "00:11-22.33 44:55";
+        assert_eq!(parse_mac(mixed).unwrap().full, "001122334455");
+        assert_eq!(
+            parse_mac_with(mixed, &strict).unwrap_err().to_string(),
+            "Invalid MAC Address: mixes different separators."
+        );
+    }
+
+    #[test]
+    fn integers_are_padded_to_twelve_digits() {
+        let zero = parse_mac_integer("0").unwrap();
+        assert_eq!(zero.full, "000000000000");
+        assert_eq!(zero.oui, "000000");
+
+        let small = parse_mac_integer("0x1122").unwrap();
+        assert_eq!(small.full, "000000001122");
+        assert_eq!(small.oui, "000000");
+
+        let max = parse_mac_integer("0xFFFFFFFFFFFF").unwrap();
+        assert_eq!(max.full, "FFFFFFFFFFFF");
+        assert_eq!(max.oui, "FFFFFF");
+        assert_eq!(parse_mac_integer("281474976710655").unwrap(), max);
+
+        assert_eq!(parse_mac("0x00000C123456").unwrap().oui, "00000C");
+        assert!(parse_mac_integer("0x1000000000000").is_err());
+        assert!(parse_mac_integer("12ab").is_err());
+    }
+
+    #[test]
+    fn non_ascii_input_is_rejected() {
+        for input in [
+            "00:11:22:33:44:5é",
+            "00:11:22:33:44:55🙂",
+            "ÀÀ:11:22:33:44:55",
+            "０0:11:22:33:44:55",
+        ] {
+            assert_eq!(
+                parse_mac(input).unwrap_err().to_string(),
+                "Invalid MAC Address: contains non-ASCII characters.",
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn link_local_eui64_address() {
+        for input in [
+            "fe80::0211:22ff:fe33:4455",
+            "fe80::211:22ff:fe33:4455",
+            "FE80::211:22FF:FE33:4455",
+        ] {
+            let mac = parse_mac(input).unwrap();
+            assert_eq!(mac.full, "001122334455", "{}", input);
+            assert_eq!(mac.oui, "001122");
+        }
+        /* The U/L bit is flipped back, so a local identifier gives a universal MAC and vice versa */
+        assert_eq!(parse_mac("fe80::11:22ff:fe33:4455").unwrap().oui, "021122");
+    }
+
+    #[test]
+    fn privacy_address_is_not_derivable() {
+        assert!(matches!(
+            parse_mac("fe80::1c2b:3a4f:9d8e:7f6a"),
+            Err(OuiError::NotEui64)
+        ));
+    }
+
+    #[test]
+    fn digit_count_is_exact() {
+        let lenient = ParseOptions {
+            allow_malformed_length: true,
+            ..ParseOptions::default()
+        };
+
+        /* 11 digits are an error unless the missing one may be filled in */
+        for input in ["0:11:22:33:44:55", "01122334455"] {
+            assert!(
+                matches!(parse_mac(input), Err(OuiError::InvalidMac)),
+                "{}",
+                input
+            );
+            let mac = parse_mac_with(input, &lenient).unwrap();
+            assert_eq!(mac.full, "001122334455");
+            assert_eq!(mac.oui, "001122");
+            assert_eq!(mac.raw, format!("0{}", input));
+            assert!(mac.padded);
+        }
+        assert!(
+            !parse_mac_with("00:11:22:33:44:55", &lenient)
+                .unwrap()
+                .padded
+        );
+
+        /* Not shifted by a digit, which would read it as 0000C1 */
+        assert!(parse_mac("0:00:0c:12:34:56").is_err());
+        assert_eq!(
+            parse_mac_with("0:00:0c:12:34:56", &lenient).unwrap().oui,
+            "00000C"
+        );
+
+        /* Extra digits are never cut off, with or without the option */
+        for input in ["0011223344556", "00:11:22:33:44:55:6", "00000C1234567890"] {
+            assert!(
+                matches!(parse_mac(input), Err(OuiError::InvalidMac)),
+                "{}",
+                input
+            );
+            assert!(
+                matches!(parse_mac_with(input, &lenient), Err(OuiError::InvalidMac)),
+                "{}",
+                input
+            );
+        }
+
+        /* Ten digits are more than one short, so padding doesn't reach them */
+        assert!(parse_mac("00-00-0C-12-34").is_err());
+        assert!(parse_mac_with("0:0:0C:12:34", &lenient).is_err());
+        assert!(matches!(
+            parse_mac("zzzzzzzzzzzz"),
+            Err(OuiError::InvalidMac)
+        ));
+    }
+
+    #[test]
+    fn every_listed_format_parses() {
+        for format in MAC_FORMATS {
+            assert!(
+                parse_mac_with(format.example, &format.options).is_ok(),
+                "{}",
+                format.name
+            );
+        }
+    }
+
+    #[test]
+    fn values_shorter_than_an_oui() {
+        /* An integer has place value, so a small one is the low end of 00:00:00 */
+        for input in ["0x12", "0x0", "18"] {
+            let options = ParseOptions {
+                integer: !input.starts_with("0x"),
+                ..ParseOptions::default()
+            };
+            let mac = parse_mac_with(input, &options).unwrap();
+            assert_eq!(mac.oui, "000000", "{}", input);
+            assert_eq!(mac.full.len(), MAC_HEX_LENGTH);
+        }
+        assert_eq!(parse_mac("0x12").unwrap().canonical(), "00:00:00:00:00:12");
+
+        /* Separators carry no place value, so too few digits are an error rather than a panic */
+        for input in ["----------ab", "::::::::::::1", "-- -- -- -- ab"] {
+            match parse_mac(input) {
+                Err(OuiError::MalformedGroups(reason)) => {
+                    assert_eq!(reason, "has fewer hex digits than an OUI", "{}", input)
+                }
+                other => panic!("{}: {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn reversed_bluetooth_address() {
+        let reverse = ParseOptions {
+            reverse: true,
+            ..ParseOptions::default()
+        };
+        let mac = parse_mac_with("56:34:12:CC:BB:AA", &reverse).unwrap();
+        assert_eq!(mac.oui, "AABBCC");
+        assert_eq!(mac.full, "AABBCC123456");
+        assert_eq!(mac.canonical(), "AA:BB:CC:12:34:56");
+
+        /* Any accepted notation reverses the same way, and reversing twice is the identity */
+        assert_eq!(
+            parse_mac_with("5634.12cc.bbaa", &reverse).unwrap().full,
+            "AABBCC123456"
+        );
+        let forward = parse_mac("00:11:22:33:44:55").unwrap();
+        assert_eq!(forward.reversed().unwrap().full, "554433221100");
+        assert_eq!(
+            forward.reversed().unwrap().reversed().unwrap().full,
+            forward.full
+        );
+    }
+
+    #[test]
+    fn bit_reversed_pairs() {
+        let bit_reverse = ParseOptions {
+            bit_reverse: true,
+            ..ParseOptions::default()
+        };
+        for (canonical, non_canonical) in [
+            ("00:00:0C:12:34:56", "00:00:30:48:2C:6A"),
+            ("00:11:22:33:44:55", "00:88:44:CC:22:AA"),
+        ] {
+            let read = parse_mac_with(non_canonical, &bit_reverse).unwrap();
+            assert_eq!(read.canonical(), canonical);
+            assert_eq!(read.oui, parse_mac(canonical).unwrap().oui);
+
+            /* Mirroring is its own inverse, in either direction */
+            let mac = parse_mac(canonical).unwrap();
+            assert_eq!(mac.bit_reversed().unwrap().canonical(), non_canonical);
+            assert_eq!(
+                mac.bit_reversed().unwrap().bit_reversed().unwrap().full,
+                mac.full
+            );
+        }
+    }
+
+    #[test]
+    fn strict_mac_borderlines() {
+        let strict = ParseOptions {
+            strict_mac: true,
+            ..ParseOptions::default()
+        };
+        let reason = |input| match parse_mac_with(input, &strict) {
+            Err(OuiError::UnlikelyMac(reason)) => Some(reason),
+            Ok(_) => None,
+            Err(e) => panic!("{}: {}", input, e),
+        };
+
+        /* One repeated digit reads like filler, not an address, but only strict mode minds */
+        for input in ["000000000000", "ffffffffffff", "AAAAAAAAAAAA"] {
+            assert_eq!(reason(input), Some("every digit is the same"), "{}", input);
+            assert!(parse_mac(input).is_ok(), "{}", input);
+        }
+        assert_eq!(
+            reason("0011223344556"),
+            Some("bare hex must be exactly 12 digits")
+        );
+        assert_eq!(reason("00112233445g"), Some("contains non-hex characters"));
+
+        /* One digit off the pattern is enough, and real addresses pass */
+        for input in ["AAAAAAAAAAAB", "00000C123456", "a4bb6d112233"] {
+            assert_eq!(reason(input), None, "{}", input);
+        }
+
+        /* Separators show the input was meant as an address */
+        for input in ["00:00:00:00:00:00", "aa-aa-aa-aa-aa-aa", "ffff.ffff.ffff"] {
+            assert_eq!(reason(input), None, "{}", input);
+        }
+    }
+
+    #[test]
+    fn wildcard_patterns() {
+        for (pattern, prefix) in [
+            ("AA:BB:*", "AABB"),
+            ("aa-bb-*", "AABB"),
+            ("AA:*:*", "AA"),
+            ("aa:bb:??:??:??:??", "AABB"),
+            ("00:11:22:3?", "0011223"),
+        ] {
+            assert_eq!(parse_wildcard(pattern).unwrap(), prefix, "{}", pattern);
+        }
+
+        let reason = |pattern| match parse_wildcard(pattern) {
+            Err(OuiError::MalformedGroups(reason)) => reason,
+            other => panic!("{}: {:?}", pattern, other),
+        };
+        assert_eq!(reason("AA:*:CC"), "wildcards may only appear at the end");
+        assert_eq!(reason("AA:??:CC"), "wildcards may only appear at the end");
+        assert_eq!(
+            reason("*:BB"),
+            "a wildcard needs at least one leading hex digit"
+        );
+        assert!(matches!(
+            parse_wildcard("AA:BB:CC:DD:EE:FF:?"),
+            Err(OuiError::InvalidMac)
+        ));
+    }
+}
@@ -1,184 +1,2963 @@
-/**
+/*
  * main.rs
- * 
+ *
  * OUI (Organizationally Unique Identifier) lookup utility.
- * 
+ *
  * OVERVIEW:
- * Matches MAC addresses to network interface card (NIC) manufacturers using 
- * the IEEE OUI database. The first 6 hexadecimal digits of a MAC address
- * identify the manufacturer. 
+ * Command-line front end for the oui library. Parses the arguments, runs
+ * the lookup and prints the result or the error.
  */
 
-use std::env;
+mod cli;
+mod output;
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::OsString;
+use std::fs;
+use std::hint::black_box;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::num::NonZeroUsize;
+use std::path::Path;
 use std::process;
+use std::time::{Duration, Instant};
 
-/** 
- * MAC address validation constants.
- * usize is Rust's unsigned integer type.
- */
-const MIN_MAC_LENGTH: usize = 12; /* Minimum length without separators */
-const MAX_MAC_LENGTH: usize = 17; /* Maximum length with separators */
-const OUI_LENGTH: usize = 6; /* OUI is first 6 hex digits */
+use oui::{
+    AddressKind, AnalysisRecord, CLASSIFIER_NAMES, Classifier, DatabaseDiff, LookupRecord,
+    LookupResult, MAC_FORMATS, MacAddress, MetadataTable, NEGATIVE_CACHE_SIZE, OUI_LENGTH,
+    OuiDatabase, OuiError, ParseOptions, Registry, ScanLookup, UNKNOWN_VENDOR, VENDOR_SUFFIXES,
+    VendorCounts, VendorMatch, VendorPolicy, VendorTable, Verdict, classifier_by_name,
+    count_vendors, database_age, database_head, database_tail, detect_columns, diff_databases,
+    download_database, format_oui, getmac_address, is_manuf, is_wildcard, local_interfaces,
+    lookup_by_prefix, lookup_by_vendor_with, lookup_longest_with, lookup_online,
+    lookup_record_schema, normalize_database, oui24_prefixes, parse_mac_with, parse_wildcard,
+    resolve_csv_path, serve, strip_vendor_suffix, vendorless_rows,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use cli::{
+    Cli, Columns, Command, ConflictArg, Field, GlobalArgs, InputFormat, LookupArgs, NormalizeArgs,
+    OutputFormat, OutputOrder, SearchArgs, SortKey, UpdateArgs,
+};
+use output::{
+    Entry, Style, Summary, Template, format_fields, format_result, print_json, print_ndjson,
+    print_yaml, write_csv, write_env, write_table,
+};
 
 /**
- * get_csv_path - Construct the path to the IEEE OUI database CSV file
- * 
- * RESULT TYPE:
- * Rust uses Result<Type, Error> for operations that can fail
- * - Ok(value) indicates success and contains the value
- * - Err(error) indicates failure and contains the error
- * 
- * ? OPERATOR:
- * This is shorthand for error propagation. When used after a Result:
- * - If Ok: unwrap the value and continue
- * - If Err: return the error to the calling function immediately
- * 
- * Return: Result containing the CSV path string, or an error if HOME is not set
- */
-fn get_csv_path() -> Result<String, std::env::VarError> {
-    let home_path = env::var("HOME")?; // Returns the error to the calling function if HOME is not set
-    let csv_path = format!("{}/.local/share/oui/IEEE_OUI.csv", home_path);
-    Ok(csv_path) // Ok wraps the successful result
+ * ExitStatus - How a run ended, mapped to the process exit code
+ *
+ * Blocked is distinct from Failure so monitoring scripts can tell a policy
+ * violation apart from a broken invocation. Matched is what a stream cut
+ * short by --exit-on-first-match ends with when no policy is given.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExitStatus {
+    Success,
+    Failure,
+    Blocked,
+    Matched,
 }
 
-/**
- * parse_mac - Clean and validate a MAC address, extracting the OUI portion
- * @mac: MAC address string in any common format
- * 
- * RUST STRINGS:
- * &str is a string slice (a reference to a string), while String is an owned string.
- * String can be modified, &str cannot. We accept &str because we only need to read it.
- * 
- * ITERATOR CHAINS:
- * Rust's iterator methods can be chained to transform data efficiently:
- * - chars() - convert string to iterator of characters
- * - filter() - keep only characters that aren't separators
- * - collect() - gather filtered characters back into a String
- * 
- * INTO() METHOD:
- * into() converts one type into another. Here, we're converting a string literal into
- * Box<dyn std::error::Error>, which is a trait object that can hold any error type.
- * 
- * Return: Result containing the 6-character OUI string, or an error for invalid input
- */
-fn parse_mac(mac: &str) -> Result<String, Box<dyn std::error::Error>> {
-    /* Validate length (must be 12-17 characters) */
-    if mac.len() < MIN_MAC_LENGTH || mac.len() > MAX_MAC_LENGTH {
-        return Err("Invalid MAC Address.".into());
+impl ExitStatus {
+    /* The numeric code passed to process::exit */
+    fn code(self) -> i32 {
+        match self {
+            ExitStatus::Success => 0,
+            ExitStatus::Failure => 1,
+            ExitStatus::Blocked => 2,
+            ExitStatus::Matched => 3,
+        }
     }
+}
 
-    /*
-     * Remove all common separators using an iterator chain
-     * chars() creates an iterator over each character
-     * filter() keeps only characters not in the separator list
-     * collect() gathers the filtered characters into a String
-     */
-    let cleaned: String = mac
-        .chars()
-        .filter(|c| !['-', ':', '.', ' '].contains(c))
-        .collect();
-
-    /* Convert result to uppercase for consistent matching with database */
-    let uppered = cleaned.to_ascii_uppercase();
+/*
+ * read_macs - Read MAC addresses from a file, one per line
+ * @path: File to read, or "-" for standard input
+ * @format: How each line is laid out
+ *
+ * Return: Result containing the addresses, or an error if the file can't be read
+ */
+fn read_macs(path: &str, format: InputFormat) -> Result<Vec<String>, OuiError> {
+    let contents = if path == "-" {
+        /* lock() gives buffered access to stdin; lines() splits on newlines */
+        io::stdin().lock().lines().collect::<Result<Vec<_>, _>>()?
+    } else {
+        fs::read_to_string(path)?
+            .lines()
+            .map(String::from)
+            .collect()
+    };
 
-    /* Extract first 6 characters */
-    let search_term = &uppered[..OUI_LENGTH];
-    Ok(search_term.to_string())
+    Ok(contents
+        .iter()
+        .filter_map(|line| input_address(line, format))
+        .map(String::from)
+        .collect())
 }
 
 /*
- * lookup_oui - Search the IEEE OUI database for a matching manufacturer
- * @csv_path: Path to the IEEE OUI CSV file
- * @mac: The 6-character OUI to search for
- * 
- * CSV FORMAT
- * The database is a semicolon-delimited CSV file:
- * Column 0: OUI
- * Column 1: Manufacturer name
- * 
- * Return: Result indicating success or failure
+ * single_address - Whether the lookup resolves exactly one address to one vendor
+ *
+ * --output env sets one set of variables, so it needs a plain one-address
+ * lookup: no batch input, no wildcard range and none of the modes that
+ * print something other than a vendor.
  */
-fn lookup_oui(csv_path: &str, mac: &str) -> Result<(), Box<dyn std::error::Error>> {
-    /*
-     * Create a CSV reader with customer delimiter
-     * b';' is a byte literal (semicolon as u8)
-     */
-    let mut rdr = csv::ReaderBuilder::new()
-        .delimiter(b';')
-        .from_path(csv_path)?;
+fn single_address(args: &LookupArgs) -> bool {
+    let other_mode = args.trim_to_oui
+        || args.no_db
+        || args.all
+        || args.exit_on_first_match
+        || args.serve.is_some()
+        || args.count_only;
+    args.macs.len() == 1
+        && args.file.is_none()
+        && !args.self_
+        && !from_capture(args)
+        && !other_mode
+        && !is_wildcard(&args.macs[0])
+}
 
-    /* 
-     * Iterate through each record in the CSV
-     * records() returns an iterator over Result<StringRecord, Error>
-     */
-    for result in rdr.records() {
-        let record = result?; // Propagate any read errors
-        if record.get(0) == Some(mac) {
-            /* Print manufacturer name (second column).
-             * unwrap_or provides a default if column doesn't exist
-             */
-            println!("{}", record.get(1).unwrap_or("Unknown vendor."));
-            return Ok(());
-        }
+/*
+ * input_address - Pick the address out of one line of --file input
+ * @line: The line as read
+ * @format: How the line is laid out
+ *
+ * Plain lines are the address itself, trimmed, and blank ones are skipped.
+ * getmac output is a table, so only the line's address column is kept.
+ *
+ * Return: The address, or None if the line has none
+ */
+fn input_address(line: &str, format: InputFormat) -> Option<&str> {
+    match format {
+        InputFormat::Plain => Some(line.trim()).filter(|line| !line.is_empty()),
+        InputFormat::Getmac => getmac_address(line),
     }
-
-    /* No match found after searching entire database */
-    println!("No match.");
-    Ok(())
 }
 
 /*
- * run - Main application logic with error handling
- * 
+ * lookup - Resolve MAC addresses to manufacturers
+ * @args: Addresses and lookup options
+ * @global: Output options shared by all subcommands
+ * @out: Where results are written (stdout when run from main)
+ * @err: Where errors and notes are written (stderr when run from main)
+ *
  * RUST OWNERSHIP:
  * Rust's ownership system ensures memory safety without garbage collection:
  * - Each value has a single owner
  * - When the owner goes out of scope, the value is dropped
  * - Value can be borrowed (referenced) without transferring ownership
- * 
- * COLLECT() METHOD:
- * env::args() returns an iterator over command-line arguments. collect() 
- * gathers them into a Vec<String> (a growable array of owned strings).
- * 
- * Return: Result indicating success or failure of the entire operation
- */
-fn run() -> Result<(), Box<dyn std::error::Error>> {
-    /* Collect command-line arguments into a vector
-     * args[0] is the program name, args[1] is the first actual argument
-     */
-    let args: Vec<String> = env::args().collect();
+ *
+ * BATCH MODE:
+ * Several addresses, or any --file, switch to batch mode: the database is
+ * loaded once and each address prints one line. An invalid address is
+ * reported without stopping the rest of the batch.
+ *
+ * Return: Result containing the exit status, or an error that stops the run
+ */
+fn lookup(
+    args: &LookupArgs,
+    global: &GlobalArgs,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<ExitStatus, OuiError> {
+    let format = global.format();
+    if args.summary && format != OutputFormat::Csv {
+        return Err(OuiError::Usage(
+            "--summary only applies to --output csv.".to_string(),
+        ));
+    }
+    if !args.field.is_empty()
+        && matches!(
+            format,
+            OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::Yaml
+        )
+    {
+        return Err(OuiError::Usage(
+            "--field can't be combined with JSON or YAML output.".to_string(),
+        ));
+    }
+
+    if format == OutputFormat::Env && !single_address(args) {
+        return Err(OuiError::Usage(
+            "--output env only supports looking up a single address.".to_string(),
+        ));
+    }
+
+    let template = output_template(args, format)?;
+    if args.echo_input && format != OutputFormat::Plain {
+        return Err(OuiError::Usage(
+            "--echo-input only applies to plain output.".to_string(),
+        ));
+    }
+    if args.low_memory && !global.extra_paths().is_empty() {
+        return Err(OuiError::Usage(
+            "--low-memory scans a single database; give only one --path.".to_string(),
+        ));
+    }
+
+    if args.print_schema {
+        print_json(out, &lookup_record_schema(), true)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    if args.explain_format {
+        explain_formats(out)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    if let Some(socket) = &args.serve {
+        let csv_path = global.csv_path()?;
+        warn_if_stale(global, err)?;
+        let db = open_database(args, &csv_path)?;
+        serve(&db, Path::new(socket), &args.parse_options(), err)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    if !args.no_db && !args.trim_to_oui && args.diff.is_none() {
+        warn_if_stale(global, err)?;
+    }
+
+    /* Inspecting the database skips everything about addresses */
+    if let Some(count) = args.head {
+        print_matches(&database_head(&global.csv_path()?, count)?, global, out)?;
+        return Ok(ExitStatus::Success);
+    }
+    if let Some(count) = args.tail {
+        print_matches(&database_tail(&global.csv_path()?, count)?, global, out)?;
+        return Ok(ExitStatus::Success);
+    }
+    if let Some(lookups) = args.benchmark_db {
+        return benchmark_db(args, global, lookups, err);
+    }
+    if let Some([old, new]) = args.diff.as_deref() {
+        let diff = diff_databases(old, new)?;
+        match format {
+            OutputFormat::Json => print_json(out, &diff, global.json_pretty)?,
+            OutputFormat::Ndjson => print_json(out, &diff, false)?,
+            OutputFormat::Yaml => print_yaml(out, &diff)?,
+            _ => print_diff(&diff, out)?,
+        }
+        return Ok(ExitStatus::Success);
+    }
+    if args.count_vendors {
+        let counts = count_vendors(&global.csv_path()?, args.top.unwrap_or(DEFAULT_TOP_VENDORS))?;
+        match format {
+            OutputFormat::Json => print_json(out, &counts, global.json_pretty)?,
+            OutputFormat::Ndjson => print_ndjson(out, &counts.top)?,
+            OutputFormat::Yaml => print_yaml(out, &counts)?,
+            _ => print_vendor_counts(&counts, out)?,
+        }
+        return Ok(ExitStatus::Success);
+    }
+    if args.as_oui24 {
+        let prefixes = oui24_prefixes(&global.csv_path()?)?;
+        if format == OutputFormat::Json {
+            print_json(out, &prefixes, global.json_pretty)?;
+        } else if format == OutputFormat::Ndjson {
+            print_ndjson(out, &prefixes)?;
+        } else {
+            for prefix in &prefixes {
+                writeln!(out, "{}", prefix)?;
+            }
+        }
+        return Ok(ExitStatus::Success);
+    }
+
+    /* Sorting needs every result first, which these modes don't collect */
+    if args.sort_output != OutputOrder::Input
+        && (args.exit_on_first_match || args.no_db || args.all)
+    {
+        writeln!(
+            err,
+            "Warning: --sort-output is ignored with --exit-on-first-match, --no-db and --all."
+        )?;
+    }
+
+    if args.exit_on_first_match {
+        return lookup_until_match(args, global, out, err);
+    }
+
+    let mut macs = args.macs.clone();
+    if let Some(file) = &args.file {
+        macs.extend(read_macs(file, args.input_format)?);
+    }
+    #[cfg(feature = "pcap")]
+    if let Some(capture) = &args.pcap {
+        macs.extend(oui::read_pcap_macs(capture)?);
+    }
+    /* Interface names ride along by position, matched up after parsing */
+    let mut interfaces = Vec::new();
+    if args.self_ {
+        for interface in local_interfaces()? {
+            interfaces.push(Some(interface.name));
+            macs.push(interface.mac);
+        }
+    }
+    let batch = macs.len() > 1 || args.file.is_some() || args.self_ || from_capture(args);
+    let parse_options = args.parse_options();
+    if parse_options.allow_malformed_length {
+        for mac in &macs {
+            if let Ok(parsed) = parse_mac_with(mac, &parse_options) {
+                warn_if_padded(mac, &parsed, err)?;
+            }
+        }
+    }
+
+    if args.trim_to_oui {
+        return trim_to_oui(args, &macs, global, out, err);
+    }
+    if args.no_db {
+        return analyze(args, &macs, batch, global, out, err);
+    }
+    if args.all {
+        return lookup_all(args, &macs, batch, global, out, err);
+    }
+
+    /* A lone pattern like 00:11:* lists the range instead of one vendor */
+    if !batch && is_wildcard(&macs[0]) {
+        let prefix = parse_wildcard(&macs[0])?;
+        let matches = lookup_by_prefix(&global.csv_path()?, &prefix)?;
+        print_matches(&matches, global, out)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    let mut entries: Vec<Entry> = if !batch {
+        /* clap guarantees at least one address when --file is absent */
+        let mac = &macs[0];
+
+        /* Parse and validate MAC address, extracting the OUI */
+        let parsed = parse_mac_with(mac, &parse_options)?;
+
+        /* Get the path to the OUI database */
+        let csv_path: String = global.csv_path()?;
+
+        /*
+         * Find the manufacturer name from the longest matching prefix. A
+         * plain scan reads the file once without building anything; --mmap
+         * maps the whole file instead, which wins on very large databases.
+         * A manuf file always goes through the index,
+         * since the scan only understands the IEEE layout, and so does
+         * --registry-filter, since the scan tries every registry, and
+         * --conflict last, since the scan keeps the first row, and
+         * several --path files, which are all loaded up front. A cached
+         * index is quicker than either once it has been built.
+         */
+        let start = Instant::now();
+        let indexed = args.mmap
+            || !args.registry_filter.is_empty()
+            || args.conflict == ConflictArg::Last
+            || !global.extra_paths().is_empty()
+            || args.cache_dir().is_some()
+            || is_manuf(&csv_path)?;
+        if indexed {
+            warn_scan_limit_ignored(args, err)?;
+        }
+        let mut entry = if indexed {
+            let dbs = open_databases(args, global)?;
+            resolve_entry(&dbs, mac, &parse_options, args.registries())
+        } else {
+            let (result, registry) =
+                lookup_longest_with(&csv_path, &parsed.full, &args.scan_options())?;
+            let mut entry = Entry::new(mac, Some(parsed), Ok(result));
+            entry.registry = registry;
+            entry.source = registry.is_some().then_some(csv_path);
+            entry
+        };
+        /* Loading counts too, since that is the cost of a one-off lookup */
+        if global.verbose {
+            writeln!(
+                err,
+                "Lookup took {:.2} ms ({}).",
+                start.elapsed().as_secs_f64() * 1000.0,
+                if indexed {
+                    "database loaded into an index"
+                } else {
+                    "database scanned"
+                }
+            )?;
+        }
+        entry.registry = found_registry(&entry, entry.registry);
+        vec![entry]
+    } else if args.low_memory && !is_manuf(&global.csv_path()?)? {
+        /* No index: scan per address, skipping OUIs already known to be absent */
+        let csv_path = global.csv_path()?;
+        let mut scan =
+            ScanLookup::with_options(&csv_path, NEGATIVE_CACHE_SIZE, args.scan_options());
+        let mut entries = Vec::with_capacity(macs.len());
+        for mac in &macs {
+            let mut entry = match parse_mac_with(mac, &parse_options) {
+                Ok(parsed) => {
+                    let (result, registry) = scan.lookup_longest(&parsed.full)?;
+                    let mut entry = Entry::new(mac, Some(parsed), Ok(result));
+                    entry.registry = registry;
+                    entry.source = registry.map(|_| csv_path.clone());
+                    entry
+                }
+                Err(e) => Entry::new(mac, None, Err(e)),
+            };
+            entry.registry = found_registry(&entry, entry.registry);
+            entries.push(entry);
+        }
+        if global.verbose {
+            writeln!(
+                err,
+                "Scanned the database {} times for {} addresses.",
+                scan.scans(),
+                macs.len()
+            )?;
+        }
+        entries
+    } else {
+        /* Load the databases once and resolve every address against them */
+        warn_scan_limit_ignored(args, err)?;
+        let dbs = open_databases(args, global)?;
+        let resolve = |mac: &String| resolve_entry(&dbs, mac, &parse_options, args.registries());
+
+        /*
+         * RAYON:
+         * par_iter() splits the addresses across a pool of worker threads,
+         * which all read the same OuiDatabase; a shared reference is enough
+         * because lookups never modify it. collect() on an indexed parallel
+         * iterator puts every result back at its input position, so output
+         * order is the same as with one thread.
+         */
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(args.threads.map_or(0, NonZeroUsize::get))
+            .build()
+            .map_err(|e| OuiError::Io(io::Error::other(e)))?;
+        pool.install(|| macs.par_iter().map(resolve).collect())
+    };
+
+    if args.self_ {
+        /* Interface addresses were appended last, after any given ones */
+        let offset = entries.len() - interfaces.len();
+        for (entry, name) in entries[offset..].iter_mut().zip(interfaces) {
+            entry.interface = name;
+        }
+    }
+
+    if args.online_fallback {
+        resolve_online(&mut entries, args, err)?;
+    }
+
+    let annotator = Annotator::new(args)?;
+    for entry in &mut entries {
+        annotator.annotate(entry);
+    }
+    sort_entries(&mut entries, args.sort_output);
+
+    if global.verbose {
+        warn_missing_vendors(&entries, err)?;
+    }
+
+    /* Health checks only want the totals, and a status that says "all found" */
+    if args.count_only {
+        let matched = entries
+            .iter()
+            .filter(|e| matches!(e.outcome, Ok(LookupResult::Found(_))))
+            .count();
+        writeln!(err, "matched={} total={}", matched, entries.len())?;
+        return Ok(if matched == entries.len() {
+            ExitStatus::Success
+        } else {
+            ExitStatus::Failure
+        });
+    }
+
+    let style = Style {
+        verbose: global.verbose,
+        preserve_input: args.preserve_input,
+        shell_escape: args.shell_escape,
+        truncate_vendor: args.truncate_vendor,
+        show_source: args.show_source,
+    };
+
+    if batch && args.no_newline {
+        writeln!(err, "Warning: --no-newline is ignored in batch mode.")?;
+    }
+
+    if format == OutputFormat::Ndjson {
+        let records: Vec<LookupRecord> = entries.iter().map(|e| e.record(&style)).collect();
+        print_ndjson(out, &records)?;
+    } else if format == OutputFormat::Json {
+        let records: Vec<LookupRecord> = entries.iter().map(|e| e.record(&style)).collect();
+        if batch {
+            print_json(out, &records, global.json_pretty)?;
+        } else {
+            print_json(out, &records[0], global.json_pretty)?;
+        }
+    } else if format == OutputFormat::Env {
+        /* single_address() leaves exactly one entry */
+        match &entries[0].outcome {
+            Ok(_) => write_env(out, &entries[0].record(&style))?,
+            Err(e) => writeln!(err, "Error: {}: {}", entries[0].input, e)?,
+        }
+    } else if format == OutputFormat::Yaml {
+        let records: Vec<LookupRecord> = entries.iter().map(|e| e.record(&style)).collect();
+        if batch {
+            print_yaml(out, &records)?;
+        } else {
+            print_yaml(out, &records[0])?;
+        }
+    } else if format == OutputFormat::Csv || format == OutputFormat::Table {
+        /* Invalid addresses have no columns to fill, so they go to err as usual */
+        let mut records = Vec::new();
+        for entry in &entries {
+            match &entry.outcome {
+                Ok(_) => records.push(entry.record(&style)),
+                Err(e) => writeln!(err, "Error: {}: {}", entry.input, e)?,
+            }
+        }
+        let mut fields = args.field.clone();
+        if fields.is_empty() {
+            if format == OutputFormat::Csv {
+                fields.extend(Field::CSV_DEFAULT);
+            } else {
+                fields.extend(Field::TABLE_DEFAULT);
+            }
+            if args.self_ {
+                fields.insert(0, Field::Interface);
+            }
+            if !annotator.policy.is_empty() {
+                fields.push(Field::Verdict);
+            }
+            if args.show_source {
+                fields.push(Field::Source);
+            }
+        }
+        if format == OutputFormat::Csv {
+            let summary = args.summary.then(|| Summary::of(&entries));
+            write_csv(out, &records, &fields, summary.as_ref())?;
+        } else {
+            let max_width = args.truncate_vendor.unwrap_or(args.max_width);
+            write_table(out, &records, &fields, max_width)?;
+        }
+    } else {
+        for entry in &entries {
+            print_entry(entry, args, template.as_ref(), &style, batch, out, err)?;
+        }
+        if args.field.is_empty() && template.is_none() && !global.quiet {
+            suggest_closest(&entries, &global.csv_path()?, err)?;
+        }
+    }
 
-    /* Validate argument count (program takes exactly one argument) */
-    if args.len() != 2 {
-        return Err("OUI takes a single argument.".into());
+    /* A capture can hold hundreds of addresses, so end with a per-vendor tally */
+    if from_capture(args)
+        && format == OutputFormat::Plain
+        && args.field.is_empty()
+        && template.is_none()
+    {
+        writeln!(out)?;
+        for (vendor, count) in vendor_counts(&entries) {
+            writeln!(out, "{:>6}  {}", count, vendor)?;
+        }
     }
 
-    /* Parse and validate MAC address, extracting the OUI */
-    let mac = parse_mac(&args[1])?;
+    let blocked = entries.iter().any(|e| e.verdict == Some(Verdict::Blocked));
+    let invalid = entries.iter().any(|e| e.outcome.is_err());
+
+    Ok(if blocked {
+        ExitStatus::Blocked
+    } else if invalid {
+        ExitStatus::Failure
+    } else {
+        ExitStatus::Success
+    })
+}
+
+/*
+ * resolve_entry - Parse one address and look it up in the loaded databases
+ * @dbs: The databases to search, in --path order; the first hit wins
+ * @mac: Address as supplied
+ * @parse_options: Validation settings
+ * @registries: Which registries may match, from --registry-filter
+ *
+ * Return: The entry, holding the parse error if the address is invalid
+ */
+fn resolve_entry(
+    dbs: &[OuiDatabase],
+    mac: &str,
+    parse_options: &ParseOptions,
+    registries: &[Registry],
+) -> Entry {
+    match parse_mac_with(mac, parse_options) {
+        Ok(parsed) => {
+            let found = dbs.iter().find_map(|db| {
+                let (registry, vendor) = db.lookup_longest_in(&parsed.full, registries)?;
+                Some((registry, vendor, db.path()))
+            });
+            let result = match found {
+                Some((_, vendor, _)) => LookupResult::Found(vendor.to_string()),
+                None => LookupResult::NotFound,
+            };
+            let registry = found.map(|(registry, _, _)| registry.for_hit(&parsed.full));
+            let mut entry = Entry::new(mac, Some(parsed), Ok(result));
+            entry.registry = registry;
+            entry.source = found.map(|(_, _, source)| source.to_string());
+            entry
+        }
+        Err(e) => Entry::new(mac, None, Err(e)),
+    }
+}
 
-    /* Get the path to the OUI database */
-    let csv_path: String = get_csv_path()?;
+/*
+ * resolve_online - Look up the database's misses with --online-fallback
+ * @entries: The looked-up entries, updated in place
+ * @args: Lookup options, for the endpoint and timeout
+ * @err: Where failed requests are reported
+ *
+ * NETWORK:
+ * Only misses are sent, and only universally administered ones, since a
+ * local address was never assigned to anyone. Each OUI is asked about once
+ * per run, which keeps batches inside the rate limits free APIs set. A
+ * request that fails or times out leaves the entry a miss and says why on
+ * err, so the network can't turn a lookup into an error.
+ *
+ * Return: Result indicating whether the warnings could be written
+ */
+fn resolve_online(
+    entries: &mut [Entry],
+    args: &LookupArgs,
+    err: &mut dyn Write,
+) -> Result<(), OuiError> {
+    let timeout = Duration::from_secs(args.online_timeout);
+    let mut answers: HashMap<String, Option<String>> = HashMap::new();
 
-    /* Find the manufacturer name from the OUI */
-    lookup_oui(&csv_path, &mac)?;
+    for entry in entries {
+        let Some(mac) = &entry.mac else {
+            continue;
+        };
+        let universal = mac.first_octet().is_some_and(|octet| octet & 0b10 == 0);
+        if !matches!(entry.outcome, Ok(LookupResult::NotFound)) || !universal {
+            continue;
+        }
 
+        let vendor = match answers.get(&mac.oui) {
+            Some(vendor) => vendor.clone(),
+            None => {
+                let vendor = match lookup_online(&args.online_url, mac, timeout) {
+                    Ok(LookupResult::Found(vendor)) => Some(vendor),
+                    Ok(LookupResult::NotFound | LookupResult::ScanLimit(_)) => None,
+                    Err(e) => {
+                        writeln!(err, "Warning: {}: {}", entry.input, e)?;
+                        None
+                    }
+                };
+                answers.insert(mac.oui.clone(), vendor.clone());
+                vendor
+            }
+        };
+        if let Some(vendor) = vendor {
+            entry.outcome = Ok(LookupResult::Found(vendor));
+            entry.online = true;
+        }
+    }
     Ok(())
 }
 
 /*
- * main - Program entry point
- * 
- * IF LET SYNTAX: 
- * "if let" is a more consise alternative to match when you only care about
- * one case. Here, we only care if an error is returned.
- * 
- * ERROR OUTPUT:
- * eprintln! prints to stderr instead of stdout, equivalent to running
- *  
+ * warn_missing_vendors - Point out hits on rows with no vendor column, for --verbose
+ * @entries: The looked-up entries
+ * @err: Where the warnings are written
+ *
+ * Such a row is reported as UNKNOWN_VENDOR, which would otherwise pass
+ * for an answer. Each database behind one of those hits is read again to
+ * find the rows, which only costs anything when the database is damaged.
+ *
+ * Return: Result indicating whether the database could be read and the warnings written
  */
-fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-        process::exit(1);
+fn warn_missing_vendors(entries: &[Entry], err: &mut dyn Write) -> Result<(), OuiError> {
+    let mut rows_by_source: HashMap<&str, Vec<(String, u64)>> = HashMap::new();
+    for entry in entries {
+        let (Ok(LookupResult::Found(vendor)), Some(mac), Some(source)) =
+            (&entry.outcome, &entry.mac, &entry.source)
+        else {
+            continue;
+        };
+        if vendor != UNKNOWN_VENDOR {
+            continue;
+        }
+        /* A manuf file has no vendor column to lose */
+        if !rows_by_source.contains_key(source.as_str()) {
+            let rows = if is_manuf(source)? {
+                Vec::new()
+            } else {
+                vendorless_rows(source)?
+            };
+            rows_by_source.insert(source, rows);
+        }
+        /* The longest key is the one the lookup matched */
+        let row = rows_by_source[source.as_str()]
+            .iter()
+            .filter(|(key, _)| key.len() >= OUI_LENGTH && mac.full.starts_with(key.as_str()))
+            .max_by_key(|(key, _)| key.len());
+        if let Some((key, line)) = row {
+            writeln!(
+                err,
+                "Warning: {}: OUI {} at line {} of {} has no vendor column.",
+                entry.input,
+                format_oui(key),
+                line,
+                source
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/* Say when --allow-malformed-length had to guess at a missing digit */
+fn warn_if_padded(input: &str, mac: &MacAddress, err: &mut dyn Write) -> io::Result<()> {
+    if mac.padded {
+        writeln!(
+            err,
+            "Warning: {}: one digit short, read as {}.",
+            input,
+            mac.canonical()
+        )?;
+    }
+    Ok(())
+}
+
+/* --max-scan-rows only caps scans, so say so when the database is indexed instead */
+fn warn_scan_limit_ignored(args: &LookupArgs, err: &mut dyn Write) -> Result<(), OuiError> {
+    if args.max_scan_rows.is_some() {
+        writeln!(
+            err,
+            "Warning: --max-scan-rows is ignored when the database is loaded into an index."
+        )?;
+    }
+    Ok(())
+}
+
+/*
+ * suggest_closest - Point out a near miss for each address that wasn't found
+ * @entries: The looked-up entries
+ * @csv_path: The database they were looked up in
+ * @err: Where the hints are written
+ *
+ * Locally administered addresses were never assigned, so a nearby OUI
+ * says nothing about them and they get no hint. The table is only loaded
+ * when there is a miss to explain.
+ *
+ * Return: Result indicating whether the database could be read
+ */
+fn suggest_closest(entries: &[Entry], csv_path: &str, err: &mut dyn Write) -> Result<(), OuiError> {
+    let misses: Vec<(&Entry, &MacAddress)> = entries
+        .iter()
+        .filter(|e| matches!(e.outcome, Ok(LookupResult::NotFound)))
+        .filter_map(|e| Some((e, e.mac.as_ref()?)))
+        .filter(|(_, mac)| mac.first_octet().is_some_and(|octet| octet & 0b10 == 0))
+        .collect();
+    if misses.is_empty() || is_manuf(csv_path)? {
+        return Ok(());
+    }
+
+    let table = VendorTable::open(csv_path)?;
+    for (entry, mac) in misses {
+        if let Some(closest) = table.closest(&mac.oui) {
+            writeln!(
+                err,
+                "Hint: {}: closest known prefix: {} ({})",
+                entry.input,
+                format_oui(&closest.oui),
+                closest.vendor
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/*
+ * found_registry - The registry to report for a hit
+ * @entry: The looked-up entry
+ * @registry: The registry the lookup matched, if it knows
+ *
+ * A plain scan only compares whole OUIs, so any hit it makes is an MA-L,
+ * or a CID if the address is in the ELI quadrant.
+ */
+fn found_registry(entry: &Entry, registry: Option<Registry>) -> Option<Registry> {
+    match (&entry.outcome, &entry.mac) {
+        (Ok(LookupResult::Found(_)), Some(mac)) => {
+            Some(registry.unwrap_or(Registry::MaL).for_hit(&mac.full))
+        }
+        _ => None,
+    }
+}
+
+/*
+ * Annotator - Everything added to an entry once it has been looked up
+ */
+struct Annotator {
+    classifiers: Vec<Box<dyn Classifier>>, /* From --classifier, in the order given */
+    metadata: Option<MetadataTable>,       /* From --metadata */
+    policy: VendorPolicy,                  /* From --allow-vendor and --block-vendor */
+    suffixes: Vec<String>,                 /* From --strip-vendor-suffix and --vendor-suffix */
+}
+
+impl Annotator {
+    /* Load whatever the lookup options ask for */
+    fn new(args: &LookupArgs) -> Result<Self, OuiError> {
+        Ok(Annotator {
+            /* clap has already checked every name against CLASSIFIER_NAMES */
+            classifiers: args
+                .classifier
+                .iter()
+                .filter_map(|name| classifier_by_name(name))
+                .collect(),
+            metadata: args
+                .metadata
+                .as_deref()
+                .map(MetadataTable::open)
+                .transpose()?,
+            policy: VendorPolicy::new(&args.allow_vendor, &args.block_vendor),
+            suffixes: if args.strip_vendor_suffix {
+                VENDOR_SUFFIXES
+                    .iter()
+                    .map(|suffix| suffix.to_string())
+                    .chain(
+                        args.vendor_suffix
+                            .iter()
+                            .map(|suffix| suffix.trim_end_matches('.').to_string()),
+                    )
+                    .collect()
+            } else {
+                Vec::new()
+            },
+        })
+    }
+
+    /* Add the classes, vendor metadata and policy verdict to one entry, and strip its vendor */
+    fn annotate(&self, entry: &mut Entry) {
+        if let Some(mac) = &entry.mac {
+            entry.classes = self
+                .classifiers
+                .iter()
+                .filter_map(|c| Some((c.name().to_string(), c.classify(mac)?)))
+                .collect();
+        }
+        if let (Some(table), Ok(LookupResult::Found(vendor))) = (&self.metadata, &entry.outcome) {
+            entry.metadata = table.get(vendor).cloned();
+        }
+        /* The verdict is only reported when a policy was actually given */
+        if !self.policy.is_empty()
+            && let Ok(result) = &entry.outcome
+        {
+            entry.verdict = Some(self.policy.check(result));
+        }
+        /* Last, so metadata and the policy still see the registered name */
+        if !self.suffixes.is_empty()
+            && let Ok(LookupResult::Found(vendor)) = &mut entry.outcome
+        {
+            *vendor = strip_vendor_suffix(vendor, &self.suffixes).to_string();
+        }
+    }
+}
+
+/*
+ * lookup_until_match - Resolve addresses one at a time, stopping at the first hit
+ * @args: Lookup options
+ * @global: Output options shared by all subcommands
+ * @out: Where results are written
+ * @err: Where invalid addresses are reported
+ *
+ * STREAMING:
+ * Unlike the batch path, nothing is read ahead: each line of --file is
+ * looked up and printed as soon as it arrives, so a monitor fed by a
+ * never-ending pipe reacts to the first address that matters. With a
+ * vendor policy that is the first blocked result; without one, the first
+ * address whose vendor is known. Output is flushed after every line so
+ * whatever reads it has seen everything up to the match.
+ *
+ * Return: Result containing Blocked or Matched if the stream was cut short, otherwise the usual status
+ */
+fn lookup_until_match(
+    args: &LookupArgs,
+    global: &GlobalArgs,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<ExitStatus, OuiError> {
+    if global.format() != OutputFormat::Plain {
+        return Err(OuiError::Usage(
+            "--exit-on-first-match only supports plain output.".to_string(),
+        ));
+    }
+
+    let dbs = open_databases(args, global)?;
+    let annotator = Annotator::new(args)?;
+    let parse_options = args.parse_options();
+    let style = Style {
+        verbose: global.verbose,
+        preserve_input: args.preserve_input,
+        shell_escape: args.shell_escape,
+        truncate_vendor: args.truncate_vendor,
+        show_source: args.show_source,
+    };
+    let template = output_template(args, OutputFormat::Plain)?;
+    let mut invalid = false;
+
+    let mut check = |mac: &str, interface: Option<String>| -> Result<bool, OuiError> {
+        let mut entry = resolve_entry(&dbs, mac, &parse_options, args.registries());
+        if let Some(parsed) = &entry.mac {
+            warn_if_padded(mac, parsed, err)?;
+        }
+        entry.interface = interface;
+        annotator.annotate(&mut entry);
+        print_entry(&entry, args, template.as_ref(), &style, true, out, err)?;
+        out.flush()?;
+        invalid |= entry.outcome.is_err();
+        Ok(if annotator.policy.is_empty() {
+            matches!(entry.outcome, Ok(LookupResult::Found(_)))
+        } else {
+            entry.verdict == Some(Verdict::Blocked)
+        })
+    };
+
+    let mut matched = false;
+    for mac in &args.macs {
+        if check(mac, None)? {
+            matched = true;
+            break;
+        }
+    }
+    if let Some(file) = args.file.as_deref().filter(|_| !matched) {
+        let reader: Box<dyn BufRead> = if file == "-" {
+            Box::new(io::stdin().lock())
+        } else {
+            Box::new(io::BufReader::new(fs::File::open(file)?))
+        };
+        for line in reader.lines() {
+            let line = line?;
+            if let Some(mac) = input_address(&line, args.input_format)
+                && check(mac, None)?
+            {
+                matched = true;
+                break;
+            }
+        }
+    }
+    #[cfg(feature = "pcap")]
+    if let Some(capture) = args.pcap.as_deref().filter(|_| !matched) {
+        for mac in oui::read_pcap_macs(capture)? {
+            if check(&mac, None)? {
+                matched = true;
+                break;
+            }
+        }
+    }
+    if args.self_ && !matched {
+        for interface in local_interfaces()? {
+            if check(&interface.mac, Some(interface.name))? {
+                matched = true;
+                break;
+            }
+        }
+    }
+
+    Ok(match (matched, annotator.policy.is_empty()) {
+        (true, false) => ExitStatus::Blocked,
+        (true, true) => ExitStatus::Matched,
+        (false, _) if invalid => ExitStatus::Failure,
+        (false, _) => ExitStatus::Success,
+    })
+}
+
+/*
+ * output_template - Parse --output-template, if given, for the chosen format
+ * @args: Lookup options
+ * @format: The output format in effect
+ *
+ * Return: Result containing the template, or a usage error if it is malformed or the format isn't plain
+ */
+fn output_template(args: &LookupArgs, format: OutputFormat) -> Result<Option<Template>, OuiError> {
+    let Some(text) = &args.output_template else {
+        return Ok(None);
+    };
+    if format != OutputFormat::Plain {
+        return Err(OuiError::Usage(
+            "--output-template only applies to plain output.".to_string(),
+        ));
+    }
+    Template::parse(text)
+        .map(Some)
+        .map_err(|e| OuiError::Usage(format!("Invalid --output-template: {}.", e)))
+}
+
+/*
+ * print_entry - Write one entry as a plain, --field or --output-template line
+ * @entry: The annotated entry
+ * @args: Lookup options, for --field, --no-newline and --echo-input
+ * @template: The parsed --output-template, which takes precedence
+ * @style: Presentation choices
+ * @batch: Whether several addresses are being printed
+ * @out: Where results are written
+ * @err: Where invalid addresses are reported
+ *
+ * With --echo-input every kind of line starts with the input exactly as
+ * given and a tab, so the address is always the first field to cut or awk
+ * on, even when --field lists input again or --verbose rewrites it.
+ *
+ * Return: Result indicating whether the line could be written
+ */
+fn print_entry(
+    entry: &Entry,
+    args: &LookupArgs,
+    template: Option<&Template>,
+    style: &Style,
+    batch: bool,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> io::Result<()> {
+    let echo = if args.echo_input {
+        format!("{}\t", entry.input)
+    } else {
+        String::new()
+    };
+    match &entry.outcome {
+        Ok(_) if let Some(template) = template => {
+            writeln!(out, "{}{}", echo, template.render(entry, style))
+        }
+        Ok(_) if !args.field.is_empty() => {
+            let fields = format_fields(&entry.record(style), &args.field);
+            writeln!(out, "{}{}", echo, fields)
+        }
+        /*
+         * print! leaves the line open, so $(oui --no-newline ...)
+         * captures the vendor without needing to trim anything
+         */
+        Ok(result) if !batch && args.no_newline => {
+            write!(out, "{}{}", echo, format_result(entry, result, style))
+        }
+        Ok(result) => writeln!(out, "{}{}", echo, format_result(entry, result, style)),
+        Err(e) => writeln!(err, "Error: {}: {}", entry.input, e),
+    }
+}
+
+/*
+ * AllRecord - Every vendor found for one address, for --all with JSON or YAML
+ */
+#[derive(Serialize)]
+struct AllRecord<'a> {
+    input: &'a str,
+    mac: Option<String>,
+    oui: Option<String>,
+    vendors: Vec<VendorMatch>,
+    #[serde(skip_serializing_if = "is_zero")]
+    omitted: usize, /* Vendors cut by --max-results-per-mac */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/* For skip_serializing_if: omitted only appears when something was cut */
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/*
+ * lookup_all - List every vendor that claims each address, for --all
+ * @args: Lookup options, for --max-results-per-mac and parsing
+ * @macs: Addresses to look up
+ * @batch: Whether several addresses are being printed
+ * @global: Output options shared by all subcommands
+ * @out: Where results are written
+ * @err: Where invalid addresses and --max-results-per-mac notes are reported
+ *
+ * The database is kept as rows in a VendorTable rather than an index,
+ * since an index keeps only one vendor per key. Plain output gives one
+ * vendor per line, prefixed with the address in batch mode.
+ *
+ * Return: Result containing the exit status, or an error if the database can't be read
+ */
+fn lookup_all(
+    args: &LookupArgs,
+    macs: &[String],
+    batch: bool,
+    global: &GlobalArgs,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<ExitStatus, OuiError> {
+    let format = global.format();
+    if format == OutputFormat::Csv || format == OutputFormat::Table {
+        return Err(OuiError::Usage(
+            "--all only supports plain, JSON and YAML output.".to_string(),
+        ));
+    }
+
+    let table = VendorTable::open(&global.csv_path()?)?;
+    let parse_options = args.parse_options();
+    let records: Vec<AllRecord> = macs
+        .iter()
+        .map(|input| match parse_mac_with(input, &parse_options) {
+            Ok(mac) => {
+                let mut vendors = table.assignments_in(&mac.full, args.registries());
+                let limit = args.max_results_per_mac.unwrap_or(usize::MAX);
+                let omitted = vendors.len().saturating_sub(limit);
+                vendors.truncate(limit);
+                AllRecord {
+                    input,
+                    mac: Some(mac.canonical()),
+                    oui: Some(mac.oui),
+                    vendors,
+                    omitted,
+                    error: None,
+                }
+            }
+            Err(e) => AllRecord {
+                input,
+                mac: None,
+                oui: None,
+                vendors: Vec::new(),
+                omitted: 0,
+                error: Some(e.to_string()),
+            },
+        })
+        .collect();
+
+    if format == OutputFormat::Ndjson {
+        print_ndjson(out, &records)?;
+    } else if format == OutputFormat::Json && batch {
+        print_json(out, &records, global.json_pretty)?;
+    } else if format == OutputFormat::Json {
+        print_json(out, &records[0], global.json_pretty)?;
+    } else if format == OutputFormat::Yaml && batch {
+        print_yaml(out, &records)?;
+    } else if format == OutputFormat::Yaml {
+        print_yaml(out, &records[0])?;
+    } else {
+        for record in &records {
+            if let Some(e) = &record.error {
+                writeln!(err, "Error: {}: {}", record.input, e)?;
+                continue;
+            }
+            let prefix = if batch {
+                format!("{}\t", record.input)
+            } else {
+                String::new()
+            };
+            if record.vendors.is_empty() {
+                writeln!(out, "{}{}", prefix, LookupResult::NotFound)?;
+            }
+            for m in &record.vendors {
+                writeln!(out, "{}{}", prefix, m.vendor)?;
+            }
+        }
+    }
+    for record in records.iter().filter(|r| r.omitted > 0) {
+        writeln!(
+            err,
+            "Note: {}: {} more vendors not shown (--max-results-per-mac {}).",
+            record.input,
+            record.omitted,
+            record.vendors.len()
+        )?;
+    }
+
+    Ok(if records.iter().any(|r| r.error.is_some()) {
+        ExitStatus::Failure
+    } else {
+        ExitStatus::Success
+    })
+}
+
+/*
+ * analyze - Describe addresses from their bits alone, for --no-db
+ * @args: Lookup options, for the classifiers and parse settings
+ * @macs: Addresses to describe
+ * @batch: Whether to print a JSON or YAML list rather than one object
+ * @global: Output options shared by all subcommands
+ * @out: Where results are written
+ * @err: Where invalid addresses are reported
+ *
+ * Runs every built-in classifier unless --classifier picks some, and
+ * never resolves the database path, so it works before one is installed.
+ *
+ * Return: Result containing the exit status, or an error if output fails
+ */
+fn analyze(
+    args: &LookupArgs,
+    macs: &[String],
+    batch: bool,
+    global: &GlobalArgs,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<ExitStatus, OuiError> {
+    let format = global.format();
+    if format == OutputFormat::Csv || format == OutputFormat::Table || !args.field.is_empty() {
+        return Err(OuiError::Usage(
+            "--no-db only supports plain, JSON and YAML output.".to_string(),
+        ));
+    }
+
+    let names: Vec<&str> = if args.classifier.is_empty() {
+        CLASSIFIER_NAMES.to_vec()
+    } else {
+        args.classifier.iter().map(String::as_str).collect()
+    };
+    let classifiers: Vec<Box<dyn Classifier>> =
+        names.into_iter().filter_map(classifier_by_name).collect();
+
+    let parse_options = args.parse_options();
+    let records: Vec<AnalysisRecord> = macs
+        .iter()
+        .map(|input| match parse_mac_with(input, &parse_options) {
+            Ok(mac) => AnalysisRecord {
+                input: input.clone(),
+                mac: Some(mac.canonical()),
+                oui: Some(mac.oui.clone()),
+                classification: AddressKind::of(&mac),
+                error: None,
+                classes: classifiers
+                    .iter()
+                    .filter_map(|c| Some((c.name().to_string(), c.classify(&mac)?)))
+                    .collect(),
+            },
+            Err(e) => AnalysisRecord {
+                input: input.clone(),
+                mac: None,
+                oui: None,
+                classification: None,
+                error: Some(e.to_string()),
+                classes: BTreeMap::new(),
+            },
+        })
+        .collect();
+
+    if format == OutputFormat::Ndjson {
+        print_ndjson(out, &records)?;
+    } else if format == OutputFormat::Json && batch {
+        print_json(out, &records, global.json_pretty)?;
+    } else if format == OutputFormat::Json {
+        print_json(out, &records[0], global.json_pretty)?;
+    } else if format == OutputFormat::Yaml && batch {
+        print_yaml(out, &records)?;
+    } else if format == OutputFormat::Yaml {
+        print_yaml(out, &records[0])?;
+    } else {
+        for record in &records {
+            match (&record.mac, &record.error) {
+                (_, Some(e)) => writeln!(err, "Error: {}: {}", record.input, e)?,
+                (Some(mac), None) if record.classes.is_empty() => writeln!(out, "{}", mac)?,
+                (Some(mac), None) => {
+                    let classes: Vec<&str> = record.classes.values().map(String::as_str).collect();
+                    writeln!(out, "{}: {}", mac, classes.join("; "))?;
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    Ok(if records.iter().any(|r| r.error.is_some()) {
+        ExitStatus::Failure
+    } else {
+        ExitStatus::Success
+    })
+}
+
+/*
+ * trim_to_oui - Reduce addresses to their distinct OUIs, for --trim-to-oui
+ * @args: Lookup options, for the parse settings
+ * @macs: Addresses to reduce
+ * @global: Output options shared by all subcommands
+ * @out: Where the OUIs are written
+ * @err: Where invalid addresses are reported
+ *
+ * A BTreeSet keeps each OUI once and iterates in sorted order, so
+ * deduplicating and sorting happen as the addresses are inserted. Like
+ * --no-db, the database path is never resolved.
+ *
+ * Return: Result containing the exit status, or an error if output fails
+ */
+fn trim_to_oui(
+    args: &LookupArgs,
+    macs: &[String],
+    global: &GlobalArgs,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<ExitStatus, OuiError> {
+    let format = global.format();
+    if !matches!(
+        format,
+        OutputFormat::Plain | OutputFormat::Json | OutputFormat::Ndjson
+    ) {
+        return Err(OuiError::Usage(
+            "--trim-to-oui only supports plain and JSON output.".to_string(),
+        ));
+    }
+
+    let parse_options = args.parse_options();
+    let mut ouis = BTreeSet::new();
+    let mut invalid = false;
+    for mac in macs {
+        match parse_mac_with(mac, &parse_options) {
+            Ok(parsed) => {
+                ouis.insert(format_oui(&parsed.oui));
+            }
+            Err(e) => {
+                writeln!(err, "Error: {}: {}", mac, e)?;
+                invalid = true;
+            }
+        }
+    }
+
+    let ouis: Vec<String> = ouis.into_iter().collect();
+    match format {
+        OutputFormat::Json => print_json(out, &ouis, global.json_pretty)?,
+        OutputFormat::Ndjson => print_ndjson(out, &ouis)?,
+        _ => {
+            for oui in &ouis {
+                writeln!(out, "{}", oui)?;
+            }
+        }
+    }
+
+    Ok(if invalid {
+        ExitStatus::Failure
+    } else {
+        ExitStatus::Success
+    })
+}
+
+/*
+ * print_diff - Write a --diff report as plain text
+ * @diff: The differences between the two files
+ * @out: Where the report is written
+ *
+ * Return: Result indicating whether the report could be written
+ */
+fn print_diff(diff: &DatabaseDiff, out: &mut dyn Write) -> io::Result<()> {
+    if diff.is_empty() {
+        return writeln!(out, "No differences.");
+    }
+    writeln!(out, "Added ({}):", diff.added.len())?;
+    for m in &diff.added {
+        writeln!(out, "  {}\t{}", m.oui, m.vendor)?;
+    }
+    writeln!(out, "Removed ({}):", diff.removed.len())?;
+    for m in &diff.removed {
+        writeln!(out, "  {}\t{}", m.oui, m.vendor)?;
+    }
+    writeln!(out, "Changed ({}):", diff.changed.len())?;
+    for c in &diff.changed {
+        writeln!(out, "  {}\t{} -> {}", c.oui, c.old, c.new)?;
+    }
+    Ok(())
+}
+
+/*
+ * print_vendor_counts - Write a --count-vendors report as plain text
+ * @counts: The totals and ranking
+ * @out: Where the report is written
+ *
+ * Return: Result indicating whether the report could be written
+ */
+fn print_vendor_counts(counts: &VendorCounts, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "{} distinct vendors in {} rows.",
+        counts.distinct, counts.rows
+    )?;
+    let width = counts.top.first().map_or(0, |c| c.ouis.to_string().len());
+    for (rank, count) in counts.top.iter().enumerate() {
+        writeln!(
+            out,
+            "{:>3}. {:>width$}  {}",
+            rank + 1,
+            count.ouis,
+            count.vendor,
+            width = width
+        )?;
+    }
+    Ok(())
+}
+
+/*
+ * open_databases - Load every --path database, in order, for lookups
+ * @args: Lookup options, passed on to open_database
+ * @global: Options shared by all subcommands, for the paths
+ *
+ * The later paths are resolved like the first, so a missing one is named
+ * in the error and --show-source labels every file the same way.
+ *
+ * Return: Result containing the databases, or an error if any of them can't be read
+ */
+fn open_databases(args: &LookupArgs, global: &GlobalArgs) -> Result<Vec<OuiDatabase>, OuiError> {
+    let mut dbs = vec![open_database(args, &global.csv_path()?)?];
+    for path in global.extra_paths() {
+        dbs.push(open_database(args, &resolve_csv_path(Some(path))?)?);
+    }
+    Ok(dbs)
+}
+
+/*
+ * open_database - Load the database the way the lookup options ask for
+ * @args: Lookup options, for --mmap, --cache-dir, --vendor-name and --conflict
+ * @csv_path: The database file
+ *
+ * Return: Result containing the loaded database, or an error if it can't be read
+ */
+fn open_database(args: &LookupArgs, csv_path: &str) -> Result<OuiDatabase, OuiError> {
+    if is_manuf(csv_path)? {
+        OuiDatabase::open_manuf(csv_path, args.vendor_name.into())
+    } else if let Some(cache_dir) = args.cache_dir() {
+        OuiDatabase::open_cached_with(csv_path, Path::new(&cache_dir), args.conflict.into())
+    } else if args.mmap {
+        OuiDatabase::open_mmap_with(csv_path, args.conflict.into())
+    } else {
+        OuiDatabase::open_with(csv_path, args.conflict.into())
+    }
+}
+
+/*
+ * benchmark_db - Measure how this machine handles the configured database
+ * @args: Lookup options, for --mmap
+ * @global: Options shared by all subcommands, for the database path
+ * @lookups: How many random prefixes to look up
+ * @err: Where the report is written
+ *
+ * The prefixes come from a fixed-seed generator so runs are comparable,
+ * and are formatted before the clock starts so only the lookups are
+ * timed. black_box stops the compiler from optimizing the unused results
+ * away.
+ *
+ * Return: Result containing the exit status, or an error if the database can't be read
+ */
+fn benchmark_db(
+    args: &LookupArgs,
+    global: &GlobalArgs,
+    lookups: usize,
+    err: &mut dyn Write,
+) -> Result<ExitStatus, OuiError> {
+    let csv_path = global.csv_path()?;
+
+    let start = Instant::now();
+    let db = open_database(args, &csv_path)?;
+    let load = start.elapsed();
+
+    /* A 64-bit LCG (Knuth's MMIX constants) is plenty for picking prefixes */
+    let mut state: u64 = 0x5EED;
+    let prefixes: Vec<String> = (0..lookups)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            format!("{:06X}", (state >> 40) & 0xFF_FFFF)
+        })
+        .collect();
+
+    let start = Instant::now();
+    let hits = prefixes
+        .iter()
+        .filter(|oui| matches!(black_box(db.lookup(oui)), LookupResult::Found(_)))
+        .count();
+    let elapsed = start.elapsed();
+    let per_lookup = elapsed.as_nanos() / lookups.max(1) as u128;
+
+    writeln!(err, "Database:    {}", csv_path)?;
+    writeln!(
+        err,
+        "Load:        {:.1} ms ({} entries{})",
+        load.as_secs_f64() * 1000.0,
+        db.len(),
+        if args.mmap { ", mmap" } else { "" }
+    )?;
+    writeln!(
+        err,
+        "Lookups:     {} in {:.1} ms, {} hits",
+        lookups,
+        elapsed.as_secs_f64() * 1000.0,
+        hits
+    )?;
+    writeln!(err, "Per lookup:  {} ns", per_lookup)?;
+    writeln!(
+        err,
+        "Memory:      ~{:.1} MiB",
+        db.memory_estimate() as f64 / (1024.0 * 1024.0)
+    )?;
+
+    Ok(ExitStatus::Success)
+}
+
+/* Whether the addresses include ones read with --pcap */
+#[cfg(feature = "pcap")]
+fn from_capture(args: &LookupArgs) -> bool {
+    args.pcap.is_some()
+}
+
+#[cfg(not(feature = "pcap"))]
+fn from_capture(_args: &LookupArgs) -> bool {
+    false
+}
+
+/*
+ * vendor_counts - Tally how many addresses resolved to each vendor
+ * @entries: The looked-up addresses
+ *
+ * Misses are grouped under "No match." and invalid addresses are left out.
+ *
+ * Return: Vendor and count pairs, most common first, ties in name order
+ */
+fn vendor_counts(entries: &[Entry]) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for entry in entries {
+        if let Ok(result) = &entry.outcome {
+            *counts.entry(result.to_string()).or_default() += 1;
+        }
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    /* The sort is stable, so equal counts keep the BTreeMap's name order */
+    counts.sort_by_key(|&(_, count)| Reverse(count));
+    counts
+}
+
+/*
+ * print_matches - Print database rows in the requested output format
+ * @matches: Rows from a search or from --head and --tail
+ * @global: Output options shared by all subcommands
+ * @out: Where the rows are written
+ *
+ * Return: Result indicating whether writing succeeded
+ */
+fn print_matches(
+    matches: &[VendorMatch],
+    global: &GlobalArgs,
+    out: &mut dyn Write,
+) -> Result<(), OuiError> {
+    if global.format() == OutputFormat::Json {
+        print_json(out, matches, global.json_pretty)?;
+    } else if global.format() == OutputFormat::Ndjson {
+        print_ndjson(out, matches)?;
+    } else if global.format() == OutputFormat::Yaml {
+        print_yaml(out, matches)?;
+    } else if global.format() == OutputFormat::Csv {
+        let mut writer = csv::Writer::from_writer(&mut *out);
+        writer.write_record(["oui", "vendor"])?;
+        for m in matches {
+            writer.write_record([&m.oui, &m.vendor])?;
+        }
+        writer.flush()?;
+    } else if matches.is_empty() {
+        writeln!(out, "No match.")?;
+    } else {
+        for m in matches {
+            writeln!(out, "{}\t{}", m.oui, m.vendor)?;
+        }
+    }
+    Ok(())
+}
+
+/*
+ * search - List the OUIs registered to matching manufacturers
+ * @args: The vendor query
+ * @global: Output options shared by all subcommands
+ * @out: Where results are written (stdout when run from main)
+ * @err: Where errors and notes are written (stderr when run from main)
+ *
+ * Return: Result containing the exit status, or an error if the database can't be read
+ */
+fn search(
+    args: &SearchArgs,
+    global: &GlobalArgs,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<ExitStatus, OuiError> {
+    warn_if_stale(global, err)?;
+    if global.format() == OutputFormat::Env {
+        return Err(OuiError::Usage(
+            "--output env only supports looking up a single address.".to_string(),
+        ));
+    }
+    if args.stdin {
+        return search_stdin(args, global, out, err);
+    }
+
+    /* clap requires VENDOR unless --stdin is given */
+    let vendor = args.vendor.as_deref().unwrap_or_default();
+    let matches = lookup_by_vendor_with(&global.csv_path()?, vendor, &args.search_options())?;
+    let (matches, omitted) = sort_and_limit(matches, args);
+
+    print_matches(&matches, global, out)?;
+    note_omitted(None, omitted, matches.len(), err)?;
+
+    Ok(ExitStatus::Success)
+}
+
+/*
+ * QueryMatches - One --stdin query and what it found, for JSON and YAML
+ */
+#[derive(Serialize)]
+struct QueryMatches<'a> {
+    query: &'a str,
+    matches: Vec<VendorMatch>,
+}
+
+/*
+ * search_stdin - Run one search per line of standard input
+ * @args: Search options, applied to every query
+ * @global: Output options shared by all subcommands
+ * @out: Where results are written
+ * @err: Where --limit notes are written
+ *
+ * The database is loaded once into a VendorTable and every query is
+ * matched against it. Plain output puts each query's matches under a
+ * "query:" header with a blank line between groups; CSV adds a query
+ * column, and JSON and YAML give a list of {query, matches} objects.
+ *
+ * Return: Result containing the exit status, or an error if the database or stdin can't be read
+ */
+fn search_stdin(
+    args: &SearchArgs,
+    global: &GlobalArgs,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<ExitStatus, OuiError> {
+    let table = VendorTable::open(&global.csv_path()?)?;
+    let queries = read_macs("-", InputFormat::Plain)?;
+
+    let mut groups = Vec::with_capacity(queries.len());
+    for query in &queries {
+        let (matches, omitted) = sort_and_limit(table.search(query, &args.search_options()), args);
+        note_omitted(Some(query), omitted, matches.len(), err)?;
+        groups.push(QueryMatches { query, matches });
+    }
+
+    match global.format() {
+        OutputFormat::Json => print_json(out, &groups, global.json_pretty)?,
+        OutputFormat::Ndjson => print_ndjson(out, &groups)?,
+        OutputFormat::Yaml => print_yaml(out, &groups)?,
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(&mut *out);
+            writer.write_record(["query", "oui", "vendor"])?;
+            for group in &groups {
+                for m in &group.matches {
+                    writer.write_record([group.query, &m.oui, &m.vendor])?;
+                }
+            }
+            writer.flush()?;
+        }
+        _ => {
+            for (i, group) in groups.iter().enumerate() {
+                if i > 0 {
+                    writeln!(out)?;
+                }
+                writeln!(out, "{}:", group.query)?;
+                print_matches(&group.matches, global, out)?;
+            }
+        }
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+/*
+ * sort_entries - Apply lookup --sort-output to resolved entries
+ * @entries: Entries in input order
+ * @order: What to sort by
+ *
+ * The sorts are stable, so entries with the same key keep their input
+ * order. Addresses that didn't parse, and misses when sorting by vendor,
+ * have no key and go last.
+ */
+fn sort_entries(entries: &mut [Entry], order: OutputOrder) {
+    match order {
+        OutputOrder::Input => {}
+        OutputOrder::Oui => entries.sort_by_cached_key(|e| {
+            let oui = e.mac.as_ref().map(|mac| mac.full.clone());
+            (oui.is_none(), oui)
+        }),
+        OutputOrder::Vendor => entries.sort_by_cached_key(|e| match &e.outcome {
+            Ok(LookupResult::Found(vendor)) => (false, vendor.to_lowercase()),
+            _ => (true, String::new()),
+        }),
+    }
+}
+
+/*
+ * sort_and_limit - Apply search --sort and --limit to a list of matches
+ * @matches: Matches in database order
+ * @args: Search options
+ *
+ * Sorting comes first so --limit keeps the top of the sorted list. The
+ * limit is applied after the full scan rather than by stopping early, so
+ * the note can say exactly how many matches were left out.
+ *
+ * Return: The matches to print and how many were cut
+ */
+fn sort_and_limit(mut matches: Vec<VendorMatch>, args: &SearchArgs) -> (Vec<VendorMatch>, usize) {
+    match args.sort {
+        Some(SortKey::Oui) => matches.sort_by(|a, b| a.oui.cmp(&b.oui)),
+        Some(SortKey::Vendor) => {
+            matches.sort_by_cached_key(|m| (m.vendor.to_lowercase(), m.oui.clone()))
+        }
+        None => {}
+    }
+
+    let omitted = match args.limit {
+        Some(limit) if matches.len() > limit => {
+            let omitted = matches.len() - limit;
+            matches.truncate(limit);
+            omitted
+        }
+        _ => 0,
+    };
+    (matches, omitted)
+}
+
+/* Tell the user how many matches --limit left out, naming the query if there are several */
+fn note_omitted(
+    query: Option<&str>,
+    omitted: usize,
+    shown: usize,
+    err: &mut dyn Write,
+) -> io::Result<()> {
+    if omitted > 0 {
+        let label = query.map(|q| format!("{}: ", q)).unwrap_or_default();
+        writeln!(
+            err,
+            "Note: {}{} more matches not shown (--limit {}).",
+            label, omitted, shown
+        )?;
+    }
+    Ok(())
+}
+
+/*
+ * update - Replace the local database with the latest IEEE registry
+ * @args: Where to download from, and whether to skip the overwrite prompt
+ * @global: Options shared by all subcommands, for the database path
+ * @err: Where the prompt and progress note are written
+ *
+ * Return: Result containing the exit status (failure if the prompt is declined), or an error if the download fails
+ */
+fn update(
+    args: &UpdateArgs,
+    global: &GlobalArgs,
+    err: &mut dyn Write,
+) -> Result<ExitStatus, OuiError> {
+    let csv_path = global.update_path()?;
+
+    /*
+     * Only someone at a terminal can answer, so scripts and cron jobs
+     * with stdin redirected never wait on the prompt
+     */
+    let stdin = io::stdin();
+    if !args.assume_yes && stdin.is_terminal() && Path::new(&csv_path).exists() {
+        write!(err, "Replace the existing database at {}? [y/N] ", csv_path)?;
+        err.flush()?;
+        let mut answer = String::new();
+        stdin.lock().read_line(&mut answer)?;
+        if !matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+            writeln!(err, "Update cancelled.")?;
+            return Ok(ExitStatus::Failure);
+        }
+    }
+
+    let rows = download_database(&args.url, Path::new(&csv_path))?;
+    writeln!(err, "Saved {} entries to {}", rows, csv_path)?;
+    Ok(ExitStatus::Success)
+}
+
+/*
+ * normalize - Convert a vendor database to the canonical format
+ * @args: Input and output files and the input's layout
+ * @err: Where the summary and any --columns auto fallback are reported
+ *
+ * With --columns auto, a header that names its columns overrides the
+ * positional settings; one that doesn't leaves them in place, with a
+ * warning, rather than failing, since the defaults fit most exports.
+ *
+ * Return: Result containing the exit status, or an error if a file can't be read or written
+ */
+fn normalize(args: &NormalizeArgs, err: &mut dyn Write) -> Result<ExitStatus, OuiError> {
+    let source = Path::new(&args.in_file);
+    let mut options = args.normalize_options();
+    if args.columns == Columns::Auto {
+        match detect_columns(source, args.delimiter)? {
+            Some(detected) => options = detected,
+            None => writeln!(
+                err,
+                "Warning: no OUI and vendor column names found in the header of {}; using columns {} and {}.",
+                args.in_file, args.oui_column, args.vendor_column
+            )?,
+        }
+    }
+    let rows = normalize_database(source, Path::new(&args.out_file), &options)?;
+    writeln!(err, "Wrote {} entries to {}", rows, args.out_file)?;
+    Ok(ExitStatus::Success)
+}
+
+/*
+ * Check - Severity of one doctor result
+ */
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Check {
+    Ok,
+    Warn,
+    Fail,
+}
+
+const DEFAULT_TOP_VENDORS: usize = 10; /* Vendors ranked by --count-vendors without --top */
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/*
+ * explain_formats - Print the accepted MAC notations with what each example parses to
+ * @out: Where the list is written
+ *
+ * Each example goes through the parser, so a notation the parser stops
+ * accepting shows up here as an error instead of quietly going out of date.
+ *
+ * Return: Result indicating whether the list could be written
+ */
+fn explain_formats(out: &mut dyn Write) -> Result<(), OuiError> {
+    writeln!(out, "Accepted MAC address formats:")?;
+    for format in MAC_FORMATS {
+        let parsed = match parse_mac_with(format.example, &format.options) {
+            Ok(mac) => mac.canonical(),
+            Err(e) => format!("not accepted: {}", e),
+        };
+        writeln!(
+            out,
+            "  {:<18} {:<26} -> {}",
+            format.name, format.example, parsed
+        )?;
+        if !format.note.is_empty() {
+            writeln!(out, "  {:<18} {}", "", format.note)?;
+        }
+    }
+    Ok(())
+}
+
+/*
+ * warn_if_stale - Warn before results when the database is getting old
+ * @global: Options shared by all subcommands, for the path and threshold
+ * @err: Where the warning is written
+ *
+ * A database that can't be found or read isn't warned about here; the
+ * lookup itself will report that properly.
+ *
+ * Return: Result indicating whether the warning could be written
+ */
+fn warn_if_stale(global: &GlobalArgs, err: &mut dyn Write) -> Result<(), OuiError> {
+    let stale_days = global.stale_days();
+    if stale_days == 0 {
+        return Ok(());
+    }
+    let Some(age) = global
+        .csv_path()
+        .ok()
+        .and_then(|path| database_age(&path).ok())
+    else {
+        return Ok(());
+    };
+    let days = age.as_secs() / SECONDS_PER_DAY;
+    if days >= stale_days {
+        writeln!(
+            err,
+            "Warning: the database is {} days old; run \"oui update\" for recent assignments.",
+            days
+        )?;
+    }
+    Ok(())
+}
+
+/*
+ * report - Print one doctor line with its status tag
+ * @out: Where to write the line
+ * @color: Whether to color the tag green, yellow or red
+ * @check: How the check turned out
+ * @message: What was checked and what was found
+ */
+fn report(out: &mut dyn Write, color: bool, check: Check, message: &str) -> io::Result<()> {
+    let (tag, code) = match check {
+        Check::Ok => (" ok ", "32"),
+        Check::Warn => ("warn", "33"),
+        Check::Fail => ("FAIL", "31"),
+    };
+    if color {
+        writeln!(out, "[\x1b[{}m{}\x1b[0m] {}", code, tag, message)
+    } else {
+        writeln!(out, "[{}] {}", tag, message)
+    }
+}
+
+/*
+ * doctor - Diagnose the usual reasons a lookup can't find the database
+ * @global: Options shared by all subcommands, for the database path
+ * @out: Where the report is written
+ *
+ * Each check depends on the one before it, so the first failure ends the
+ * report. A stale database only warns, since lookups still work.
+ *
+ * Return: Result containing Failure if any check failed, Success otherwise
+ */
+fn doctor(global: &GlobalArgs, out: &mut dyn Write) -> Result<ExitStatus, OuiError> {
+    /* Escape codes only make sense on a terminal, and NO_COLOR opts out */
+    let color = io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+
+    let csv_path = match global.csv_path() {
+        Ok(path) => {
+            report(out, color, Check::Ok, &format!("Database path is {}", path))?;
+            path
+        }
+        Err(e) => {
+            report(out, color, Check::Fail, &e.to_string())?;
+            return Ok(ExitStatus::Failure);
+        }
+    };
+
+    if let Err(e) = fs::File::open(&csv_path) {
+        let hint = if e.kind() == io::ErrorKind::NotFound {
+            "Database file is missing; run \"oui update\" to download it".to_string()
+        } else {
+            format!("Database file can't be opened: {}", e)
+        };
+        report(out, color, Check::Fail, &hint)?;
+        return Ok(ExitStatus::Failure);
+    }
+    report(out, color, Check::Ok, "Database file is readable")?;
+
+    match OuiDatabase::open(&csv_path) {
+        Ok(db) => report(
+            out,
+            color,
+            Check::Ok,
+            &format!("Database has {} entries", db.len()),
+        )?,
+        Err(e) => {
+            report(out, color, Check::Fail, &e.to_string())?;
+            return Ok(ExitStatus::Failure);
+        }
+    }
+
+    let days = database_age(&csv_path)?.as_secs() / SECONDS_PER_DAY;
+    let stale_days = global.stale_days();
+    if stale_days > 0 && days >= stale_days {
+        let message = format!(
+            "Database is {} days old; run \"oui update\" for recent assignments",
+            days
+        );
+        report(out, color, Check::Warn, &message)?;
+    } else {
+        report(
+            out,
+            color,
+            Check::Ok,
+            &format!("Database is {} days old", days),
+        )?;
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+/*
+ * try_run - Parse the arguments and dispatch to the chosen subcommand
+ * @args: Full argument list, including the program name
+ * @out: Where results are written
+ * @err: Where errors and notes are written
+ *
+ * CLAP ERRORS:
+ * parse_args() returns an error instead of exiting, so usage mistakes
+ * get ExitStatus::Failure rather than clap's default status of 2, which
+ * here means a blocked vendor. --help and --version also arrive as
+ * "errors" but go to stdout and count as success.
+ *
+ * Return: Result containing the exit status, or an error that stops the run
+ */
+fn try_run(
+    args: Vec<OsString>,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<ExitStatus, OuiError> {
+    let cli = match Cli::parse_args(args) {
+        Ok(cli) => cli,
+        Err(e) if e.use_stderr() => {
+            write!(err, "{}", e.render())?;
+            return Ok(ExitStatus::Failure);
+        }
+        Err(e) => {
+            write!(out, "{}", e.render())?;
+            return Ok(ExitStatus::Success);
+        }
+    };
+
+    /* --quiet only silences results; errors still reach err */
+    let mut sink = io::sink();
+    let out: &mut dyn Write = if cli.global.quiet { &mut sink } else { out };
+
+    match &cli.command {
+        /* A bare "oui <MAC>" behaves exactly like "oui lookup <MAC>" */
+        None => lookup(&cli.lookup, &cli.global, out, err),
+        Some(Command::Lookup(args)) => lookup(args, &cli.global, out, err),
+        Some(Command::Search(args)) => search(args, &cli.global, out, err),
+        Some(Command::Update(args)) => update(args, &cli.global, err),
+        Some(Command::Doctor) => doctor(&cli.global, out),
+        Some(Command::Normalize(args)) => normalize(args, err),
+        Some(Command::Where) => {
+            writeln!(out, "{}", cli.global.csv_path()?)?;
+            Ok(ExitStatus::Success)
+        }
+    }
+}
+
+/*
+ * run_with_args - Run the whole program against an argument list
+ * @args: Full argument list, including the program name
+ * @out: Where results are written
+ * @err: Where errors and notes are written
+ *
+ * DYN WRITE:
+ * &mut dyn Write accepts anything that bytes can be written to: stdout
+ * and stderr in main, or a Vec<u8> when capturing output in a test. The
+ * program never prints directly, so every run can be checked end to end
+ * without spawning a process.
+ *
+ * Return: The exit status; errors are reported on err before returning
+ */
+fn run_with_args<I, T>(args: I, out: &mut dyn Write, err: &mut dyn Write) -> ExitStatus
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let args = args.into_iter().map(Into::into).collect();
+    match try_run(args, out, err) {
+        Ok(status) => status,
+        /* The reader has everything it asked for, as with "oui --file big | head" */
+        Err(e) if is_broken_pipe(&e) => ExitStatus::Success,
+        Err(e) => {
+            /* Every error goes through OuiError's Display impl */
+            let _ = writeln!(err, "Error: {}", e);
+            ExitStatus::Failure
+        }
+    }
+}
+
+/*
+ * is_broken_pipe - Whether an error came from writing to a closed pipe
+ * @e: The error that ended the run
+ *
+ * SIGPIPE:
+ * Rust programs ignore SIGPIPE, so writing to a pipe whose reader has
+ * exited returns a BrokenPipe error instead of killing the process. That
+ * isn't a failure of the lookup, so it ends the run quietly. The csv crate
+ * wraps I/O errors in its own type, so both forms are checked.
+ */
+fn is_broken_pipe(e: &OuiError) -> bool {
+    match e {
+        OuiError::Io(e) => e.kind() == io::ErrorKind::BrokenPipe,
+        OuiError::Csv(e) => {
+            matches!(e.kind(), csv::ErrorKind::Io(e) if e.kind() == io::ErrorKind::BrokenPipe)
+        }
+        _ => false,
+    }
+}
+
+/*
+ * main - Program entry point
+ *
+ * FLUSHING:
+ * process::exit ends the program immediately without running destructors,
+ * so anything still buffered in stdout has to be flushed by hand first.
+ */
+fn main() {
+    let mut out = io::stdout().lock();
+    let status = run_with_args(std::env::args_os(), &mut out, &mut io::stderr());
+    let _ = out.flush();
+    process::exit(status.code());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASIC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/basic.csv");
+
+    /* Run with captured output; the stale warning is off since a checkout's mtime varies */
+    fn run(args: &[&str]) -> (ExitStatus, String, String) {
+        run_as_given(&[&["--warn-stale-days", "0"][..], args].concat())
+    }
+
+    /* run() without turning the stale warning off */
+    fn run_as_given(args: &[&str]) -> (ExitStatus, String, String) {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let status = run_with_args(["oui"].iter().chain(args), &mut out, &mut err);
+        (
+            status,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn single_hit() {
+        let (status, out, err) = run(&["-p", BASIC, "00:00:0c:12:34:56"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(out, "Cisco Systems, Inc\n");
+        assert_eq!(err, "");
+    }
+
+    #[test]
+    fn miss_prints_a_hint() {
+        let (status, out, err) = run(&["-p", BASIC, "00:11:23:44:55:66"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(out, "No match.\n");
+        assert_eq!(
+            err,
+            "Hint: 00:11:23:44:55:66: closest known prefix: 00:11:22 (CIMSYS Inc)\n"
+        );
+    }
+
+    #[test]
+    fn invalid_address_fails() {
+        let (status, out, err) = run(&["-p", BASIC, "zz"]);
+        assert_eq!(status, ExitStatus::Failure);
+        assert_eq!(out, "");
+        assert_eq!(err, "Error: Invalid MAC Address.\n");
+    }
+
+    #[test]
+    fn batch_json() {
+        let (status, out, _) = run(&["-p", BASIC, "--json", "00:00:0c:12:34:56", "001122334455"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(
+            out,
+            concat!(
+                r#"[{"input":"00:00:0c:12:34:56","mac":"00:00:0C:12:34:56","oui":"00000C","found":true,"vendor":"Cisco Systems, Inc"},"#,
+                r#"{"input":"001122334455","mac":"00:11:22:33:44:55","oui":"001122","found":true,"vendor":"CIMSYS Inc"}]"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn where_prints_the_path() {
+        let (status, out, _) = run(&["-p", BASIC, "where"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(out, format!("{}\n", BASIC));
+    }
+
+    #[test]
+    fn empty_database_fails_instead_of_missing() {
+        let empty = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/empty.csv");
+        let (status, out, err) = run(&["-p", empty, "00:00:0c:12:34:56"]);
+        assert_eq!(status, ExitStatus::Failure);
+        assert_eq!(out, "");
+        assert_eq!(
+            err,
+            format!("Error: OUI database {} contains no usable rows.\n", empty)
+        );
+    }
+
+    #[test]
+    fn yaml_batch_matches_golden_file() {
+        let (status, out, _) = run(&[
+            "-p",
+            BASIC,
+            "--output",
+            "yaml",
+            "00:00:0c:12:34:56",
+            "00:11:23:44:55:66",
+            "zz",
+        ]);
+        assert_eq!(status, ExitStatus::Failure);
+        assert_eq!(out, include_str!("../tests/fixtures/batch.yaml"));
+    }
+
+    #[test]
+    fn verbose_json_adds_the_analysis() {
+        let (status, out, err) = run(&["-p", BASIC, "-v", "--json", "00:00:0c:12:34:56"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(
+            out,
+            concat!(
+                r#"{"input":"00:00:0c:12:34:56","mac":"00:00:0C:12:34:56","oui":"00000C","found":true,"#,
+                r#""vendor":"Cisco Systems, Inc","multicast":false,"locally_administered":false,"#,
+                r#""eui64":"0200:0cff:fe12:3456","registry":"MA-L","block_size":16777216}"#,
+                "\n"
+            )
+        );
+        assert!(err.starts_with("Lookup took "));
+
+        /* A miss has no registry or block to report */
+        let (_, out, _) = run(&["-p", BASIC, "-v", "--json", "03:11:23:44:55:66"]);
+        let record: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(record["multicast"], true);
+        assert_eq!(record["locally_administered"], true);
+        assert!(record.get("registry").is_none());
+        assert!(record.get("block_size").is_none());
+
+        let (_, out, _) = run(&["-p", BASIC, "--json", "00:00:0c:12:34:56"]);
+        let record: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert!(record.get("multicast").is_none());
+    }
+
+    #[test]
+    fn getmac_file_input() {
+        let sample = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/getmac_table.txt"
+        );
+        let (status, out, _) = run(&[
+            "-p",
+            BASIC,
+            "--input-format",
+            "getmac",
+            "--file",
+            sample,
+            "--field",
+            "input",
+        ]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(
+            out,
+            "00-15-5D-01-02-03\nA4-BB-6D-11-22-33\n0A-00-27-00-00-0C\n"
+        );
+    }
+
+    /*
+     * A validator for the parts of JSON Schema that lookup_record_schema
+     * uses, enough to catch a record field the schema doesn't describe or
+     * describes wrongly
+     */
+    fn validate(
+        schema: &serde_json::Value,
+        value: &serde_json::Value,
+        at: &str,
+    ) -> Result<(), String> {
+        use serde_json::Value;
+
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                other => other.as_str().into_iter().collect(),
+            };
+            let matches = |kind: &str| match kind {
+                "string" => value.is_string(),
+                "boolean" => value.is_boolean(),
+                "integer" => value.is_u64() || value.is_i64(),
+                "object" => value.is_object(),
+                "null" => value.is_null(),
+                _ => false,
+            };
+            if !types.iter().any(|kind| matches(kind)) {
+                return Err(format!("{}: {} is not {:?}", at, value, types));
+            }
+        }
+        if let Some(Value::Array(allowed)) = schema.get("enum")
+            && !allowed.contains(value)
+        {
+            return Err(format!("{}: {} is not one of {:?}", at, value, allowed));
+        }
+        if let Some(constant) = schema.get("const")
+            && constant != value
+        {
+            return Err(format!("{}: {} is not {}", at, value, constant));
+        }
+        if let Some(minimum) = schema.get("minimum").and_then(Value::as_u64)
+            && value.as_u64().is_some_and(|n| n < minimum)
+        {
+            return Err(format!("{}: {} is below {}", at, value, minimum));
+        }
+        if let Some(maximum) = schema.get("maximum").and_then(Value::as_u64)
+            && value.as_u64().is_some_and(|n| n > maximum)
+        {
+            return Err(format!("{}: {} is above {}", at, value, maximum));
+        }
+
+        let Some(object) = value.as_object() else {
+            return Ok(());
+        };
+        for name in schema["required"].as_array().into_iter().flatten() {
+            let name = name.as_str().unwrap();
+            if !object.contains_key(name) {
+                return Err(format!("{}: missing {}", at, name));
+            }
+        }
+        for (name, item) in object {
+            let path = format!("{}.{}", at, name);
+            match (
+                schema["properties"].get(name),
+                schema.get("additionalProperties"),
+            ) {
+                (Some(property), _) => validate(property, item, &path)?,
+                (None, Some(Value::Bool(false))) => {
+                    return Err(format!("{}: not in the schema", path));
+                }
+                (None, Some(extra)) => validate(extra, item, &path)?,
+                (None, None) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /* Every record of a JSON batch run, checked against the schema */
+    fn assert_records_validate(args: &[&str]) -> usize {
+        let schema = lookup_record_schema();
+        let (_, out, _) = run(args);
+        let records: Vec<serde_json::Value> = serde_json::from_str(&out).unwrap();
+        for (i, record) in records.iter().enumerate() {
+            if let Err(e) = validate(&schema, record, &format!("record {}", i)) {
+                panic!("{} in {}", e, record);
+            }
+        }
+        records.len()
+    }
+
+    #[test]
+    fn records_validate_against_the_schema() {
+        let macs = [
+            "00:00:0c:12:34:56",
+            "00:11:23:44:55:66",
+            "zz",
+            "ff:ff:ff:ff:ff:ff",
+            "02:00:00:00:00:01",
+        ];
+        let plain = [&["-p", BASIC, "--json"][..], &macs].concat();
+        assert_eq!(assert_records_validate(&plain), macs.len());
+
+        let verbose = [
+            &["-p", BASIC, "--json", "-v", "--classifier", "cast-type"][..],
+            &macs,
+        ]
+        .concat();
+        assert_records_validate(&verbose);
+
+        let metadata = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/metadata.csv");
+        let policy = [
+            &[
+                "-p",
+                BASIC,
+                "--json",
+                "--metadata",
+                metadata,
+                "--allow-vendor",
+                "Cisco",
+            ][..],
+            &macs,
+        ]
+        .concat();
+        assert_records_validate(&policy);
+
+        let second = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/diff_new.csv");
+        let merged = [
+            &["-p", BASIC, "-p", second, "--json", "AA:BB:CC:00:00:01"][..],
+            &macs,
+        ]
+        .concat();
+        assert_records_validate(&merged);
+    }
+
+    /* The validator itself has to reject what the schema rules out */
+    #[test]
+    fn schema_rejects_unknown_fields() {
+        let schema = lookup_record_schema();
+        let record = serde_json::json!({
+            "input": "x", "mac": null, "oui": null, "found": false, "vendor": null
+        });
+        assert!(validate(&schema, &record, "record").is_ok());
+
+        let mut extra = record.clone();
+        extra["colour"] = "blue".into();
+        assert!(validate(&schema, &extra, "record").is_err());
+
+        let mut wrong = record.clone();
+        wrong["found"] = "yes".into();
+        assert!(validate(&schema, &wrong, "record").is_err());
+
+        let mut missing = record;
+        missing.as_object_mut().unwrap().remove("vendor");
+        assert!(validate(&schema, &missing, "record").is_err());
+    }
+
+    /* The inputs of a sorted batch, in the order they were printed */
+    fn sorted_inputs(order: &str) -> Vec<String> {
+        let (_, out, _) = run(&[
+            "-p",
+            BASIC,
+            "--field",
+            "input",
+            "--sort-output",
+            order,
+            "00:11:22:00:00:01",
+            "zz",
+            "00:00:0c:00:00:01",
+            "00:11:23:00:00:01",
+            "00:00:00:00:00:01",
+            "00:00:c1:00:00:01",
+        ]);
+        out.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn sort_by_input() {
+        assert_eq!(
+            sorted_inputs("input"),
+            [
+                "00:11:22:00:00:01",
+                "00:00:0c:00:00:01",
+                "00:11:23:00:00:01",
+                "00:00:00:00:00:01",
+                "00:00:c1:00:00:01"
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_by_oui() {
+        assert_eq!(
+            sorted_inputs("oui"),
+            [
+                "00:00:00:00:00:01",
+                "00:00:0c:00:00:01",
+                "00:00:c1:00:00:01",
+                "00:11:22:00:00:01",
+                "00:11:23:00:00:01"
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_by_vendor_puts_misses_last() {
+        assert_eq!(
+            sorted_inputs("vendor"),
+            [
+                "00:11:22:00:00:01",
+                "00:00:0c:00:00:01",
+                "00:00:c1:00:00:01",
+                "00:00:00:00:00:01",
+                "00:11:23:00:00:01"
+            ]
+        );
+    }
+
+    #[test]
+    fn cid_hits_are_annotated() {
+        let cid = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/cid.csv");
+        let (status, out, _) = run(&["-p", cid, "0a:1b:2c:00:00:01", "00:00:0c:00:00:01"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(
+            out,
+            "Example CID Holder [CID, not a globally unique MAC prefix]\nCisco Systems, Inc\n"
+        );
+
+        let (_, out, _) = run(&["-p", cid, "-v", "--json", "0a:1b:2c:00:00:01"]);
+        let record: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(record["registry"], "CID");
+    }
+
+    /* Lookup options parsed from a command line */
+    fn lookup_args(args: &[&str]) -> LookupArgs {
+        let argv = ["oui"].iter().chain(args).map(OsString::from).collect();
+        Cli::parse_args(argv).unwrap().lookup
+    }
+
+    #[test]
+    fn stripped_suffixes_collapse_into_one_group() {
+        let args = lookup_args(&["--strip-vendor-suffix", "--vendor-suffix", "Holdings", "x"]);
+        let annotator = Annotator::new(&args).unwrap();
+        let mut entries: Vec<Entry> = [
+            "Acme Networks, Inc.",
+            "Acme Networks GmbH",
+            "Acme Networks Co., Ltd.",
+            "Acme Networks Holdings",
+            "Globex",
+        ]
+        .into_iter()
+        .map(|vendor| Entry::new("x", None, Ok(LookupResult::Found(vendor.to_string()))))
+        .collect();
+        for entry in &mut entries {
+            annotator.annotate(entry);
+        }
+
+        assert_eq!(
+            vendor_counts(&entries),
+            [("Acme Networks".to_string(), 4), ("Globex".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn short_flags() {
+        let macs = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/macs.txt");
+        let long = run(&["--path", BASIC, "--json", "--file", macs]);
+        let short = run(&["-p", BASIC, "-j", "-f", macs]);
+        assert_eq!(short, long);
+        assert!(short.1.starts_with(r#"[{"input":"00:00:0c:12:34:56""#));
+
+        let (_, out, _) = run(&["-p", BASIC, "-v", "00:00:0c:12:34:56"]);
+        assert_eq!(
+            out,
+            "00:00:0C:12:34:56 (00:00:0C): Cisco Systems, Inc [MA-L, 2^24 addresses]\n"
+        );
+
+        let (status, out, err) = run(&["-p", BASIC, "-q", "00:00:0c:12:34:56"]);
+        assert_eq!(
+            (status, out.as_str(), err.as_str()),
+            (ExitStatus::Success, "", "")
+        );
+        let (status, _, err) = run(&["-p", BASIC, "-q", "zz"]);
+        assert_eq!(status, ExitStatus::Failure);
+        assert_eq!(err, "Error: Invalid MAC Address.\n");
+
+        let (status, _, err) = run(&["-p", BASIC, "-q", "-v", "00:00:0c:12:34:56"]);
+        assert_eq!(status, ExitStatus::Failure);
+        assert!(err.starts_with("error: the argument '--quiet' cannot be used with '--verbose'"));
+    }
+
+    #[test]
+    fn help_lists_the_short_flags() {
+        let (status, out, _) = run(&["--help"]);
+        assert_eq!(status, ExitStatus::Success);
+        for flag in [
+            "-f, --file",
+            "-j, --json",
+            "-p, --path",
+            "-v, --verbose",
+            "-q, --quiet",
+        ] {
+            assert!(out.contains(flag), "{}", flag);
+        }
+    }
+
+    #[test]
+    fn special_addresses_in_json() {
+        let cases = [
+            (
+                "ff:ff:ff:ff:ff:ff",
+                r#"{"input":"ff:ff:ff:ff:ff:ff","mac":"FF:FF:FF:FF:FF:FF","oui":"FFFFFF","found":false,"vendor":null,"classification":"broadcast"}"#,
+            ),
+            (
+                "00:00:00:00:00:00",
+                r#"{"input":"00:00:00:00:00:00","mac":"00:00:00:00:00:00","oui":"000000","found":true,"vendor":"XEROX CORPORATION","classification":"null"}"#,
+            ),
+            (
+                "01:00:5e:00:00:01",
+                r#"{"input":"01:00:5e:00:00:01","mac":"01:00:5E:00:00:01","oui":"01005E","found":false,"vendor":null,"classification":"multicast"}"#,
+            ),
+            (
+                "02:00:0c:00:00:01",
+                r#"{"input":"02:00:0c:00:00:01","mac":"02:00:0C:00:00:01","oui":"02000C","found":false,"vendor":null,"classification":"locally-administered"}"#,
+            ),
+            (
+                "00:00:0c:00:00:01",
+                r#"{"input":"00:00:0c:00:00:01","mac":"00:00:0C:00:00:01","oui":"00000C","found":true,"vendor":"Cisco Systems, Inc"}"#,
+            ),
+        ];
+        for (mac, expected) in cases {
+            let (_, out, _) = run(&["-p", BASIC, "--json", mac]);
+            assert_eq!(out, format!("{}\n", expected));
+        }
+    }
+
+    #[test]
+    fn old_database_warns() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("old.csv");
+        fs::copy(BASIC, &path).unwrap();
+        let old = SystemTime::now() - Duration::from_secs(200 * SECONDS_PER_DAY);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let path = path.to_str().unwrap();
+        let warning =
+            "Warning: the database is 200 days old; run \"oui update\" for recent assignments.\n";
+
+        /* Assumes OUI_WARN_STALE_DAYS isn't set, so the default of 90 applies */
+        let (status, out, err) = run_as_given(&["-p", path, "00:00:0c:12:34:56"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(out, "Cisco Systems, Inc\n");
+        assert_eq!(err, warning);
+
+        let (_, _, err) =
+            run_as_given(&["-p", path, "--warn-stale-days", "150", "00:00:0c:12:34:56"]);
+        assert_eq!(err, warning);
+        let (_, _, err) =
+            run_as_given(&["-p", path, "--warn-stale-days", "365", "00:00:0c:12:34:56"]);
+        assert_eq!(err, "");
+        let (_, _, err) =
+            run_as_given(&["-p", path, "--warn-stale-days", "0", "00:00:0c:12:34:56"]);
+        assert_eq!(err, "");
+    }
+
+    #[test]
+    fn max_scan_rows_reports_the_limit() {
+        let (status, out, _) = run(&["-p", BASIC, "--max-scan-rows", "1", "00:00:c1:11:22:33"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(out, "Not found within scan limit (1 row).\n");
+
+        let (_, out, _) = run(&["-p", BASIC, "--max-scan-rows", "4", "00:00:c1:11:22:33"]);
+        assert_eq!(out, "Madge Ltd.\n");
+    }
+
+    #[test]
+    fn allow_malformed_length_warns() {
+        let (status, _, err) = run(&["-p", BASIC, "0:00:0c:12:34:56"]);
+        assert_eq!(status, ExitStatus::Failure);
+        assert_eq!(err, "Error: Invalid MAC Address.\n");
+
+        let (status, out, err) =
+            run(&["-p", BASIC, "--allow-malformed-length", "0:00:0c:12:34:56"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(out, "Cisco Systems, Inc\n");
+        assert_eq!(
+            err,
+            "Warning: 0:00:0c:12:34:56: one digit short, read as 00:00:0C:12:34:56.\n"
+        );
+    }
+
+    #[test]
+    fn trim_to_oui_sorts_and_deduplicates() {
+        /* A database that doesn't exist shows none is looked for */
+        let args = ["-p", "/nonexistent/oui.csv", "--trim-to-oui"];
+        let macs = [
+            "00-11-22-AA-BB-CC",
+            "00:00:0c:12:34:56",
+            "001122334455",
+            "0000.0cff.0001",
+        ];
+        let (status, out, err) = run(&[&args[..], &macs].concat());
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(out, "00:00:0C\n00:11:22\n");
+        assert_eq!(err, "");
+
+        let (_, out, _) = run(&[&args[..], &["--json"], &macs].concat());
+        assert_eq!(out, "[\"00:00:0C\",\"00:11:22\"]\n");
+
+        let (status, out, err) = run(&[&args[..], &["zz", "001122334455"]].concat());
+        assert_eq!(status, ExitStatus::Failure);
+        assert_eq!(out, "00:11:22\n");
+        assert_eq!(err, "Error: zz: Invalid MAC Address.\n");
+    }
+
+    #[test]
+    fn vendorless_row_warns_when_verbose() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/missing_vendor.csv"
+        );
+        let (status, out, err) = run(&["-p", path, "00:00:0c:12:34:56"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(out, "Unknown vendor.\n");
+        assert_eq!(err, "");
+
+        let (_, _, err) = run(&["-p", path, "-v", "00:00:0c:12:34:56"]);
+        let warning = err
+            .lines()
+            .find(|line| line.starts_with("Warning:"))
+            .unwrap();
+        assert!(warning.starts_with("Warning: 00:00:0c:12:34:56: OUI 00:00:0C at line 2 of "));
+        assert!(warning.ends_with("missing_vendor.csv has no vendor column."));
+
+        /* An empty vendor after the delimiter is a real entry, not a lost column */
+        let (_, _, err) = run(&["-p", path, "-v", "00:11:22:33:44:55"]);
+        assert!(!err.contains("Warning:"), "{}", err);
+    }
+
+    #[test]
+    fn normalize_columns_auto() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("oui.csv");
+        let out = out.to_str().unwrap();
+        let ieee = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/ieee_sample.csv"
+        );
+
+        let (status, _, err) = run(&["normalize", "--in", ieee, "--out", out, "--columns", "auto"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(err, format!("Wrote 4 entries to {}\n", out));
+        assert!(
+            fs::read_to_string(out)
+                .unwrap()
+                .starts_with("00000C;Cisco Systems, Inc\n")
+        );
+
+        /* A file without the header names keeps the positional columns, with a warning */
+        let (status, _, err) = run(&[
+            "normalize",
+            "--in",
+            BASIC,
+            "--out",
+            out,
+            "--columns",
+            "auto",
+        ]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(
+            err,
+            format!(
+                "Warning: no OUI and vendor column names found in the header of {}; using columns 1 and 2.\nWrote 4 entries to {}\n",
+                BASIC, out
+            )
+        );
+    }
+
+    #[test]
+    fn env_output_for_one_address() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/quote_vendor.csv"
+        );
+        let (status, out, _) = run(&["-p", path, "--output", "env", "aa:bb:cc:00:00:01"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(
+            out,
+            "OUI_VENDOR='O'\\''Brien \"Labs\" Ltd'\nOUI_PREFIX=AA:BB:CC\n"
+        );
+
+        let (status, out, err) = run(&[
+            "-p",
+            path,
+            "--output",
+            "env",
+            "aa:bb:cc:00:00:01",
+            "00000c000001",
+        ]);
+        assert_eq!(status, ExitStatus::Failure);
+        assert_eq!(out, "");
+        assert_eq!(
+            err,
+            "Error: --output env only supports looking up a single address.\n"
+        );
+    }
+
+    #[test]
+    fn single_lookup_matches_the_longest_prefix() {
+        let nested = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/nested_blocks.csv"
+        );
+        let mac = "AA:BB:CC:D0:00:01";
+        let (status, out, _) = run(&["-p", nested, mac]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(out, "Medium Vendor\n");
+
+        /* Scanned, indexed and batched, the address gets the same answer */
+        let expected = r#""vendor":"Medium Vendor","#;
+        let analysis = r#""registry":"MA-M","block_size":1048576}"#;
+        let dir = tempfile::tempdir().unwrap();
+        let macs = dir.path().join("macs.txt");
+        fs::write(&macs, format!("{}\n", mac)).unwrap();
+        let macs = macs.to_str().unwrap();
+        for args in [
+            &["-p", nested, "-v", "--json", mac][..],
+            &["-p", nested, "-v", "--json", "--mmap", mac],
+            &["-p", nested, "-v", "--json", "--low-memory", "--file", macs],
+            &["-p", nested, "-v", "--json", "--file", macs],
+        ] {
+            let (_, out, _) = run(args);
+            assert!(
+                out.contains(expected) && out.contains(analysis),
+                "{:?}: {}",
+                args,
+                out
+            );
+        }
+    }
+
+    #[test]
+    fn show_source_names_each_database() {
+        let nested = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/nested_blocks.csv"
+        );
+        let label = |path| {
+            fs::canonicalize(path)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+        let args = ["-p", BASIC, "-p", nested, "--show-source"];
+        let macs = [
+            "00:00:0c:12:34:56",
+            "11:22:33:a0:00:01",
+            "00:11:23:00:00:01",
+        ];
+
+        let (status, out, _) = run(&[&args[..], &macs].concat());
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(
+            out,
+            format!(
+                "Cisco Systems, Inc (from {})\nOnly Medium Ltd (from {})\nNo match.\n",
+                label(BASIC),
+                label(nested)
+            )
+        );
+
+        let (_, out, _) = run(&[&args[..], &["--json"], &macs].concat());
+        let records: Vec<serde_json::Value> = serde_json::from_str(&out).unwrap();
+        let sources: Vec<_> = records
+            .iter()
+            .map(|record| record.get("source").cloned())
+            .collect();
+        assert_eq!(
+            sources,
+            [Some(label(BASIC).into()), Some(label(nested).into()), None]
+        );
+
+        /* A later database that doesn't exist is named like the first */
+        let (status, _, err) = run(&["-p", BASIC, "-p", "/nonexistent/oui.csv", macs[0]]);
+        assert_eq!(status, ExitStatus::Failure);
+        assert_eq!(
+            err,
+            "Error: No OUI database found (tried /nonexistent/oui.csv); run \"oui update\" to download one.\n"
+        );
+    }
+
+    #[test]
+    fn echo_input_prefixes_batch_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let macs = dir.path().join("macs.txt");
+        fs::write(&macs, "00-00-0C-12-34-56\nzz\naa:bb:cc:00:00:01\n").unwrap();
+        let macs = macs.to_str().unwrap();
+
+        let (status, out, err) = run(&["-p", BASIC, "--echo-input", "--file", macs]);
+        assert_eq!(status, ExitStatus::Failure);
+        assert_eq!(
+            out,
+            "00-00-0C-12-34-56\tCisco Systems, Inc\naa:bb:cc:00:00:01\tNo match.\n"
+        );
+        assert_eq!(err, "Error: zz: Invalid MAC Address.\n");
+
+        /* With --field the raw input comes first, then the tab-separated fields */
+        let (_, out, _) = run(&[
+            "-p",
+            BASIC,
+            "--echo-input",
+            "--field",
+            "oui,vendor",
+            "--file",
+            macs,
+        ]);
+        assert_eq!(
+            out,
+            "00-00-0C-12-34-56\t00000C\tCisco Systems, Inc\naa:bb:cc:00:00:01\tAABBCC\t\n"
+        );
+
+        let (status, _, err) = run(&[
+            "-p",
+            BASIC,
+            "--echo-input",
+            "--output",
+            "csv",
+            "--file",
+            macs,
+        ]);
+        assert_eq!(status, ExitStatus::Failure);
+        assert_eq!(err, "Error: --echo-input only applies to plain output.\n");
+    }
+
+    /* A pipe whose reader goes away after taking the first few bytes */
+    struct ShortReader {
+        room: usize,
+        taken: Vec<u8>,
+    }
+
+    impl Write for ShortReader {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.room == 0 {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+            let n = buf.len().min(self.room);
+            self.room -= n;
+            self.taken.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn closed_pipe_ends_the_run_quietly() {
+        /* Enough rows to fill the csv crate's buffer, so its own writes hit the pipe */
+        let dir = tempfile::tempdir().unwrap();
+        let macs = dir.path().join("macs.txt");
+        fs::write(&macs, "00:00:0c:12:34:56\n".repeat(1000)).unwrap();
+        let macs = macs.to_str().unwrap();
+
+        for format in ["plain", "csv"] {
+            let args = [
+                "oui",
+                "--warn-stale-days",
+                "0",
+                "-p",
+                BASIC,
+                "--output",
+                format,
+                "--file",
+                macs,
+            ];
+            let short = || ShortReader {
+                room: 10,
+                taken: Vec::new(),
+            };
+
+            let mut err = Vec::new();
+            let error = try_run(
+                args.iter().map(OsString::from).collect(),
+                &mut short(),
+                &mut err,
+            );
+            match (format, error) {
+                ("plain", Err(OuiError::Io(_))) | ("csv", Err(OuiError::Csv(_))) => {}
+                (_, other) => panic!("{}: {:?}", format, other.map(|status| status.code())),
+            }
+
+            let mut out = short();
+            let mut err = Vec::new();
+            assert_eq!(
+                run_with_args(args, &mut out, &mut err),
+                ExitStatus::Success,
+                "{}",
+                format
+            );
+            assert_eq!(out.taken.len(), 10, "{}", format);
+            assert_eq!(String::from_utf8(err).unwrap(), "", "{}", format);
+        }
+    }
+
+    #[test]
+    fn broken_pipe_in_either_error_form() {
+        let pipe = || io::Error::from(io::ErrorKind::BrokenPipe);
+        assert!(is_broken_pipe(&OuiError::Io(pipe())));
+        assert!(is_broken_pipe(&OuiError::Csv(csv::Error::from(pipe()))));
+        assert!(!is_broken_pipe(&OuiError::Io(
+            io::ErrorKind::PermissionDenied.into()
+        )));
+        assert!(!is_broken_pipe(&OuiError::InvalidMac));
+    }
+
+    #[test]
+    fn reverse_reads_a_bluetooth_address() {
+        let (status, out, _) = run(&["-p", BASIC, "--reverse", "56:34:12:0c:00:00"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(out, "Cisco Systems, Inc\n");
+    }
+
+    #[test]
+    fn wildcard_lists_the_range() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/wildcards.csv");
+        let (status, out, _) = run(&["-p", path, "AA-BB-*"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(
+            out,
+            "AABBCC\tOne Ltd\nAABBDD\tTwo Ltd\nAABBCCD\tMedium Ltd\n"
+        );
+
+        let (status, out, err) = run(&["-p", path, "AA:*:CC"]);
+        assert_eq!(status, ExitStatus::Failure);
+        assert_eq!(out, "");
+        assert_eq!(
+            err,
+            "Error: Invalid MAC Address: wildcards may only appear at the end.\n"
+        );
     }
 }
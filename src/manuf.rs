@@ -0,0 +1,167 @@
+/*!
+ * manuf.rs
+ *
+ * Reading Wireshark's "manuf" vendor database.
+ *
+ * FILE FORMAT:
+ * Each line is a prefix, a short vendor name and, usually, the full name,
+ * separated by tabs, e.g. "00:00:0C<TAB>Cisco<TAB>Cisco Systems, Inc".
+ * Lines starting with '#' are comments. Prefixes longer than 24 bits carry
+ * a mask length, as in "00:1B:C5:00:00:00/36" for an MA-S block.
+ */
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+
+use crate::error::OuiError;
+use crate::mac::{OUI_LENGTH, SEPARATORS};
+use crate::paths::ensure_not_directory;
+
+const BITS_PER_DIGIT: usize = 4; /* One hex digit is four bits of prefix */
+
+/**
+ * VendorName - Which of the two manuf names to report
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VendorName {
+    /* Abbreviated name, e.g. "Cisco" */
+    Short,
+    /* Full registered name, e.g. "Cisco Systems, Inc" */
+    #[default]
+    Long,
+}
+
+/**
+ * ManufEntry - One prefix from a manuf file
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManufEntry {
+    pub prefix: String, /* Uppercase hex digits covered by the mask, e.g. "00000C" */
+    pub short: String,  /* Short name, possibly empty */
+    pub long: Option<String>, /* Full name, when the line has one */
+}
+
+impl ManufEntry {
+    /**
+     * name - The requested vendor name, falling back to the other one
+     * @which: Short or long
+     *
+     * Return: The requested name, or the other one when it is missing
+     */
+    pub fn name(&self, which: VendorName) -> &str {
+        let long = self.long.as_deref().filter(|l| !l.is_empty());
+        match which {
+            VendorName::Short if !self.short.is_empty() => &self.short,
+            VendorName::Short => long.unwrap_or_default(),
+            VendorName::Long => long.unwrap_or(&self.short),
+        }
+    }
+}
+
+/**
+ * is_manuf - Guess whether a database file is in manuf format
+ * @path: Path to the database file
+ *
+ * The IEEE database separates its two columns with ';', while manuf uses
+ * tabs and starts with a comment block, so the first line that isn't a
+ * comment is enough to tell them apart.
+ *
+ * Return: Result containing true for manuf, or an error if the file can't be read
+ */
+pub fn is_manuf(path: &str) -> Result<bool, OuiError> {
+    ensure_not_directory(path)?;
+    /*
+     * Only the top of the file is needed, so read line by line. Bytes
+     * that aren't UTF-8 are left for the real parser to report with
+     * their line number.
+     */
+    for line in BufReader::new(File::open(path)?).split(b'\n') {
+        let line = String::from_utf8_lossy(&line?).into_owned();
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        return Ok(line.contains('\t') && !line.contains(';'));
+    }
+    Ok(false)
+}
+
+/**
+ * load_manuf - Read every prefix from a manuf file
+ * @path: Path to the manuf file
+ *
+ * The mask length decides how many hex digits of the prefix are kept: 24
+ * bits (the default) keeps 6, 28 keeps 7 and 36 keeps 9, matching how the
+ * IEEE writes MA-L, MA-M and MA-S assignments. Malformed lines are skipped.
+ *
+ * Return: Result containing the entries in file order, or an error if the file can't be read
+ */
+pub fn load_manuf(path: &str) -> Result<Vec<ManufEntry>, OuiError> {
+    ensure_not_directory(path)?;
+    let contents = fs::read_to_string(path)?;
+    let mut entries = Vec::new();
+
+    for line in contents.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split('\t');
+        let (Some(address), Some(short)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+
+        let (address, bits) = match address.split_once('/') {
+            Some((address, mask)) => match mask.trim().parse::<usize>() {
+                Ok(bits) => (address, bits),
+                Err(_) => continue,
+            },
+            None => (address, OUI_LENGTH * BITS_PER_DIGIT),
+        };
+        let digits: String = address
+            .trim()
+            .chars()
+            .filter(|c| !SEPARATORS.contains(c))
+            .collect::<String>()
+            .to_ascii_uppercase();
+        let Some(prefix) = digits.get(..bits.div_ceil(BITS_PER_DIGIT)) else {
+            continue;
+        };
+        if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            continue;
+        }
+
+        entries.push(ManufEntry {
+            prefix: prefix.to_string(),
+            short: short.trim().to_string(),
+            long: fields
+                .next()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(String::from),
+        });
+    }
+
+    Ok(entries)
+}
+
+/**
+ * load_manuf_map - Read a manuf file into a prefix to vendor HashMap
+ * @path: Path to the manuf file
+ * @which: Which name to keep for each prefix
+ *
+ * Return: Result containing the map, or an error if the file can't be read or has no entries
+ */
+pub fn load_manuf_map(path: &str, which: VendorName) -> Result<HashMap<String, String>, OuiError> {
+    let map: HashMap<String, String> = load_manuf(path)?
+        .into_iter()
+        .map(|entry| {
+            let name = entry.name(which).to_string();
+            (entry.prefix, name)
+        })
+        .collect();
+    if map.is_empty() {
+        return Err(OuiError::EmptyDatabase(path.to_string()));
+    }
+    Ok(map)
+}
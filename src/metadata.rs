@@ -0,0 +1,128 @@
+/*!
+ * metadata.rs
+ *
+ * Optional per-vendor details stored alongside the OUI database.
+ *
+ * FILE FORMAT:
+ * The companion file has the same shape as the database: no header, fields
+ * separated by semicolons, one vendor per line in the order
+ * Vendor;Country;Founded, for example "Hewlett Packard;US;1939".
+ *
+ * The vendor name must match the name the database uses, ignoring case.
+ * Country and Founded may be left empty, and trailing fields may be left
+ * off entirely.
+ */
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::error::OuiError;
+use crate::lookup::{database_records, open_database};
+
+/**
+ * VendorMetadata - Extra details about one manufacturer
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VendorMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>, /* Where the vendor is based, e.g. "US" */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub founded: Option<u16>, /* Year the vendor was founded */
+}
+
+/* Display gives the short form used in plain output, e.g. "US, founded 1939" */
+impl fmt::Display for VendorMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.country, self.founded) {
+            (Some(country), Some(year)) => write!(f, "{}, founded {}", country, year),
+            (Some(country), None) => write!(f, "{}", country),
+            (None, Some(year)) => write!(f, "founded {}", year),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/**
+ * MetadataTable - A companion metadata file loaded into memory
+ *
+ * Keys are lowercased when loading so get() can match vendor names from
+ * the database regardless of how either file capitalizes them.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct MetadataTable {
+    entries: HashMap<String, VendorMetadata>,
+}
+
+impl MetadataTable {
+    /**
+     * open - Load a metadata file
+     * @path: Path to the semicolon-separated companion file
+     *
+     * Later lines for the same vendor replace earlier ones, like
+     * Conflict::Last does for the database.
+     *
+     * Return: Result containing the table, or an error if the file can't be read or a year isn't a number
+     */
+    pub fn open(path: &str) -> Result<Self, OuiError> {
+        let mut rdr = open_database(path)?;
+        let mut entries = HashMap::new();
+
+        for result in database_records(&mut rdr, path) {
+            let record = result?;
+            let Some(vendor) = record.get(0).map(str::trim).filter(|v| !v.is_empty()) else {
+                continue;
+            };
+
+            /* Empty fields mean "not known" rather than an empty value */
+            let field = |i| {
+                record
+                    .get(i)
+                    .map(str::trim)
+                    .filter(|v: &&str| !v.is_empty())
+            };
+            let founded = match field(2) {
+                Some(year) => Some(year.parse().map_err(|_| {
+                    let line = record.position().map_or(0, |p| p.line());
+                    OuiError::Metadata(format!("line {}: \"{}\" is not a year", line, year))
+                })?),
+                None => None,
+            };
+
+            entries.insert(
+                vendor.to_lowercase(),
+                VendorMetadata {
+                    country: field(1).map(String::from),
+                    founded,
+                },
+            );
+        }
+
+        Ok(MetadataTable { entries })
+    }
+
+    /**
+     * get - Find the metadata for a vendor
+     * @vendor: Manufacturer name as reported by a lookup
+     *
+     * Return: The vendor's metadata, or None if the file doesn't list it
+     */
+    pub fn get(&self, vendor: &str) -> Option<&VendorMetadata> {
+        self.entries.get(&vendor.trim().to_lowercase())
+    }
+
+    /**
+     * len - Number of vendors in the table
+     */
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /**
+     * is_empty - Whether the table lists no vendors at all
+     */
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
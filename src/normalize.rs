@@ -0,0 +1,335 @@
+/*!
+ * normalize.rs
+ *
+ * Converting third-party vendor databases into the format lookups read.
+ */
+
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::error::OuiError;
+use crate::lookup::{database_records, normalize_db_key};
+use crate::paths::ensure_not_directory;
+use crate::update::RemoveOnDrop;
+
+/**
+ * NormalizeOptions - How the source file of normalize_database is laid out
+ *
+ * Columns count from 0. The Default impl describes the canonical format
+ * itself: no header, ';' between fields, the OUI first and the vendor
+ * second, so normalizing a database that is already canonical only
+ * cleans up its keys.
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct NormalizeOptions {
+    pub delimiter: u8,        /* Byte between fields, e.g. b',' */
+    pub oui_column: usize,    /* Column holding the prefix */
+    pub vendor_column: usize, /* Column holding the vendor name */
+    pub has_header: bool,     /* Whether the first row is column names */
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        NormalizeOptions {
+            delimiter: b';',
+            oui_column: 0,
+            vendor_column: 1,
+            has_header: false,
+        }
+    }
+}
+
+/* Header names, lowercased, that mark the OUI column */
+const OUI_HEADERS: &[&str] = &[
+    "assignment",
+    "oui",
+    "prefix",
+    "mac prefix",
+    "mac address prefix",
+    "registry assignment",
+];
+
+/* Header names, lowercased, that mark the vendor column */
+const VENDOR_HEADERS: &[&str] = &[
+    "organization name",
+    "organization",
+    "vendor",
+    "vendor name",
+    "manufacturer",
+    "company",
+    "company name",
+];
+
+/* Delimiters guessed between when detect_columns isn't given one */
+const CANDIDATE_DELIMITERS: [u8; 3] = [b',', b';', b'\t'];
+
+/**
+ * detect_columns - Find the OUI and vendor columns of a file by their header names
+ * @source: The database to inspect
+ * @delimiter: Field separator, or None to guess it from the header line
+ *
+ * HEADER NAMES:
+ * The first row is compared, trimmed and ignoring case, against the names
+ * exports commonly use: Assignment, OUI or Prefix for the key, and
+ * Organization Name, Vendor, Manufacturer or Company for the vendor. The
+ * IEEE's own oui.csv starts with "Registry,Assignment,Organization
+ * Name,Organization Address", for example. Without a delimiter, the one
+ * of ',', ';' and tab that the header line holds most of is used.
+ *
+ * On success the options mark the header row to be skipped. A file whose
+ * first row doesn't name both columns, including one with no header at
+ * all, gives None, so the caller can fall back to fixed columns.
+ *
+ * Return: Result containing options for normalize_database, or None if the columns weren't recognized
+ */
+pub fn detect_columns(
+    source: &Path,
+    delimiter: Option<u8>,
+) -> Result<Option<NormalizeOptions>, OuiError> {
+    ensure_not_directory(&source.to_string_lossy())?;
+    let mut header = Vec::new();
+    BufReader::new(File::open(source)?).read_until(b'\n', &mut header)?;
+
+    let delimiter = delimiter.unwrap_or_else(|| {
+        CANDIDATE_DELIMITERS
+            .into_iter()
+            .max_by_key(|&d| header.iter().filter(|&&b| b == d).count())
+            .unwrap_or(b';')
+    });
+    let names = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .from_reader(header.as_slice())
+        .byte_records()
+        .next()
+        .transpose()?;
+    let Some(names) = names else {
+        return Ok(None);
+    };
+
+    let find = |known: &[&str]| {
+        names.iter().position(|name| {
+            let name = String::from_utf8_lossy(name);
+            let name = name.trim_start_matches('\u{feff}').trim().to_lowercase();
+            known.contains(&name.as_str())
+        })
+    };
+    Ok(match (find(OUI_HEADERS), find(VENDOR_HEADERS)) {
+        (Some(oui_column), Some(vendor_column)) => Some(NormalizeOptions {
+            delimiter,
+            oui_column,
+            vendor_column,
+            has_header: true,
+        }),
+        _ => None,
+    })
+}
+
+/**
+ * normalize_database - Rewrite a vendor database in the canonical OUI;Vendor format
+ * @source: The database to convert
+ * @dest: Where to write the result
+ * @options: Layout of the source file
+ *
+ * CANONICAL FORMAT:
+ * Each output row is the prefix as uppercase hex digits without
+ * separators, a semicolon, and the vendor, quoted only if the name holds
+ * a semicolon or a quote. Keys are cleaned the way lookups clean them, so
+ * "aa-bb-cc" and "AABBCC000000" both become "AABBCC". Rows whose key
+ * isn't hex, or that lack either column, are skipped.
+ *
+ * The output is written under a temporary name and renamed into place,
+ * as download_database does, so dest may be the source file itself, and
+ * the temporary file is removed again if anything fails.
+ *
+ * Return: Result containing the number of rows written, or an error if a file can't be read or written or no row is usable
+ */
+pub fn normalize_database(
+    source: &Path,
+    dest: &Path,
+    options: &NormalizeOptions,
+) -> Result<usize, OuiError> {
+    let source_name = source.to_string_lossy();
+    ensure_not_directory(&source_name)?;
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .has_headers(options.has_header)
+        .flexible(true)
+        .from_path(source)?;
+
+    let partial = dest.with_extension("csv.part");
+    let _cleanup = RemoveOnDrop(&partial);
+    let mut wtr = csv::WriterBuilder::new()
+        .delimiter(b';')
+        .from_path(&partial)?;
+
+    let mut rows = 0;
+    for result in database_records(&mut rdr, &source_name) {
+        let record = result?;
+        let (Some(key), Some(vendor)) = (
+            record.get(options.oui_column),
+            record.get(options.vendor_column),
+        ) else {
+            continue;
+        };
+        let key = normalize_db_key(key);
+        let vendor = vendor.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_hexdigit()) || vendor.is_empty() {
+            continue;
+        }
+        wtr.write_record([key.as_ref(), vendor])?;
+        rows += 1;
+    }
+    wtr.flush()?;
+    drop(wtr);
+
+    if rows == 0 {
+        return Err(OuiError::EmptyDatabase(source_name.into_owned()));
+    }
+    fs::rename(&partial, dest)?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::OuiDatabase;
+    use crate::lookup::LookupResult;
+
+    const IEEE_SAMPLE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/ieee_sample.csv"
+    );
+
+    fn ieee_options() -> NormalizeOptions {
+        NormalizeOptions {
+            delimiter: b',',
+            oui_column: 1,
+            vendor_column: 2,
+            has_header: true,
+        }
+    }
+
+    #[test]
+    fn normalize_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.csv");
+        let second = dir.path().join("second.csv");
+
+        /* Dashed, lowercase and padded keys are cleaned; the mangled key and the vendorless row are dropped */
+        assert_eq!(
+            normalize_database(Path::new(IEEE_SAMPLE), &first, &ieee_options()).unwrap(),
+            4
+        );
+        let written = fs::read_to_string(&first).unwrap();
+        assert_eq!(
+            written,
+            "00000C;Cisco Systems, Inc\n001122;CIMSYS Inc\n0000C1;Madge Ltd.\nAABBCC;\"Smith; Jones \"\"Labs\"\" Ltd\"\n"
+        );
+
+        /* The output is already in the database format, so normalizing it again changes nothing */
+        assert_eq!(
+            normalize_database(&first, &second, &NormalizeOptions::default()).unwrap(),
+            4
+        );
+        assert_eq!(fs::read_to_string(&second).unwrap(), written);
+
+        let db = OuiDatabase::open(first.to_str().unwrap()).unwrap();
+        assert_eq!(
+            db.lookup("0000C1"),
+            LookupResult::Found("Madge Ltd.".to_string())
+        );
+        assert_eq!(
+            db.lookup("AABBCC"),
+            LookupResult::Found("Smith; Jones \"Labs\" Ltd".to_string())
+        );
+        assert_eq!(db.lookup("000000"), LookupResult::NotFound);
+        assert!(!dir.path().join("first.csv.part").exists());
+    }
+
+    #[test]
+    fn normalize_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("oui.csv");
+        fs::copy(IEEE_SAMPLE, &path).unwrap();
+
+        assert_eq!(
+            normalize_database(&path, &path, &ieee_options()).unwrap(),
+            4
+        );
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .starts_with("00000C;Cisco Systems, Inc\n")
+        );
+    }
+
+    #[test]
+    fn normalize_nothing_usable() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source.csv");
+        let dest = dir.path().join("dest.csv");
+        fs::write(
+            &source,
+            "Registry,Assignment,Organization Name\nMA-L,zz,Bad\nMA-L,001122,\n",
+        )
+        .unwrap();
+
+        let err = normalize_database(&source, &dest, &ieee_options()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            OuiError::EmptyDatabase(source.to_string_lossy().into_owned()).to_string()
+        );
+        assert!(!dest.exists());
+        assert!(!dir.path().join("dest.csv.part").exists());
+    }
+
+    /* The columns detect_columns finds in a one-off file holding contents */
+    fn detected(contents: &str, delimiter: Option<u8>) -> Option<(u8, usize, usize)> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        fs::write(&path, contents).unwrap();
+        detect_columns(&path, delimiter)
+            .unwrap()
+            .map(|options| (options.delimiter, options.oui_column, options.vendor_column))
+    }
+
+    #[test]
+    fn ieee_headers_are_detected() {
+        let options = detect_columns(Path::new(IEEE_SAMPLE), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (options.delimiter, options.oui_column, options.vendor_column),
+            (b',', 1, 2)
+        );
+        assert!(options.has_header);
+
+        /* Names match whatever their case, padding, order or byte order mark */
+        assert_eq!(
+            detected("\u{feff}ORGANIZATION NAME , oui\nCisco,00000C\n", None),
+            Some((b',', 1, 0))
+        );
+        assert_eq!(
+            detected("Vendor Name;MAC Prefix;Notes\n", None),
+            Some((b';', 1, 0))
+        );
+        assert_eq!(detected("Company\tAssignment\n", None), Some((b'\t', 1, 0)));
+        assert_eq!(
+            detected("id|OUI|Manufacturer\n", Some(b'|')),
+            Some((b'|', 1, 2))
+        );
+    }
+
+    #[test]
+    fn unrecognized_headers_fall_back() {
+        /* No header at all, or one naming just one of the columns */
+        assert_eq!(detected("00000C;Cisco Systems, Inc\n", None), None);
+        assert_eq!(
+            detected("Assignment,Address\n00000C,San Jose\n", None),
+            None
+        );
+        assert_eq!(detected("", None), None);
+    }
+}
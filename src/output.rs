@@ -0,0 +1,712 @@
+/*
+ * output.rs
+ *
+ * Rendering lookup results for the terminal and for other programs.
+ */
+
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+use oui::{
+    Classifier, LookupRecord, LookupResult, MacAddress, OuiError, RandomizedMac, Registry,
+    VendorMetadata, Verdict, format_oui,
+};
+use serde::Serialize;
+
+use crate::cli::Field;
+
+/*
+ * Entry - One looked-up address and everything needed to print it
+ */
+pub struct Entry {
+    pub input: String,                           /* Address as supplied */
+    pub mac: Option<MacAddress>,                 /* Parsed address, if it parsed */
+    pub outcome: Result<LookupResult, OuiError>, /* Lookup result or parse error */
+    pub verdict: Option<Verdict>,                /* Vendor policy verdict, if any */
+    pub classes: Vec<(String, String)>,          /* Classifier name and description */
+    pub metadata: Option<VendorMetadata>,        /* Vendor details from --metadata */
+    pub interface: Option<String>,               /* Local interface name, for --self */
+    pub registry: Option<Registry>,              /* Registry of the matched block, for a hit */
+    pub online: bool,                            /* The vendor came from --online-fallback */
+    pub source: Option<String>,                  /* Database file that held the vendor */
+}
+
+/*
+ * Style - Presentation choices shared by every output mode
+ */
+#[derive(Clone, Copy, Default)]
+pub struct Style {
+    pub verbose: bool,        /* Show normalized addresses alongside results */
+    pub preserve_input: bool, /* Echo addresses with the input's casing and separators */
+    pub shell_escape: bool,   /* Quote the vendor so a shell reads it back as one word */
+    pub truncate_vendor: Option<usize>, /* Shorten plain vendor names to this many characters */
+    pub show_source: bool,    /* Say which database file each vendor came from */
+}
+
+impl Entry {
+    /* An entry with nothing but the lookup itself filled in */
+    pub fn new(
+        input: &str,
+        mac: Option<MacAddress>,
+        outcome: Result<LookupResult, OuiError>,
+    ) -> Self {
+        Entry {
+            input: input.to_string(),
+            mac,
+            outcome,
+            verdict: None,
+            classes: Vec::new(),
+            metadata: None,
+            interface: None,
+            registry: None,
+            online: false,
+            source: None,
+        }
+    }
+
+    pub fn record(&self, style: &Style) -> LookupRecord {
+        let mut record =
+            LookupRecord::new(&self.input, self.mac.as_ref(), &self.outcome, self.verdict);
+        record.classes = self.classes.iter().cloned().collect();
+        record.metadata = self.metadata.clone();
+        record.interface = self.interface.clone();
+        record.online = self.online.then_some(true);
+        if style.show_source {
+            record.source = self.source.clone();
+        }
+        if style.verbose
+            && let Some(mac) = &self.mac
+        {
+            record.add_analysis(mac, self.registry);
+        }
+        if style.preserve_input
+            && let Some(mac) = &self.mac
+        {
+            record.mac = Some(mac.full_as_input());
+            record.oui = Some(mac.oui_as_input());
+        }
+        record
+    }
+}
+
+/*
+ * format_result - Render a lookup result for plain-text output
+ * @entry: The looked-up address, for the verdict and normalized forms
+ * @result: Outcome of the lookup
+ * @style: verbose prefixes the result with the MAC and OUI, written the
+ *         way they were supplied when preserve_input is set, and adds the
+ *         registry and block size of a hit
+ *
+ * Return: The result text, tagged with the verdict when there is one
+ */
+pub fn format_result(entry: &Entry, result: &LookupResult, style: &Style) -> String {
+    /* A guess about a randomized address says more than "No match." */
+    let guess = entry
+        .classes
+        .iter()
+        .find(|(name, _)| name == RandomizedMac.name())
+        .filter(|_| *result == LookupResult::NotFound)
+        .map(|(_, guess)| guess);
+    let result = match (result, style.truncate_vendor) {
+        (LookupResult::Found(vendor), Some(max)) if guess.is_none() => {
+            truncate_display(vendor, max)
+        }
+        _ => guess.cloned().unwrap_or_else(|| result.to_string()),
+    };
+    let result = if style.shell_escape {
+        shell_quote(&result)
+    } else {
+        result
+    };
+    let mut line = match (&entry.mac, style.verbose) {
+        /* Interfaces always show their address, since that's what was looked up */
+        (Some(mac), _) if entry.interface.is_some() => format!(
+            "{}\t{}\t{}",
+            entry.interface.as_deref().unwrap_or_default(),
+            mac.canonical(),
+            result
+        ),
+        (Some(mac), true) if style.preserve_input => {
+            format!(
+                "{} ({}): {}",
+                mac.full_as_input(),
+                mac.oui_as_input(),
+                result
+            )
+        }
+        (Some(mac), true) => format!("{} ({}): {}", mac.canonical(), format_oui(&mac.oui), result),
+        _ => result,
+    };
+    if entry.online {
+        line.push_str(" [online]");
+    }
+    if style.show_source
+        && let Some(source) = &entry.source
+    {
+        line.push_str(&format!(" (from {})", source));
+    }
+    match entry.registry {
+        Some(Registry::Cid) => line.push_str(" [CID, not a globally unique MAC prefix]"),
+        Some(registry) if style.verbose => {
+            let bits = registry.block_size().trailing_zeros();
+            line.push_str(&format!(" [{}, 2^{} addresses]", registry, bits));
+        }
+        _ => {}
+    }
+    if let Some(metadata) = &entry.metadata {
+        line.push_str(&format!(" {{{}}}", metadata));
+    }
+    let classes: Vec<&str> = entry
+        .classes
+        .iter()
+        .map(|(_, class)| class)
+        .filter(|&class| Some(class) != guess)
+        .map(String::as_str)
+        .collect();
+    if !classes.is_empty() {
+        line.push_str(&format!(" ({})", classes.join("; ")));
+    }
+    if let Some(verdict) = entry.verdict {
+        line.push_str(&format!(" [{}]", verdict));
+    }
+    line
+}
+
+/*
+ * shell_quote - Quote text so a POSIX shell reads it back unchanged
+ * @text: The text to quote
+ *
+ * SHELL QUOTING:
+ * Inside single quotes a shell treats every character literally, except
+ * the single quote itself, which can't be escaped there. Each ' is written
+ * as '\'' instead: close the quotes, add an escaped quote, reopen them. So
+ * O'Brien Electronics becomes 'O'\''Brien Electronics'. Text made only of
+ * characters no shell treats specially is left bare.
+ *
+ * Return: The quoted text
+ */
+pub fn shell_quote(text: &str) -> String {
+    let plain = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:@%+=,".contains(c));
+    if plain {
+        return text.to_string();
+    }
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/*
+ * write_env - Write one record as shell variable assignments, for --output env
+ * @out: Where to write the assignments
+ * @record: The record of a single lookup
+ *
+ * Both variables are always set, to empty strings for a miss, so a script
+ * that evals the output never picks up values left over from earlier.
+ * Values go through shell_quote, so eval "$(oui --output env MAC)" is safe
+ * whatever the vendor is called.
+ *
+ * Return: Result indicating whether writing succeeded
+ */
+pub fn write_env(out: &mut dyn Write, record: &LookupRecord) -> Result<(), OuiError> {
+    let vendor = record.vendor.as_deref().unwrap_or_default();
+    let prefix = record.oui.as_deref().map(format_oui).unwrap_or_default();
+    writeln!(out, "OUI_VENDOR={}", shell_quote(vendor))?;
+    writeln!(out, "OUI_PREFIX={}", shell_quote(&prefix))?;
+    Ok(())
+}
+
+/*
+ * print_json - Serialize a value and write it as JSON
+ * @out: Where to write the JSON
+ * @value: Anything serde can serialize, usually records or a list of them
+ * @pretty: Indent the output for humans instead of keeping it compact
+ *
+ * serde_json handles the escaping, and to_string_pretty differs from
+ * to_string only in layout, so both modes share this one code path.
+ *
+ * Return: Result indicating whether serialization succeeded
+ */
+pub fn print_json<T: Serialize + ?Sized>(
+    out: &mut dyn Write,
+    value: &T,
+    pretty: bool,
+) -> Result<(), OuiError> {
+    let serialized = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+    .map_err(|e| OuiError::Io(e.into()))?;
+
+    writeln!(out, "{}", serialized)?;
+    Ok(())
+}
+
+/**
+ * print_ndjson - Write each item as one compact JSON object per line
+ * @out: Where to write the lines
+ * @items: The records to write, in order
+ *
+ * NDJSON suits tools that read output a line at a time, such as jq -c or
+ * log shippers, since no line depends on the ones around it.
+ *
+ * Return: Result indicating whether serialization succeeded
+ */
+pub fn print_ndjson<T: Serialize>(out: &mut dyn Write, items: &[T]) -> Result<(), OuiError> {
+    for item in items {
+        print_json(out, item, false)?;
+    }
+    Ok(())
+}
+
+/*
+ * print_yaml - Serialize a value and write it as YAML
+ * @out: Where to write the YAML
+ * @value: Anything serde can serialize, usually records or a list of them
+ *
+ * serde_yaml drives the same Serialize impls as print_json, so YAML output
+ * has the same fields in the same order as JSON output. It quotes any
+ * string that would otherwise read back as something else, so a vendor
+ * named "true" or "1e3" stays a string.
+ *
+ * Return: Result indicating whether serialization succeeded
+ */
+pub fn print_yaml<T: Serialize + ?Sized>(out: &mut dyn Write, value: &T) -> Result<(), OuiError> {
+    serde_yaml::to_writer(out, value).map_err(|e| OuiError::Io(io::Error::other(e)))
+}
+
+/*
+ * format_fields - Render selected record fields as one tab-separated line
+ * @record: The looked-up address
+ * @fields: Which fields to print, in order
+ *
+ * Missing values (no vendor for a miss, no OUI for invalid input) become
+ * empty strings so every line has the same number of columns.
+ *
+ * Return: The tab-separated line
+ */
+pub fn format_fields(record: &LookupRecord, fields: &[Field]) -> String {
+    field_values(record, fields).join("\t")
+}
+
+/*
+ * field_values - Pull the selected fields out of a record as strings
+ * @record: The looked-up address
+ * @fields: Which fields to return, in order
+ */
+fn field_values(record: &LookupRecord, fields: &[Field]) -> Vec<String> {
+    fields
+        .iter()
+        .map(|field| match field {
+            Field::Interface => record.interface.clone().unwrap_or_default(),
+            Field::Input => record.input.clone(),
+            Field::Mac => record.mac.clone().unwrap_or_default(),
+            Field::Oui => record.oui.clone().unwrap_or_default(),
+            Field::Vendor => record.vendor.clone().unwrap_or_default(),
+            Field::Found => record.found.to_string(),
+            Field::Verdict => record.verdict.map(|v| v.to_string()).unwrap_or_default(),
+            Field::Source => record.source.clone().unwrap_or_default(),
+        })
+        .collect()
+}
+
+/*
+ * Template - A parsed --output-template, ready to fill in for each result
+ *
+ * TEMPLATE SYNTAX:
+ * Text is copied as is, and {name} is replaced by that field of the
+ * result: any --field name, plus {registry} for the IEEE registry of a
+ * hit. {{ and }} stand for literal braces. The template is checked once,
+ * up front, so a typo is a usage error instead of a blank column on every
+ * line.
+ */
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+/* One literal run or placeholder of a template */
+enum Piece {
+    Text(String),
+    Field(Field),
+    Registry,
+}
+
+impl Template {
+    /*
+     * parse - Split a template into literal text and placeholders
+     * @template: The text given to --output-template
+     *
+     * Return: Result containing the template, or a message naming what is wrong with it
+     */
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("\"{{{}\" is never closed", name)),
+                        }
+                    }
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(placeholder(&name)?);
+                }
+                '}' => return Err("a lone \"}\" needs to be written as \"}}\"".to_string()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(Template { pieces })
+    }
+
+    /*
+     * render - Fill in the template for one result
+     * @entry: The looked-up address
+     * @style: Presentation choices, as for --field
+     *
+     * Return: The line, with empty strings for fields the result doesn't have
+     */
+    pub fn render(&self, entry: &Entry, style: &Style) -> String {
+        let record = entry.record(style);
+        let mut line = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => line.push_str(text),
+                Piece::Field(field) => {
+                    line.push_str(&field_values(&record, std::slice::from_ref(field))[0])
+                }
+                Piece::Registry => {
+                    line.push_str(entry.registry.map(Registry::name).unwrap_or_default())
+                }
+            }
+        }
+        line
+    }
+}
+
+/* The piece a {name} placeholder stands for */
+fn placeholder(name: &str) -> Result<Piece, String> {
+    if name == "registry" {
+        return Ok(Piece::Registry);
+    }
+    Field::from_str(name, false).map(Piece::Field).map_err(|_| {
+        let known: Vec<String> = Field::value_variants()
+            .iter()
+            .map(|field| format!("{{{}}}", field.name()))
+            .chain(std::iter::once("{registry}".to_string()))
+            .collect();
+        format!(
+            "unknown placeholder {{{}}}; expected one of {}",
+            name,
+            known.join(", ")
+        )
+    })
+}
+
+/*
+ * truncate_display - Shorten text to a number of characters, marking the cut
+ * @text: The text to shorten
+ * @max: Most characters to keep, including the ellipsis
+ *
+ * Counts chars rather than bytes so a vendor name with accented letters is
+ * never cut in the middle of a character. A limit of 0 leaves no room even
+ * for the ellipsis, so nothing is kept.
+ *
+ * Return: The text unchanged if it fits, otherwise cut short and ending in "…"
+ */
+pub fn truncate_display(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let mut cut: String = text.chars().take(max.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/*
+ * write_table - Write records as a table with aligned columns
+ * @out: Where to write the table
+ * @records: The records to write, one row each
+ * @fields: Which columns to write, in order
+ * @max_width: Longest vendor name to show before truncating
+ *
+ * Column widths depend on every row, so the whole batch has to be known
+ * before the first line is written.
+ *
+ * Return: Result indicating whether writing succeeded
+ */
+pub fn write_table(
+    out: &mut dyn Write,
+    records: &[LookupRecord],
+    fields: &[Field],
+    max_width: usize,
+) -> Result<(), OuiError> {
+    let header: Vec<String> = fields.iter().map(|f| f.name().to_uppercase()).collect();
+    let rows: Vec<Vec<String>> = records
+        .iter()
+        .map(|record| {
+            field_values(record, fields)
+                .into_iter()
+                .zip(fields)
+                .map(|(value, field)| match field {
+                    Field::Vendor => truncate_display(&value, max_width),
+                    _ => value,
+                })
+                .collect()
+        })
+        .collect();
+
+    let widths: Vec<usize> = (0..fields.len())
+        .map(|i| {
+            rows.iter()
+                .chain([&header])
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    for row in [&header].into_iter().chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        /* The last column is padded too, so trim it rather than leave trailing spaces */
+        writeln!(out, "{}", cells.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+/*
+ * Summary - Totals for a batch, reported by --summary
+ */
+#[derive(Default)]
+pub struct Summary {
+    pub processed: usize,
+    pub matched: usize,
+    pub unmatched: usize,
+    pub invalid: usize,
+}
+
+impl Summary {
+    /* Count how each entry in a batch turned out */
+    pub fn of(entries: &[Entry]) -> Summary {
+        let mut summary = Summary {
+            processed: entries.len(),
+            ..Summary::default()
+        };
+        for entry in entries {
+            match &entry.outcome {
+                Ok(LookupResult::Found(_)) => summary.matched += 1,
+                Ok(LookupResult::NotFound | LookupResult::ScanLimit(_)) => summary.unmatched += 1,
+                Err(_) => summary.invalid += 1,
+            }
+        }
+        summary
+    }
+}
+
+/*
+ * write_csv - Write records as CSV with a header row
+ * @out: Where to write the CSV
+ * @records: The records to write, one row each
+ * @fields: Which columns to write, in order
+ * @summary: Totals to append as a trailing comment line, if requested
+ *
+ * CSV WRITER:
+ * csv::Writer quotes any value containing a comma, quote or newline, which
+ * vendor names often do. The summary line is written after the writer is
+ * flushed and starts with '#', so it never becomes part of a row and
+ * readers configured with a comment character skip it.
+ *
+ * Return: Result indicating whether writing succeeded
+ */
+pub fn write_csv(
+    out: &mut dyn Write,
+    records: &[LookupRecord],
+    fields: &[Field],
+    summary: Option<&Summary>,
+) -> Result<(), OuiError> {
+    let mut writer = csv::Writer::from_writer(&mut *out);
+    writer.write_record(fields.iter().map(|f| f.name()))?;
+    for record in records {
+        writer.write_record(field_values(record, fields))?;
+    }
+    writer.flush()?;
+    drop(writer);
+
+    if let Some(s) = summary {
+        writeln!(
+            out,
+            "# processed={} matched={} unmatched={} invalid={}",
+            s.processed, s.matched, s.unmatched, s.invalid
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* Strings YAML would otherwise read as a bool or a number stay strings */
+    #[test]
+    fn yaml_quotes_ambiguous_strings() {
+        let mut out = Vec::new();
+        print_yaml(&mut out, &["true", "1e3", "Cisco Systems, Inc"]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "- 'true'\n- '1e3'\n- Cisco Systems, Inc\n"
+        );
+    }
+
+    #[test]
+    fn shell_quote_cases() {
+        assert_eq!(shell_quote("Cisco"), "Cisco");
+        assert_eq!(shell_quote("Cisco Systems, Inc"), "'Cisco Systems, Inc'");
+        assert_eq!(
+            shell_quote("O'Brien Electronics"),
+            "'O'\\''Brien Electronics'"
+        );
+        assert_eq!(shell_quote("Say \"hi\" (Ltd)"), "'Say \"hi\" (Ltd)'");
+        assert_eq!(shell_quote("$HOME;`rm`"), "'$HOME;`rm`'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn truncate_counts_characters() {
+        assert_eq!(truncate_display("Société Générale", 8), "Société…");
+        assert_eq!(truncate_display("Société Générale", 16), "Société Générale");
+        assert_eq!(truncate_display("华为技术有限公司", 3), "华为…");
+        assert_eq!(truncate_display("Cisco", 1), "…");
+        assert_eq!(truncate_display("Cisco", 0), "");
+        assert_eq!(truncate_display("", 0), "");
+    }
+
+    /* The --output env lines for looking up mac with the given outcome */
+    fn env_lines(mac: &str, outcome: Result<LookupResult, OuiError>) -> String {
+        let mac = oui::parse_mac(mac).unwrap();
+        let mut out = Vec::new();
+        write_env(
+            &mut out,
+            &LookupRecord::new("input", Some(&mac), &outcome, None),
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn env_assignments_are_escaped() {
+        let found = LookupResult::Found("O'Brien \"Labs\" Ltd".to_string());
+        let lines = env_lines("aa:bb:cc:00:00:01", Ok(found));
+        assert_eq!(
+            lines,
+            "OUI_VENDOR='O'\\''Brien \"Labs\" Ltd'\nOUI_PREFIX=AA:BB:CC\n"
+        );
+
+        /* A miss still sets both, so nothing is left over from an earlier eval */
+        assert_eq!(
+            env_lines("11:bb:cc:00:00:01", Ok(LookupResult::NotFound)),
+            "OUI_VENDOR=''\nOUI_PREFIX=11:BB:CC\n"
+        );
+    }
+
+    /* The shell reads the quoting back as the original name */
+    #[cfg(unix)]
+    #[test]
+    fn env_assignments_survive_eval() {
+        let name = "O'Brien \"Labs\" $HOME `id` Ltd";
+        let lines = env_lines(
+            "aa:bb:cc:00:00:01",
+            Ok(LookupResult::Found(name.to_string())),
+        );
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{}printf %s \"$OUI_VENDOR\"", lines))
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), name);
+    }
+
+    /* What Template::parse says is wrong with a template */
+    fn template_error(template: &str) -> String {
+        match Template::parse(template) {
+            Ok(_) => panic!("{} parsed", template),
+            Err(message) => message,
+        }
+    }
+
+    /* A template filled in for a Cisco hit with the default style */
+    fn render(template: &str) -> String {
+        let mac = oui::parse_mac("00:00:0c:12:34:56").unwrap();
+        let mut entry = Entry::new(
+            "00:00:0c:12:34:56",
+            Some(mac),
+            Ok(LookupResult::Found("Cisco".to_string())),
+        );
+        entry.registry = Some(Registry::MaL);
+        match Template::parse(template) {
+            Ok(template) => template.render(&entry, &Style::default()),
+            Err(message) => panic!("{}: {}", template, message),
+        }
+    }
+
+    #[test]
+    fn template_placeholders() {
+        assert_eq!(
+            render("{input} is {vendor} ({registry})"),
+            "00:00:0c:12:34:56 is Cisco (MA-L)"
+        );
+        assert_eq!(render("{{{vendor}}}"), "{Cisco}");
+        assert_eq!(render("{{vendor}} }}"), "{vendor} }");
+        assert_eq!(render(""), "");
+    }
+
+    #[test]
+    fn template_errors() {
+        let unknown = template_error("{vendor} {bogus}");
+        assert!(
+            unknown.starts_with("unknown placeholder {bogus}; expected one of {"),
+            "{}",
+            unknown
+        );
+        assert!(
+            unknown.contains("{vendor}") && unknown.ends_with("{registry}"),
+            "{}",
+            unknown
+        );
+
+        assert_eq!(template_error("{vendor"), "\"{vendor\" is never closed");
+        assert_eq!(
+            template_error("{vendor}}"),
+            "a lone \"}\" needs to be written as \"}}\""
+        );
+        assert_eq!(
+            template_error("}"),
+            "a lone \"}\" needs to be written as \"}}\""
+        );
+    }
+}
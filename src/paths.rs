@@ -0,0 +1,298 @@
+/*!
+ * paths.rs
+ *
+ * Locating the OUI database on disk.
+ */
+
+use std::env;
+use std::fs;
+
+use crate::error::OuiError;
+
+/**
+ * SYSTEM_CSV_PATH - Database shared by every user, e.g. from a distro package
+ */
+pub const SYSTEM_CSV_PATH: &str = "/usr/share/oui/IEEE_OUI.csv";
+
+/**
+ * get_csv_path - Construct the path to the IEEE OUI database CSV file
+ *
+ * RESULT TYPE:
+ * Rust uses Result<Type, Error> for operations that can fail
+ * - Ok(value) indicates success and contains the value
+ * - Err(error) indicates failure and contains the error
+ *
+ * ? OPERATOR:
+ * This is shorthand for error propagation. When used after a Result:
+ * - If Ok: unwrap the value and continue
+ * - If Err: return the error to the calling function immediately
+ *
+ * Return: Result containing the CSV path string, or an error if HOME is unset or empty
+ */
+pub fn get_csv_path() -> Result<String, OuiError> {
+    /* map_err converts the VarError into our own error type before ? returns it */
+    let home_path = env::var("HOME").map_err(|_| OuiError::HomeNotSet)?;
+
+    /* An empty HOME would silently turn into a path under the filesystem root */
+    if home_path.is_empty() {
+        return Err(OuiError::HomeNotSet);
+    }
+    let csv_path = format!("{}/.local/share/oui/IEEE_OUI.csv", home_path);
+    Ok(csv_path) // Ok wraps the successful result
+}
+
+/**
+ * csv_path_candidates - Every place a database may be, in order of preference
+ * @explicit: A path given directly, e.g. with --path
+ *
+ * 1. the explicit path
+ * 2. the OUI_CSV_PATH environment variable, if non-empty
+ * 3. the per-user location from get_csv_path(), when HOME is set
+ * 4. SYSTEM_CSV_PATH
+ *
+ * A path the user named, with the flag or the variable, is the only
+ * candidate: falling back from it to another database would answer from a
+ * file the user didn't ask for. Only the implicit locations fall back.
+ *
+ * Return: The candidate paths, most preferred first
+ */
+pub fn csv_path_candidates(explicit: Option<&str>) -> Vec<String> {
+    candidates(explicit, env::var("OUI_CSV_PATH").ok(), get_csv_path().ok())
+}
+
+/*
+ * candidates - csv_path_candidates() with the environment passed in
+ * @explicit: A path given directly, e.g. with --path
+ * @from_env: The value of OUI_CSV_PATH, if set
+ * @per_user: The per-user location, if HOME is set
+ */
+fn candidates(
+    explicit: Option<&str>,
+    from_env: Option<String>,
+    per_user: Option<String>,
+) -> Vec<String> {
+    let named = explicit
+        .map(String::from)
+        .or(from_env.filter(|path| !path.is_empty()));
+    if let Some(path) = named {
+        return vec![path];
+    }
+    per_user
+        .into_iter()
+        .chain([SYSTEM_CSV_PATH.to_string()])
+        .collect()
+}
+
+/**
+ * resolve_csv_path - Decide which database file to read
+ * @explicit: A path given directly, e.g. with --path
+ *
+ * SYSTEM FALLBACK:
+ * The first of csv_path_candidates() that exists wins, so a per-user copy
+ * shadows a system-wide install, which in turn saves every user from
+ * downloading their own. When none exists, the error lists them all so
+ * it's clear where the database was expected; for a --path or
+ * OUI_CSV_PATH that doesn't exist, that is the one path given.
+ *
+ * The result goes through canonical_path(), so a database installed as a
+ * symlink is always handled through the file it points to.
+ *
+ * Return: Result containing the CSV path string, or an error naming every path tried
+ */
+pub fn resolve_csv_path(explicit: Option<&str>) -> Result<String, OuiError> {
+    first_existing(csv_path_candidates(explicit))
+}
+
+/* The first candidate that exists, canonicalized, or an error listing them all */
+fn first_existing(candidates: Vec<String>) -> Result<String, OuiError> {
+    match candidates.iter().find(|path| fs::metadata(path).is_ok()) {
+        Some(path) => Ok(canonical_path(path)),
+        None => Err(OuiError::DatabaseNotFound(candidates)),
+    }
+}
+
+/**
+ * update_target - Decide where a downloaded database should be saved
+ * @explicit: A path given directly, e.g. with --path
+ *
+ * Unlike resolve_csv_path() the file doesn't have to exist yet, and the
+ * system location is never chosen, since it belongs to the package
+ * manager and usually isn't writable anyway.
+ *
+ * Return: Result containing the CSV path string, or an error if falling back to HOME fails
+ */
+pub fn update_target(explicit: Option<&str>) -> Result<String, OuiError> {
+    let path = match explicit {
+        Some(path) => path.to_string(),
+        None => match env::var("OUI_CSV_PATH") {
+            Ok(path) if !path.is_empty() => path,
+            _ => get_csv_path()?,
+        },
+    };
+    Ok(canonical_path(&path))
+}
+
+/**
+ * canonical_path - Resolve symlinks and relative parts of a path
+ * @path: Path that may be, or pass through, a symlink
+ *
+ * Package managers often install the database as a link into their own
+ * tree. Resolving it means the age check, caches keyed by path, and
+ * "oui update" all act on the real file; update in particular replaces
+ * the target instead of overwriting the link with a plain file.
+ *
+ * Return: The canonical path, or the path unchanged if it doesn't exist yet
+ */
+pub fn canonical_path(path: &str) -> String {
+    match fs::canonicalize(path) {
+        Ok(real) => real.to_string_lossy().into_owned(),
+        Err(_) => path.to_string(),
+    }
+}
+
+/*
+ * ensure_not_directory - Reject a directory where a file is expected
+ * @path: Path about to be opened as a file
+ *
+ * Opening a directory fails with a platform-specific message (or, on some
+ * systems, only once reading starts), so check up front and say plainly
+ * what went wrong. A missing path is left for the open itself to report.
+ *
+ * Return: Result indicating whether the path can be opened as a file
+ */
+pub(crate) fn ensure_not_directory(path: &str) -> Result<(), OuiError> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => Err(OuiError::IsDirectory(path.to_string())),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn some(path: &str) -> Option<String> {
+        Some(path.to_string())
+    }
+
+    #[test]
+    fn candidate_order() {
+        let home = some("/home/u/.local/share/oui/IEEE_OUI.csv");
+        assert_eq!(
+            candidates(Some("/flag.csv"), some("/env.csv"), home.clone()),
+            ["/flag.csv"]
+        );
+        assert_eq!(
+            candidates(None, some("/env.csv"), home.clone()),
+            ["/env.csv"]
+        );
+        assert_eq!(
+            candidates(None, some(""), home.clone()),
+            [home.clone().unwrap(), SYSTEM_CSV_PATH.to_string()]
+        );
+        assert_eq!(
+            candidates(None, None, home.clone()),
+            [home.unwrap(), SYSTEM_CSV_PATH.to_string()]
+        );
+        assert_eq!(candidates(None, None, None), [SYSTEM_CSV_PATH]);
+    }
+
+    #[test]
+    fn first_existing_candidate_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.csv").to_str().unwrap().to_string();
+        let present = dir.path().join("present.csv").to_str().unwrap().to_string();
+        fs::write(&present, "00000C;Cisco Systems, Inc\n").unwrap();
+
+        let found = first_existing(vec![missing.clone(), present.clone()]).unwrap();
+        assert_eq!(found, canonical_path(&present));
+    }
+
+    #[test]
+    fn missing_explicit_path_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.csv").to_str().unwrap().to_string();
+
+        let error = first_existing(candidates(Some(&missing), None, None)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "No OUI database found (tried {}); run \"oui update\" to download one.",
+                missing
+            )
+        );
+        let error = resolve_csv_path(Some(&missing)).unwrap_err();
+        assert!(matches!(error, OuiError::DatabaseNotFound(tried) if tried == [missing]));
+    }
+
+    #[test]
+    fn directory_as_database_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let expected = format!("Expected a file but found a directory: {}", path);
+
+        assert_eq!(
+            ensure_not_directory(path).unwrap_err().to_string(),
+            expected
+        );
+        let open = crate::OuiDatabase::open(path).unwrap_err();
+        assert_eq!(open.to_string(), expected);
+        let scan = crate::lookup_oui(path, "00000C").unwrap_err();
+        assert_eq!(scan.to_string(), expected);
+    }
+
+    /* A fresh link to an old file must look old, as the package-managed case does */
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_database_uses_the_target_mtime() {
+        use std::time::{Duration, SystemTime};
+
+        const DAY: u64 = 24 * 60 * 60;
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("IEEE_OUI.csv");
+        fs::write(&target, "00000C;Cisco Systems, Inc\n").unwrap();
+        let old = SystemTime::now() - Duration::from_secs(200 * DAY);
+        fs::File::options()
+            .write(true)
+            .open(&target)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let link = dir.path().join("link.csv");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let resolved = resolve_csv_path(link.to_str()).unwrap();
+        assert_eq!(resolved, canonical_path(target.to_str().unwrap()));
+        let age = crate::database_age(&resolved).unwrap();
+        assert!(age.as_secs() / DAY >= 199);
+
+        let link_age = fs::symlink_metadata(&link).unwrap().modified().unwrap();
+        assert!(link_age.elapsed().unwrap() < Duration::from_secs(DAY));
+    }
+
+    /*
+     * The only test that touches HOME, and no other test in this binary
+     * depends on it, so changing it can't race with a reader here
+     */
+    #[test]
+    fn empty_home_is_not_set() {
+        let saved = env::var_os("HOME");
+        let set_home = |value: Option<&std::ffi::OsStr>| match value {
+            /* SAFETY: see above; nothing else reads or writes HOME meanwhile */
+            Some(value) => unsafe { env::set_var("HOME", value) },
+            None => unsafe { env::remove_var("HOME") },
+        };
+
+        set_home(Some("".as_ref()));
+        let empty = get_csv_path();
+        set_home(None);
+        let unset = get_csv_path();
+        set_home(Some("/home/u".as_ref()));
+        let set = get_csv_path();
+        set_home(saved.as_deref());
+
+        assert!(matches!(empty, Err(OuiError::HomeNotSet)), "{:?}", empty);
+        assert!(matches!(unset, Err(OuiError::HomeNotSet)), "{:?}", unset);
+        assert_eq!(set.unwrap(), "/home/u/.local/share/oui/IEEE_OUI.csv");
+    }
+}
@@ -0,0 +1,109 @@
+/*!
+ * pcap.rs
+ *
+ * Pulling MAC addresses out of packet captures (the "pcap" feature).
+ *
+ * FILE FORMAT:
+ * A classic pcap file is a 24-byte global header followed by one record
+ * per packet. The header starts with a magic number that also gives the
+ * byte order, and ends with the link type, which must be Ethernet for the
+ * first 12 bytes of each packet to be the destination and source MACs.
+ * Each record has a 16-byte header whose third field is the number of
+ * packet bytes stored after it. The newer pcapng format is not supported.
+ */
+
+use std::collections::HashSet;
+use std::fs;
+
+use crate::error::OuiError;
+
+const GLOBAL_HEADER_LENGTH: usize = 24; /* Magic, version, zone, sigfigs, snaplen, link type */
+const RECORD_HEADER_LENGTH: usize = 16; /* Seconds, fraction, captured length, original length */
+const LINKTYPE_ETHERNET: u32 = 1; /* IEEE 802.3 Ethernet frames */
+const ADDRESS_BYTES: usize = 6; /* One MAC address */
+
+/* Magic numbers as read in little-endian order */
+const MAGIC_MICROS: u32 = 0xA1B2_C3D4;
+const MAGIC_NANOS: u32 = 0xA1B2_3C4D;
+const MAGIC_PCAPNG: u32 = 0x0A0D_0D0A;
+
+/**
+ * read_pcap_macs - Collect the unique MAC addresses seen in a capture
+ * @path: Path to a classic pcap file with Ethernet frames
+ *
+ * Both the source and destination of every frame are collected, in the
+ * order they first appear, as colon-separated strings ready for parse_mac.
+ * Frames too short to hold both addresses are skipped.
+ *
+ * Return: Result containing the addresses, or an error if the file isn't a readable Ethernet capture
+ */
+pub fn read_pcap_macs(path: &str) -> Result<Vec<String>, OuiError> {
+    let data = fs::read(path)?;
+    let header = data
+        .get(..GLOBAL_HEADER_LENGTH)
+        .ok_or_else(|| OuiError::Pcap("file is too short to be a capture".to_string()))?;
+
+    /* The magic is written in the capturing machine's byte order */
+    let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    let big_endian = match magic {
+        MAGIC_MICROS | MAGIC_NANOS => false,
+        _ if magic.swap_bytes() == MAGIC_MICROS || magic.swap_bytes() == MAGIC_NANOS => true,
+        MAGIC_PCAPNG => {
+            return Err(OuiError::Pcap(
+                "pcapng is not supported; convert with \"editcap -F pcap\"".to_string(),
+            ));
+        }
+        _ => return Err(OuiError::Pcap("not a pcap file".to_string())),
+    };
+    let read_u32 = |bytes: &[u8]| {
+        let word = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if big_endian {
+            u32::from_be_bytes(word)
+        } else {
+            u32::from_le_bytes(word)
+        }
+    };
+
+    let link_type = read_u32(&header[20..24]);
+    if link_type != LINKTYPE_ETHERNET {
+        return Err(OuiError::Pcap(format!(
+            "link type {} is not Ethernet",
+            link_type
+        )));
+    }
+
+    let mut seen = HashSet::new();
+    let mut macs = Vec::new();
+    let mut offset = GLOBAL_HEADER_LENGTH;
+
+    while offset < data.len() {
+        let record = data
+            .get(offset..offset + RECORD_HEADER_LENGTH)
+            .ok_or_else(|| OuiError::Pcap("capture ends inside a packet header".to_string()))?;
+        let captured = read_u32(&record[8..12]) as usize;
+        offset += RECORD_HEADER_LENGTH;
+
+        let frame = data
+            .get(offset..offset + captured)
+            .ok_or_else(|| OuiError::Pcap("capture ends inside a packet".to_string()))?;
+        offset += captured;
+
+        if frame.len() < 2 * ADDRESS_BYTES {
+            continue;
+        }
+        /* Destination comes first in the frame, then source */
+        for address in frame.chunks_exact(ADDRESS_BYTES).take(2) {
+            if seen.insert(address.to_vec()) {
+                macs.push(
+                    address
+                        .iter()
+                        .map(|byte| format!("{:02X}", byte))
+                        .collect::<Vec<_>>()
+                        .join(":"),
+                );
+            }
+        }
+    }
+
+    Ok(macs)
+}
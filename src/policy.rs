@@ -0,0 +1,98 @@
+/*!
+ * policy.rs
+ *
+ * Vendor allowlist/blocklist checks layered over lookup results.
+ */
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::lookup::LookupResult;
+
+/**
+ * Verdict - Whether a looked-up address passes the vendor policy
+ *
+ * Serializes as the lowercase variant name, the same text Display prints.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Allowed,
+    Blocked,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Allowed => write!(f, "allowed"),
+            Verdict::Blocked => write!(f, "blocked"),
+        }
+    }
+}
+
+/**
+ * VendorPolicy - Sets of approved and forbidden vendor names
+ *
+ * Entries are matched as case-insensitive substrings of the vendor name, so
+ * "cisco" covers both "Cisco Systems, Inc" and "Cisco Meraki".
+ *
+ * RULES:
+ * - A vendor matching any block entry is blocked
+ * - When an allowlist is given, a vendor matching none of its entries is
+ *   blocked, and so is an address with no vendor at all
+ * - Everything else is allowed
+ */
+#[derive(Debug, Clone, Default)]
+pub struct VendorPolicy {
+    allow: Vec<String>,
+    block: Vec<String>,
+}
+
+impl VendorPolicy {
+    /**
+     * new - Build a policy from allow and block substrings
+     * @allow: Vendors that are approved; empty means "anything not blocked"
+     * @block: Vendors that are never approved
+     */
+    pub fn new(allow: &[String], block: &[String]) -> Self {
+        /* Lowercase once here instead of on every check */
+        let lower = |list: &[String]| list.iter().map(|s| s.to_lowercase()).collect();
+        VendorPolicy {
+            allow: lower(allow),
+            block: lower(block),
+        }
+    }
+
+    /**
+     * is_empty - Whether the policy has no entries and therefore never applies
+     */
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.block.is_empty()
+    }
+
+    /**
+     * check - Decide whether a lookup result passes the policy
+     * @result: Outcome of looking up an address
+     *
+     * Return: The verdict for this result
+     */
+    pub fn check(&self, result: &LookupResult) -> Verdict {
+        let vendor = match result {
+            LookupResult::Found(vendor) => vendor.to_lowercase(),
+            LookupResult::NotFound | LookupResult::ScanLimit(_) if self.allow.is_empty() => {
+                return Verdict::Allowed;
+            }
+            LookupResult::NotFound | LookupResult::ScanLimit(_) => return Verdict::Blocked,
+        };
+
+        /* any() stops at the first entry for which the closure returns true */
+        let matches = |list: &[String]| list.iter().any(|entry| vendor.contains(entry.as_str()));
+
+        if matches(&self.block) || (!self.allow.is_empty() && !matches(&self.allow)) {
+            Verdict::Blocked
+        } else {
+            Verdict::Allowed
+        }
+    }
+}
@@ -0,0 +1,211 @@
+/*!
+ * record.rs
+ *
+ * Serializable description of one lookup, used for machine-readable output.
+ */
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::classify::AddressKind;
+use crate::error::OuiError;
+use crate::lookup::{LookupResult, Registry};
+use crate::mac::MacAddress;
+use crate::metadata::VendorMetadata;
+use crate::policy::Verdict;
+
+/**
+ * LookupRecord - Everything known about one looked-up address
+ *
+ * SERDE:
+ * #[derive(Serialize)] generates the code that turns this struct into JSON
+ * (or any other serde format), using the field names as keys. Building
+ * output from a struct instead of by hand means every format sees the same
+ * fields and escaping is always correct.
+ *
+ * skip_serializing_if leaves a field out entirely when it has no value, so
+ * optional details don't clutter the common case. classes is a BTreeMap
+ * rather than a HashMap so its keys always serialize in the same order.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LookupRecord {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interface: Option<String>, /* Local interface the address belongs to, for --self */
+    pub input: String,          /* The address exactly as supplied */
+    pub mac: Option<String>,    /* Normalized address, e.g. "00:11:22:33:44:55" */
+    pub oui: Option<String>,    /* Normalized OUI, e.g. "001122" */
+    pub found: bool,            /* Whether the OUI is in the database */
+    pub vendor: Option<String>, /* Manufacturer name for a hit */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classification: Option<AddressKind>, /* Broadcast, null, multicast or local, if special */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<VendorMetadata>, /* Details from --metadata, when the vendor is listed */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verdict: Option<Verdict>, /* Vendor policy verdict, when a policy is in effect */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub online: Option<bool>, /* Set when the vendor came from --online-fallback */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>, /* Database file the vendor came from, with --show-source */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_limit: Option<usize>, /* Rows read before a capped scan gave up */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>, /* Why the address couldn't be looked up */
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub classes: BTreeMap<String, String>, /* Classifier name to description */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multicast: Option<bool>, /* I/G bit, with add_analysis */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locally_administered: Option<bool>, /* U/L bit, with add_analysis */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eui64: Option<String>, /* Modified EUI-64 interface identifier, with add_analysis */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<Registry>, /* IEEE registry of the matched block, with add_analysis */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_size: Option<u64>, /* Addresses in the matched block, with add_analysis */
+}
+
+impl LookupRecord {
+    /**
+     * new - Build a record from the outcome of looking up one address
+     * @input: The address exactly as supplied
+     * @mac: The parsed address, if it parsed
+     * @outcome: The lookup result, or the error that prevented it
+     * @verdict: Vendor policy verdict, if any
+     */
+    pub fn new(
+        input: &str,
+        mac: Option<&MacAddress>,
+        outcome: &Result<LookupResult, OuiError>,
+        verdict: Option<Verdict>,
+    ) -> Self {
+        let (vendor, error) = match outcome {
+            Ok(LookupResult::Found(vendor)) => (Some(vendor.clone()), None),
+            Ok(LookupResult::NotFound | LookupResult::ScanLimit(_)) => (None, None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let scan_limit = match outcome {
+            Ok(LookupResult::ScanLimit(rows)) => Some(*rows),
+            _ => None,
+        };
+
+        LookupRecord {
+            interface: None,
+            input: input.to_string(),
+            mac: mac.map(MacAddress::canonical),
+            oui: mac.map(|mac| mac.oui.clone()),
+            found: vendor.is_some(),
+            vendor,
+            classification: mac.and_then(AddressKind::of),
+            metadata: None,
+            verdict,
+            online: None,
+            source: None,
+            scan_limit,
+            error,
+            classes: BTreeMap::new(),
+            multicast: None,
+            locally_administered: None,
+            eui64: None,
+            registry: None,
+            block_size: None,
+        }
+    }
+
+    /**
+     * add_analysis - Fill in the structural fields for an address
+     * @mac: The parsed address
+     * @registry: Registry of the matched assignment, for a hit
+     *
+     * These are left out of lean output and only added when asked for,
+     * e.g. by --verbose with JSON.
+     */
+    pub fn add_analysis(&mut self, mac: &MacAddress, registry: Option<Registry>) {
+        if let Some(octet) = mac.first_octet() {
+            self.multicast = Some(octet & 0b01 != 0);
+            self.locally_administered = Some(octet & 0b10 != 0);
+        }
+        self.eui64 = mac.modified_eui64();
+        self.registry = registry;
+        /* A CID doesn't give out addresses, so it has no block to size */
+        self.block_size = registry
+            .filter(|registry| *registry != Registry::Cid)
+            .map(Registry::block_size);
+    }
+}
+
+/**
+ * AnalysisRecord - What the bits of one address say, without a database
+ *
+ * Used by --no-db, where there is no vendor to report, so the lookup
+ * fields of LookupRecord are left out rather than sent as always-empty.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AnalysisRecord {
+    pub input: String,       /* The address exactly as supplied */
+    pub mac: Option<String>, /* Normalized address, e.g. "00:11:22:33:44:55" */
+    pub oui: Option<String>, /* Normalized OUI, e.g. "001122" */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classification: Option<AddressKind>, /* Broadcast, null, multicast or local, if special */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>, /* Why the address couldn't be parsed */
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub classes: BTreeMap<String, String>, /* Classifier name to description */
+}
+
+/**
+ * lookup_record_schema - JSON Schema for one serialized LookupRecord
+ *
+ * SCHEMA:
+ * Written by hand to match the struct above, so a new field has to be
+ * added in both places; the binary's tests validate real output against
+ * it to catch one that isn't. Fields marked skip_serializing_if are optional,
+ * the rest are always present. Batch output is an array of these objects
+ * and NDJSON output is one per line.
+ *
+ * Return: The schema, following JSON Schema draft 2020-12
+ */
+pub fn lookup_record_schema() -> serde_json::Value {
+    let string = || serde_json::json!({ "type": "string" });
+    let nullable_string = || serde_json::json!({ "type": ["string", "null"] });
+    let boolean = || serde_json::json!({ "type": "boolean" });
+
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "LookupRecord",
+        "description": "Everything known about one looked-up address",
+        "type": "object",
+        "properties": {
+            "interface": string(),
+            "input": string(),
+            "mac": nullable_string(),
+            "oui": nullable_string(),
+            "found": boolean(),
+            "vendor": nullable_string(),
+            "classification": {
+                "enum": ["broadcast", "null", "multicast", "locally-administered"]
+            },
+            "metadata": {
+                "type": "object",
+                "properties": {
+                    "country": string(),
+                    "founded": { "type": "integer", "minimum": 0, "maximum": u16::MAX }
+                },
+                "additionalProperties": false
+            },
+            "verdict": { "enum": ["allowed", "blocked"] },
+            "online": { "const": true },
+            "source": string(),
+            "scan_limit": { "type": "integer", "minimum": 0 },
+            "error": string(),
+            "classes": { "type": "object", "additionalProperties": string() },
+            "multicast": boolean(),
+            "locally_administered": boolean(),
+            "eui64": string(),
+            "registry": { "enum": ["MA-L", "MA-M", "MA-S", "CID"] },
+            "block_size": { "type": "integer", "minimum": 1 }
+        },
+        "required": ["input", "mac", "oui", "found", "vendor"],
+        "additionalProperties": false
+    })
+}
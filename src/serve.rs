@@ -0,0 +1,320 @@
+/*!
+ * serve.rs
+ *
+ * Answering lookups over a Unix domain socket from a database loaded once.
+ *
+ * PROTOCOL:
+ * A client connects, writes one MAC address per line and reads one line
+ * back for each: the vendor, "Unknown" if the database doesn't list the
+ * prefix, or "Error: " and the reason if the line isn't an address.
+ * Connections are served one after another, each until the client closes
+ * it, so a client that keeps its connection open avoids reconnecting.
+ *
+ * SIGNALS:
+ * SIGINT and SIGTERM stop the server between lines and remove the socket
+ * file, so the next start doesn't find a leftover one in its way.
+ */
+
+use std::io;
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+#[cfg(unix)]
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(unix)]
+use std::time::Duration;
+
+use crate::database::OuiDatabase;
+use crate::error::OuiError;
+use crate::mac::{ParseOptions, parse_mac_with};
+
+#[cfg(unix)]
+const POLL_INTERVAL: Duration = Duration::from_millis(200); /* How often an idle server checks for a signal */
+
+#[cfg(unix)]
+static STOP: AtomicBool = AtomicBool::new(false); /* Set by the signal handler */
+
+/**
+ * answer - The reply line for one query
+ * @db: Loaded database
+ * @query: Line sent by the client, without its newline
+ * @options: How the address is parsed
+ *
+ * The longest assigned prefix wins, so MA-M and MA-S blocks are reported
+ * instead of the MA-L block around them.
+ *
+ * Return: The vendor, "Unknown", or "Error: " followed by why the query isn't an address
+ */
+pub fn answer(db: &OuiDatabase, query: &str, options: &ParseOptions) -> String {
+    match parse_mac_with(query.trim(), options) {
+        Ok(mac) => match db.lookup_longest(&mac.full) {
+            Some((_, vendor)) => vendor.to_string(),
+            None => "Unknown".to_string(),
+        },
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+/**
+ * serve - Answer queries on a Unix socket until SIGINT or SIGTERM
+ * @db: Loaded database
+ * @socket: Path to create the socket at
+ * @options: How addresses are parsed
+ * @log: Told once the socket is ready for clients
+ *
+ * A socket left behind by a server that was killed is replaced, but one
+ * that still accepts connections, or a path that isn't a socket at all,
+ * is an error rather than being removed.
+ *
+ * Return: Result indicating whether the server stopped cleanly
+ */
+#[cfg(unix)]
+pub fn serve(
+    db: &OuiDatabase,
+    socket: &Path,
+    options: &ParseOptions,
+    log: &mut dyn Write,
+) -> Result<(), OuiError> {
+    remove_stale_socket(socket)?;
+    let listener = UnixListener::bind(socket)?;
+    let _cleanup = RemoveOnDrop(socket.to_path_buf());
+    stop_on_signals()?;
+    writeln!(
+        log,
+        "Serving {} entries from {} on {}",
+        db.len(),
+        db.path(),
+        socket.display()
+    )?;
+
+    /* std retries accept() when a signal interrupts it, so poll instead of blocking */
+    listener.set_nonblocking(true)?;
+    while !STOP.load(Ordering::Relaxed) {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if is_timeout(&e) => {
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        stream.set_nonblocking(false)?;
+        /* One client hanging up mid-reply shouldn't take the server down */
+        let _ = serve_connection(db, stream, options);
+    }
+    Ok(())
+}
+
+/* Other systems have no Unix domain sockets */
+#[cfg(not(unix))]
+pub fn serve(
+    _db: &OuiDatabase,
+    _socket: &Path,
+    _options: &ParseOptions,
+    _log: &mut dyn io::Write,
+) -> Result<(), OuiError> {
+    Err(OuiError::Io(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--serve needs Unix domain sockets, which this system doesn't have",
+    )))
+}
+
+/*
+ * serve_connection - Reply to each line from one client until it disconnects
+ *
+ * Reads time out every POLL_INTERVAL so a signal is noticed even while a
+ * client sits idle. read_until keeps what it has read so far in line when
+ * it times out, so a query arriving in pieces is still read whole.
+ */
+#[cfg(unix)]
+fn serve_connection(
+    db: &OuiDatabase,
+    stream: UnixStream,
+    options: &ParseOptions,
+) -> Result<(), OuiError> {
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+
+    while !STOP.load(Ordering::Relaxed) {
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) if is_timeout(&e) => continue,
+            Err(e) => return Err(e.into()),
+        }
+        /* A last line without a newline is only complete once the client closes */
+        if line.last() != Some(&b'\n') {
+            continue;
+        }
+        let query = String::from_utf8_lossy(&line);
+        let reply = answer(db, query.trim_end_matches(['\r', '\n']), options);
+        writer.write_all(format!("{}\n", reply).as_bytes())?;
+        line.clear();
+    }
+
+    if !line.is_empty() {
+        let query = String::from_utf8_lossy(&line);
+        writer.write_all(format!("{}\n", answer(db, &query, options)).as_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+    )
+}
+
+/* Remove a socket nothing is listening on any more; anything else is left alone */
+#[cfg(unix)]
+fn remove_stale_socket(socket: &Path) -> Result<(), OuiError> {
+    let Ok(metadata) = std::fs::symlink_metadata(socket) else {
+        return Ok(());
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(OuiError::Usage(format!(
+            "{} exists and isn't a socket.",
+            socket.display()
+        )));
+    }
+    if UnixStream::connect(socket).is_ok() {
+        return Err(OuiError::Usage(format!(
+            "Another server is already listening on {}.",
+            socket.display()
+        )));
+    }
+    std::fs::remove_file(socket)?;
+    Ok(())
+}
+
+/* Removes the socket file however serve() returns */
+#[cfg(unix)]
+struct RemoveOnDrop(PathBuf);
+
+#[cfg(unix)]
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/*
+ * stop_on_signals - Make SIGINT and SIGTERM set STOP instead of killing the process
+ *
+ * The handler can only safely touch an atomic, so the loops above notice
+ * the flag the next time they wake up, at most POLL_INTERVAL later.
+ */
+#[cfg(unix)]
+fn stop_on_signals() -> Result<(), OuiError> {
+    extern "C" fn handle(_signal: libc::c_int) {
+        STOP.store(true, Ordering::Relaxed);
+    }
+
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        /* SAFETY: sigaction is zeroed and then fully set up before use, and the handler only stores an atomic */
+        let status = unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handle as *const () as libc::sighandler_t;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(signal, &action, std::ptr::null_mut())
+        };
+        if status != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASIC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/basic.csv");
+
+    #[test]
+    fn answer_replies() {
+        let db = OuiDatabase::open(BASIC).unwrap();
+        let options = ParseOptions::default();
+        assert_eq!(
+            answer(&db, "00:00:0c:12:34:56", &options),
+            "Cisco Systems, Inc"
+        );
+        assert_eq!(answer(&db, " 001122334455 ", &options), "CIMSYS Inc");
+        assert_eq!(answer(&db, "00:11:23:44:55:66", &options), "Unknown");
+        assert_eq!(answer(&db, "zz", &options), "Error: Invalid MAC Address.");
+    }
+
+    /* Send some queries on a fresh connection and read back every reply */
+    #[cfg(unix)]
+    fn exchange(socket: &Path, queries: &str) -> String {
+        use std::io::Read;
+
+        let mut stream = UnixStream::connect(socket).unwrap();
+        stream.write_all(queries.as_bytes()).unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+        let mut replies = String::new();
+        stream.read_to_string(&mut replies).unwrap();
+        replies
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn serves_connections_in_turn_and_removes_the_socket() {
+        let db = OuiDatabase::open(BASIC).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("oui.sock");
+        STOP.store(false, Ordering::Relaxed);
+
+        std::thread::scope(|scope| {
+            let server = scope.spawn(|| {
+                let mut log = Vec::new();
+                serve(&db, &socket, &ParseOptions::default(), &mut log).map(|()| log)
+            });
+            for _ in 0..250 {
+                if socket.exists() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+
+            assert_eq!(
+                exchange(&socket, "00:00:0c:12:34:56\nzz\n"),
+                "Cisco Systems, Inc\nError: Invalid MAC Address.\n"
+            );
+            /* The last query has no newline and is answered once the client closes */
+            assert_eq!(
+                exchange(&socket, "00:11:23:44:55:66\r\n00000C123456"),
+                "Unknown\nCisco Systems, Inc\n"
+            );
+
+            STOP.store(true, Ordering::Relaxed);
+            let log = String::from_utf8(server.join().unwrap().unwrap()).unwrap();
+            assert!(log.starts_with("Serving 4 entries from "));
+        });
+        assert!(!socket.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_to_replace_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("not-a-socket");
+        std::fs::write(&path, "").unwrap();
+        let error = remove_stale_socket(&path).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("{} exists and isn't a socket.", path.display())
+        );
+        assert!(path.exists());
+    }
+}
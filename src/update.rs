@@ -0,0 +1,270 @@
+/*!
+ * update.rs
+ *
+ * Downloading a fresh copy of the IEEE OUI database.
+ */
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::OuiError;
+use crate::lookup::LookupResult;
+use crate::mac::MacAddress;
+
+/**
+ * IEEE_OUI_URL - Where the IEEE publishes the MA-L (OUI) registry as CSV
+ */
+pub const IEEE_OUI_URL: &str = "https://standards-oui.ieee.org/oui/oui.csv";
+
+/**
+ * ONLINE_LOOKUP_URL - The vendor API lookup_online asks by default
+ *
+ * {mac} is replaced with the address being looked up.
+ */
+pub const ONLINE_LOOKUP_URL: &str = "https://api.macvendors.com/{mac}";
+
+/* Fields a JSON vendor API might name the manufacturer with, in order of preference */
+const ONLINE_VENDOR_FIELDS: [&str; 4] = ["vendor", "company", "organization", "name"];
+
+/*
+ * IEEE export columns.
+ * The published file has a header row:
+ * Registry,Assignment,Organization Name,Organization Address
+ */
+const IEEE_ASSIGNMENT_COLUMN: usize = 1;
+const IEEE_ORGANIZATION_COLUMN: usize = 2;
+
+/**
+ * download_database - Fetch the IEEE registry and save it as the local database
+ * @url: Address of an IEEE-format CSV export, normally IEEE_OUI_URL
+ * @dest: Where to write the database
+ *
+ * CONVERSION:
+ * The IEEE export is comma-separated with a header and four columns, while
+ * the local database is headerless "OUI;Manufacturer". Only the two columns
+ * lookups need are kept.
+ *
+ * ATOMIC REPLACE:
+ * The new database is written next to the old one and then renamed over it.
+ * A rename within one directory is atomic, so a failed or interrupted
+ * download never leaves a half-written database behind. The partial file
+ * is removed on every early return, whether the connection drops, a row
+ * can't be read or the disk fills up.
+ *
+ * Return: Result containing the number of rows written, or an error if the download or write fails
+ */
+pub fn download_database(url: &str, dest: &Path) -> Result<usize, OuiError> {
+    /* Some mirrors reject requests without a user agent */
+    let mut response = ureq::get(url)
+        .header("User-Agent", concat!("oui/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(|e| OuiError::Download(e.to_string()))?;
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(response.body_mut().as_reader());
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let partial = dest.with_extension("csv.part");
+    let _cleanup = RemoveOnDrop(&partial);
+
+    let mut wtr = csv::WriterBuilder::new()
+        .delimiter(b';')
+        .from_path(&partial)?;
+
+    let mut rows = 0;
+    for result in rdr.records() {
+        let record = result?;
+        if let (Some(oui), Some(vendor)) = (
+            record.get(IEEE_ASSIGNMENT_COLUMN),
+            record.get(IEEE_ORGANIZATION_COLUMN),
+        ) {
+            wtr.write_record([oui.trim(), vendor.trim()])?;
+            rows += 1;
+        }
+    }
+    wtr.flush()?;
+
+    /* An empty download is almost certainly an error page, so keep the old database */
+    if rows == 0 {
+        return Err(OuiError::Download(format!("no records found at {}", url)));
+    }
+
+    fs::rename(&partial, dest)?;
+    Ok(rows)
+}
+
+/* Removes a partial output file however its writer returns; after the rename it is already gone */
+pub(crate) struct RemoveOnDrop<'a>(pub(crate) &'a Path);
+
+impl Drop for RemoveOnDrop<'_> {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(self.0);
+    }
+}
+
+/**
+ * lookup_online - Ask a web API for the vendor of an address
+ * @url: Endpoint, with {mac} where the address goes, normally ONLINE_LOOKUP_URL
+ * @mac: The address to look up
+ * @timeout: Longest the whole request may take
+ *
+ * RESPONSES:
+ * A 404 means the service doesn't know the address either. Any other
+ * success is read as the vendor: plain text as-is, as macvendors.com
+ * replies, or the first of a few common name fields in a JSON object.
+ * Without a {mac} placeholder the address is appended to the URL.
+ *
+ * Return: Result containing the lookup outcome, or an Online error if the request fails or times out
+ */
+pub fn lookup_online(
+    url: &str,
+    mac: &MacAddress,
+    timeout: Duration,
+) -> Result<LookupResult, OuiError> {
+    let address = mac.canonical();
+    let url = if url.contains("{mac}") {
+        url.replace("{mac}", &address)
+    } else {
+        format!("{}{}", url, address)
+    };
+
+    /* Statuses are checked here, since a 404 is an answer rather than a failure */
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let mut response = agent
+        .get(&url)
+        .header("User-Agent", concat!("oui/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(|e| OuiError::Online(e.to_string()))?;
+
+    let status = response.status().as_u16();
+    if status == 404 {
+        return Ok(LookupResult::NotFound);
+    }
+    if !(200..300).contains(&status) {
+        return Err(OuiError::Online(format!(
+            "{} answered HTTP {}",
+            url, status
+        )));
+    }
+
+    let body = response
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| OuiError::Online(e.to_string()))?;
+    let vendor = match serde_json::from_str::<serde_json::Value>(&body) {
+        Ok(serde_json::Value::Object(object)) => ONLINE_VENDOR_FIELDS
+            .iter()
+            .find_map(|field| object.get(*field)?.as_str())
+            .map(str::to_string),
+        _ => Some(body.trim().to_string()),
+    };
+    Ok(match vendor.filter(|v| !v.is_empty()) {
+        Some(vendor) => LookupResult::Found(vendor),
+        None => LookupResult::NotFound,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    const HEADER: &str = "Registry,Assignment,Organization Name,Organization Address\n";
+
+    /* Answer one HTTP request with body, claiming length bytes, and return the URL */
+    fn serve_once(body: Vec<u8>, length: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/oui.csv", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request);
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                length
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(&body);
+        });
+        url
+    }
+
+    fn dir_entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn download_converts_the_ieee_export() {
+        let body = format!("{}MA-L,00000C,\"Cisco Systems, Inc\",San Jose\n", HEADER).into_bytes();
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("IEEE_OUI.csv");
+
+        let length = body.len();
+        assert_eq!(
+            download_database(&serve_once(body, length), &dest).unwrap(),
+            1
+        );
+        assert_eq!(
+            fs::read_to_string(&dest).unwrap(),
+            "00000C;Cisco Systems, Inc\n"
+        );
+        assert_eq!(dir_entries(dir.path()), ["IEEE_OUI.csv"]);
+    }
+
+    #[test]
+    fn bad_row_removes_the_partial_file() {
+        let mut body = format!("{}MA-L,00000C,Cisco,San Jose\nMA-L,001122,", HEADER).into_bytes();
+        body.extend_from_slice(b"\xff\xfe,Nowhere\n");
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("IEEE_OUI.csv");
+        fs::write(&dest, "old").unwrap();
+
+        let length = body.len();
+        assert!(download_database(&serve_once(body, length), &dest).is_err());
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "old");
+        assert_eq!(dir_entries(dir.path()), ["IEEE_OUI.csv"]);
+    }
+
+    #[test]
+    fn dropped_connection_removes_the_partial_file() {
+        let body = format!("{}MA-L,00000C,Cisco,San Jose\n", HEADER).into_bytes();
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("IEEE_OUI.csv");
+
+        let length = body.len() + 1000;
+        assert!(download_database(&serve_once(body, length), &dest).is_err());
+        assert!(dir_entries(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn empty_download_keeps_the_old_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("IEEE_OUI.csv");
+        fs::write(&dest, "old").unwrap();
+
+        let body = HEADER.as_bytes().to_vec();
+        let length = body.len();
+        let error = download_database(&serve_once(body, length), &dest).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Failed to download database: no records found at ")
+        );
+        assert_eq!(dir_entries(dir.path()), ["IEEE_OUI.csv"]);
+    }
+}